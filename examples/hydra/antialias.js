@@ -0,0 +1,10 @@
+// Supersampled hard edges: a kaleidoscope of thresholded stripes at 8 samples per pixel, over
+// posterized voronoi cells at the default 4
+osc(40, 0.05)
+  .thresh(0.5)
+  .kaleid(7)
+  .rotate(0, 0.2)
+  .supersample(8)
+  .mult(shape(5, 0.5, 0.01))
+  .add(voronoi(8, 0.3).posterize(3).supersample(), 0.4)
+  .out(o0)
@@ -0,0 +1,10 @@
+// Photoshop-style blend modes; the second argument is the amount, 1 by default
+osc(8, 0.1, 1.2)
+  .screen(shape(4, 0.4).scrollX(0, 0.1), 0.6)
+  .overlay(gradient(0.5))
+  .softLight(noise(3, 0.2), 0.8)
+  .colorDodge(voronoi(6, 0.3).color(0.3, 0.2, 0.5), 0.5)
+  .hue(osc(3, 0.05, 2).rotate(0.5))
+  .exclusion(shape(3, 0.3).repeat(2, 2), 0.4)
+  .luminosity(gradient(0.2), 0.3)
+  .out()
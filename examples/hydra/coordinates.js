@@ -0,0 +1,9 @@
+// Coordinate transforms: brick-tiled shapes in polar space, a sheared and mirrored oscillator,
+// and a voronoi pattern through a general affine matrix
+shape(4, 0.35)
+  .tile(6, 4, 0.5)
+  .polar()
+  .rotate(0, 0.1)
+  .add(osc(20, 0.1, 1).skew(0.4, 0).mirror(0), 0.5)
+  .diff(voronoi(6, 0.2).transform(1.2, 0.3, -0.2, 0.8, 0.1, 0).cartesian().mirror(2))
+  .out(o0)
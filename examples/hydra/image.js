@@ -0,0 +1,6 @@
+// External image source: a test card warped by an oscillator
+s0.initImage("../media/test_card.png")
+src(s0)
+  .modulate(osc(6, 0.1), 0.05)
+  .scale(1.2)
+  .out(o0)
@@ -0,0 +1,7 @@
+// Multi-pass effects: a blurred oscillator square, shape outlines and a glow over both
+osc(30, 0.05, 1.2)
+  .mult(shape(4, 0.5))
+  .blur(3)
+  .add(shape(3, 0.3).rotate(0, 0.3).edges(), 0.8)
+  .bloom(0.6, 1)
+  .out(o0)
@@ -0,0 +1,5 @@
+// Temporal effects: a triangle leaving trails, echoes of a dot and a motion-blurred stripe
+shape(3, 0.15, 0.01).scrollX(0, 0.4).trails(0.92)
+  .add(shape(40, 0.08).scrollY(0, -0.3).color(1, 0.4, 0.1).echo(5, 4))
+  .add(osc(20, 0.5).mask(shape(4, 0.3)).motionBlur(6), 0.5)
+  .out(o0)
@@ -0,0 +1,7 @@
+// External video source (Y4M, looped) mixed with a still
+s0.initImage("../media/test_card.png")
+s1.initVideo("../media/sweep.y4m")
+src(s1)
+  .kaleid(4)
+  .blend(src(s0).scale(0.5), 0.3)
+  .out(o0)
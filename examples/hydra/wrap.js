@@ -0,0 +1,3 @@
+// Wrap modes: mirrored horizontally, transparent outside vertically
+s0.initImage("../media/test_card.png", { wrapS: "mirror", wrapT: "border" })
+src(s0).scale(0.4).rotate(0.2).out(o0)
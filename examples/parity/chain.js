@@ -0,0 +1 @@
+osc(8, 0.1, 1.5).add(voronoi(5), 0.5).saturate(2).posterize(4, 0.6).out()
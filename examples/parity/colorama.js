@@ -0,0 +1 @@
+osc(10, 0.1, 1.2).colorama(0.4).out()
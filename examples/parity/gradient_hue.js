@@ -0,0 +1 @@
+gradient().hue(0.25).out()
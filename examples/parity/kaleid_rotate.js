@@ -0,0 +1 @@
+osc(30, 0.05).kaleid(5).rotate(0.3, 0.1).out()
@@ -0,0 +1 @@
+osc(10, 0.1).modulate(noise(3), 0.3).out()
@@ -0,0 +1 @@
+noise(3, 0.1).out()
@@ -0,0 +1 @@
+osc(20, 0.1, 0.8).out()
@@ -0,0 +1,315 @@
+// CPU port of the hydra-synth functions used by the parity corpus, for writing reference renders
+// without a browser. Every function follows its GLSL in hydra-synth's src/glsl/glsl-functions.js
+// (v1.3) operation for operation, in 32-bit floats, and a sketch is evaluated the way Hydra
+// compiles it: coordinate functions apply in reverse chain order, then the source, then colour
+// functions in order.
+//
+// Usage: node examples/parity/reference.mjs [sketch.js ...]
+// Writes sketch.ppm next to each sketch (all of examples/parity by default), top row first, with
+// Hydra's y = 0 at the bottom of the image.
+
+import fs from 'node:fs';
+import path from 'node:path';
+import { fileURLToPath } from 'node:url';
+
+const SIZE = 256;
+const DEFAULT_TIME = 1.0;
+
+const f = Math.fround;
+const fract = (x) => f(x - Math.floor(x));
+const mod = (x, y) => f(x - f(y * Math.floor(f(x / y))));
+const mix = (a, b, t) => f(f(a * f(1 - t)) + f(b * t));
+const clamp = (x, lo, hi) => Math.min(Math.max(x, lo), hi);
+const step = (edge, x) => (x < edge ? 0 : 1);
+const smoothstep = (e0, e1, x) => {
+  const t = clamp(f(f(x - e0) / f(e1 - e0)), 0, 1);
+  return f(f(t * t) * f(3 - f(2 * t)));
+};
+const dot3 = (a, b) => f(f(f(a[0] * b[0]) + f(a[1] * b[1])) + f(a[2] * b[2]));
+
+let time = 0;
+
+// ---- _noise: 3D simplex noise (Ian McEwan, Ashima Arts)
+
+const permute = (x) => x.map((v) => mod(f(f(f(v * 34) + 1) * v), 289));
+const taylorInvSqrt = (r) => r.map((v) => f(1.79284291400159 - f(0.85373472095314 * v)));
+
+function snoise(v) {
+  const C = [f(1 / 6), f(1 / 3)];
+  const s = dot3(v, [C[1], C[1], C[1]]);
+  const i = v.map((c) => Math.floor(f(c + s)));
+  const t = dot3(i, [C[0], C[0], C[0]]);
+  const x0 = v.map((c, k) => f(f(c - i[k]) + t));
+  const g = [step(x0[1], x0[0]), step(x0[2], x0[1]), step(x0[0], x0[2])];
+  const l = g.map((c) => 1 - c);
+  const lzxy = [l[2], l[0], l[1]];
+  const i1 = g.map((c, k) => Math.min(c, lzxy[k]));
+  const i2 = g.map((c, k) => Math.max(c, lzxy[k]));
+  const x1 = x0.map((c, k) => f(f(c - i1[k]) + C[0]));
+  const x2 = x0.map((c, k) => f(f(c - i2[k]) + f(2 * C[0])));
+  const x3 = x0.map((c) => f(f(c - 1) + f(3 * C[0])));
+  const im = i.map((c) => mod(c, 289));
+  const axis = (k) => [0, i1[k], i2[k], 1];
+  let p = permute(axis(2).map((c) => f(im[2] + c)));
+  p = permute(p.map((c, n) => f(f(c + im[1]) + axis(1)[n])));
+  p = permute(p.map((c, n) => f(f(c + im[0]) + axis(0)[n])));
+  const n_ = f(1 / 7);
+  const ns = [f(f(n_ * 2) - 0), f(f(n_ * 0.5) - 1), f(f(n_ * 1) - 0)];
+  const j = p.map((c) => f(c - f(49 * Math.floor(f(f(c * ns[2]) * ns[2])))));
+  const x_ = j.map((c) => Math.floor(f(c * ns[2])));
+  const y_ = j.map((c, k) => Math.floor(f(c - f(7 * x_[k]))));
+  const x = x_.map((c) => f(f(c * ns[0]) + ns[1]));
+  const y = y_.map((c) => f(f(c * ns[0]) + ns[1]));
+  const h = x.map((c, k) => f(f(1 - Math.abs(c)) - Math.abs(y[k])));
+  const b0 = [x[0], x[1], y[0], y[1]];
+  const b1 = [x[2], x[3], y[2], y[3]];
+  const s0 = b0.map((c) => f(Math.floor(c) * 2 + 1));
+  const s1 = b1.map((c) => f(Math.floor(c) * 2 + 1));
+  const sh = h.map((c) => -step(c, 0));
+  const a0 = [f(b0[0] + f(s0[0] * sh[0])), f(b0[2] + f(s0[2] * sh[0])), f(b0[1] + f(s0[1] * sh[1])), f(b0[3] + f(s0[3] * sh[1]))];
+  const a1 = [f(b1[0] + f(s1[0] * sh[2])), f(b1[2] + f(s1[2] * sh[2])), f(b1[1] + f(s1[1] * sh[3])), f(b1[3] + f(s1[3] * sh[3]))];
+  let p0 = [a0[0], a0[1], h[0]];
+  let p1 = [a0[2], a0[3], h[1]];
+  let p2 = [a1[0], a1[1], h[2]];
+  let p3 = [a1[2], a1[3], h[3]];
+  const norm = taylorInvSqrt([dot3(p0, p0), dot3(p1, p1), dot3(p2, p2), dot3(p3, p3)]);
+  p0 = p0.map((c) => f(c * norm[0]));
+  p1 = p1.map((c) => f(c * norm[1]));
+  p2 = p2.map((c) => f(c * norm[2]));
+  p3 = p3.map((c) => f(c * norm[3]));
+  let m = [x0, x1, x2, x3].map((xk) => Math.max(f(0.6 - dot3(xk, xk)), 0));
+  m = m.map((c) => f(c * c));
+  const d = [dot3(p0, x0), dot3(p1, x1), dot3(p2, x2), dot3(p3, x3)];
+  let sum = 0;
+  for (let k = 0; k < 4; k++) sum = f(sum + f(f(m[k] * m[k]) * d[k]));
+  return f(42 * sum);
+}
+
+// ---- HSV helpers
+
+function rgbToHsv(c) {
+  const K = [0, f(-1 / 3), f(2 / 3), -1];
+  const sp = step(c[2], c[1]);
+  const p = [mix(c[2], c[1], sp), mix(c[1], c[2], sp), mix(K[3], K[0], sp), mix(K[2], K[1], sp)];
+  const sq = step(p[0], c[0]);
+  const q = [mix(p[0], c[0], sq), mix(p[1], p[1], sq), mix(p[3], p[2], sq), mix(c[0], p[0], sq)];
+  const d = f(q[0] - Math.min(q[3], q[1]));
+  const e = 1.0e-10;
+  return [
+    Math.abs(f(q[2] + f(f(q[3] - q[1]) / f(f(6 * d) + e)))),
+    f(d / f(q[0] + e)),
+    q[0],
+  ];
+}
+
+function hsvToRgb(c) {
+  const K = [1, f(2 / 3), f(1 / 3), 3];
+  const p = [K[0], K[1], K[2]].map((k) => Math.abs(f(f(fract(f(c[0] + k)) * 6) - K[3])));
+  return p.map((v) => f(c[2] * mix(K[0], clamp(f(v - K[0]), 0, 1), c[1])));
+}
+
+// ---- Sources: (st) => rgba
+
+const sources = {
+  osc(frequency = 60, sync = 0.1, offset = 0) {
+    return ([x]) => {
+      const t = f(time * sync);
+      const wave = (shift) => f(f(Math.sin(f(f(f(x + shift) + t) * frequency)) * 0.5) + 0.5);
+      const o = f(offset / frequency);
+      return [wave(-o), wave(0), wave(o), 1];
+    };
+  },
+  noise(scale = 10, offset = 0.1) {
+    return ([x, y]) => {
+      const n = snoise([f(x * scale), f(y * scale), f(offset * time)]);
+      return [n, n, n, 1];
+    };
+  },
+  voronoi(scale = 5, speed = 0.3, blending = 0.3) {
+    return ([x, y]) => {
+      const st = [f(x * scale), f(y * scale)];
+      const ist = st.map(Math.floor);
+      const fst = st.map(fract);
+      let mDist = 10;
+      let mPoint = [0, 0];
+      for (let j = -1; j <= 1; j++) {
+        for (let i = -1; i <= 1; i++) {
+          const p = [ist[0] + i, ist[1] + j];
+          let point = [
+            fract(f(f(Math.sin(f(f(p[0] * 127.1) + f(p[1] * 311.7)))) * 43758.5453)),
+            fract(f(f(Math.sin(f(f(p[0] * 269.5) + f(p[1] * 183.3)))) * 43758.5453)),
+          ];
+          point = point.map((c) => f(0.5 + f(0.5 * f(Math.sin(f(f(time * speed) + f(6.2831 * c)))))));
+          const diff = [f(f(i + point[0]) - fst[0]), f(f(j + point[1]) - fst[1])];
+          const dist = f(Math.hypot(diff[0], diff[1]));
+          if (dist < mDist) {
+            mDist = dist;
+            mPoint = point;
+          }
+        }
+      }
+      let c = f(f(mPoint[0] * 0.3) + f(mPoint[1] * 0.6));
+      c = f(c * f(1 - f(blending * mDist)));
+      return [c, c, c, 1];
+    };
+  },
+  shape(sides = 3, radius = 0.3, smoothing = 0.01) {
+    return ([x, y]) => {
+      const st = [f(f(x * 2) - 1), f(f(y * 2) - 1)];
+      const a = f(Math.atan2(st[0], st[1]) + 3.1416);
+      const r = f(f(2 * 3.1416) / sides);
+      const d = f(f(Math.cos(f(f(Math.floor(f(0.5 + f(a / r))) * r) - a))) * f(Math.hypot(st[0], st[1])));
+      const c = f(1 - smoothstep(radius, f(f(radius + smoothing) + 0.0000001), d));
+      return [c, c, c, 1];
+    };
+  },
+  gradient(speed = 0) {
+    return ([x, y]) => [x, y, f(Math.sin(f(time * speed))), 1];
+  },
+};
+
+// ---- Coordinate functions: (st) => st
+
+const coords = {
+  rotate(angle = 10, speed = 0) {
+    return ([x, y]) => {
+      const xy = [f(x - 0.5), f(y - 0.5)];
+      const ang = f(angle + f(speed * time));
+      const c = f(Math.cos(ang));
+      const s = f(Math.sin(ang));
+      // mat2(cos, -sin, sin, cos) * xy, column-major
+      return [f(f(f(c * xy[0]) + f(s * xy[1])) + 0.5), f(f(f(-s * xy[0]) + f(c * xy[1])) + 0.5)];
+    };
+  },
+  kaleid(nSides = 4) {
+    return ([x, y]) => {
+      const st = [f(x - 0.5), f(y - 0.5)];
+      const r = f(Math.hypot(st[0], st[1]));
+      let a = f(Math.atan2(st[1], st[0]));
+      const pi = f(2 * 3.1416);
+      a = mod(a, f(pi / nSides));
+      a = Math.abs(f(a - f(f(pi / nSides) / 2)));
+      return [f(r * f(Math.cos(a))), f(r * f(Math.sin(a)))];
+    };
+  },
+};
+
+// ---- Colour functions: (rgba) => rgba
+
+const colors = {
+  saturate(amount = 2) {
+    return (c) => {
+      const intensity = dot3(c, [0.2125, 0.7154, 0.0721]);
+      return [mix(intensity, c[0], amount), mix(intensity, c[1], amount), mix(intensity, c[2], amount), c[3]];
+    };
+  },
+  posterize(bins = 3, gamma = 0.6) {
+    return (c) => {
+      const post = (v) => {
+        let c2 = f(Math.pow(v, gamma));
+        c2 = f(Math.floor(f(c2 * bins)) / bins);
+        return f(Math.pow(c2, f(1 / gamma)));
+      };
+      return [post(c[0]), post(c[1]), post(c[2]), c[3]];
+    };
+  },
+  hue(hue = 0.4) {
+    return (c) => {
+      const hsv = rgbToHsv(c);
+      hsv[0] = f(hsv[0] + hue);
+      return [...hsvToRgb(hsv), c[3]];
+    };
+  },
+  colorama(amount = 0.005) {
+    return (c) => {
+      const hsv = rgbToHsv(c).map((v) => f(v + amount));
+      return [...hsvToRgb(hsv).map(fract), c[3]];
+    };
+  },
+};
+
+// ---- Combining functions: (rgba, rgba) => rgba
+
+const combines = {
+  add(amount = 1) {
+    return (c0, c1) => c0.map((v, k) => f(f(f(v + c1[k]) * amount) + f(v * f(1 - amount))));
+  },
+};
+
+// A chain is the function from st to colour, built up as Hydra composes its GLSL
+class Chain {
+  constructor(eval_) {
+    this.eval = eval_;
+  }
+
+  out() {
+    rendered = this;
+  }
+
+  modulate(other, amount = 0.1) {
+    return new Chain((st) => {
+      const c = other.eval(st);
+      return this.eval([f(st[0] + f(c[0] * amount)), f(st[1] + f(c[1] * amount))]);
+    });
+  }
+}
+
+for (const [name, fn] of Object.entries(coords)) {
+  Chain.prototype[name] = function (...args) {
+    const transform = fn(...args);
+    return new Chain((st) => this.eval(transform(st)));
+  };
+}
+for (const [name, fn] of Object.entries(colors)) {
+  Chain.prototype[name] = function (...args) {
+    const color = fn(...args);
+    return new Chain((st) => color(this.eval(st)));
+  };
+}
+for (const [name, fn] of Object.entries(combines)) {
+  Chain.prototype[name] = function (other, ...args) {
+    const combine = fn(...args);
+    return new Chain((st) => combine(this.eval(st), other.eval(st)));
+  };
+}
+
+let rendered = null;
+
+// Same `// parity: time=2.5` line as tests/parity.rs reads
+function sketchTime(source) {
+  const match = source.match(/\/\/ parity:.*\btime=([0-9.]+)/);
+  return match ? parseFloat(match[1]) : DEFAULT_TIME;
+}
+
+function render(sketchPath) {
+  const source = fs.readFileSync(sketchPath, 'utf8');
+  time = sketchTime(source);
+  rendered = null;
+  const names = Object.keys(sources);
+  new Function(...names, source)(...names.map((name) => (...args) => new Chain(sources[name](...args))));
+  if (!rendered) throw new Error(`${sketchPath}: no .out() call`);
+
+  const header = Buffer.from(`P6\n${SIZE} ${SIZE}\n255\n`);
+  const pixels = Buffer.alloc(SIZE * SIZE * 3);
+  for (let row = 0; row < SIZE; row++) {
+    // gl_FragCoord at pixel centres, y up
+    const y = f(f(SIZE - row - 0.5) / SIZE);
+    for (let col = 0; col < SIZE; col++) {
+      const x = f(f(col + 0.5) / SIZE);
+      const c = rendered.eval([x, y]);
+      for (let k = 0; k < 3; k++) {
+        pixels[(row * SIZE + col) * 3 + k] = Math.round(clamp(c[k], 0, 1) * 255);
+      }
+    }
+  }
+  const out = sketchPath.replace(/\.js$/, '.ppm');
+  fs.writeFileSync(out, Buffer.concat([header, pixels]));
+  console.log(`wrote ${out} (t=${time})`);
+}
+
+let sketches = process.argv.slice(2);
+if (sketches.length === 0) {
+  const dir = path.dirname(fileURLToPath(import.meta.url));
+  sketches = fs.readdirSync(dir).filter((n) => n.endsWith('.js')).sort().map((n) => path.join(dir, n));
+}
+sketches.forEach(render);
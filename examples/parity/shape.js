@@ -0,0 +1 @@
+shape(5, 0.4, 0.02).out()
@@ -0,0 +1,6 @@
+// parity: time=2.5 threshold=6
+// The cell hash, fract(sin(x) * 43758.5453) with x in the thousands, magnifies the GPU's sin error
+// 40000 times. Vulkan only bounds sin to 2^-11, and an error of 5e-7 already changes about 8% of the
+// reference's pixels, so which cells differ depends on the driver (hydra-synth's own renders differ
+// between GPUs the same way). llvmpipe lands at a mean dE of 5.2.
+voronoi(8, 0.3, 0.3).out()
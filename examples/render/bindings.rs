@@ -0,0 +1,249 @@
+use live_lang::backend::spirv_reflect::{ShaderInterface, TextureBinding, TextureDimension};
+use live_lang::external::{self, ExternalSource, Wrap};
+use live_lang::passes;
+
+use super::media::Frame;
+
+// Bind group layouts and bind groups built from the reflected shader interface:
+// one uniform buffer per block, a texture per texture binding (1x1 transparent until
+// `upload_texture`, `bind_pass` or `bind_history`) and a linear sampler per sampler (repeating
+// unless `bind_source`, `bind_pass` or `bind_history` says otherwise).
+pub struct Bindings {
+    pub layouts: Vec<wgpu::BindGroupLayout>,
+    pub groups: Vec<wgpu::BindGroup>,
+    interface: ShaderInterface,
+    // Parallel to interface.uniform_buffers
+    buffers: Vec<wgpu::Buffer>,
+    // Parallel to interface.textures
+    textures: Vec<wgpu::Texture>,
+    // Parallel to interface.samplers
+    samplers: Vec<(wgpu::Sampler, [Wrap; 2])>,
+}
+
+fn create_texture(device: &wgpu::Device, name: &str, width: u32, height: u32, layers: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(name),
+        size: wgpu::Extent3d { width, height, depth_or_array_layers: layers },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        // Texels are sampled as stored, like Hydra's WebGL textures
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+// Border is clamped here and zeroed by the shader, so no clamp-to-border device feature is needed
+fn create_sampler(device: &wgpu::Device, wrap: [Wrap; 2]) -> wgpu::Sampler {
+    let mode = |w: Wrap| match w {
+        Wrap::Repeat => wgpu::AddressMode::Repeat,
+        Wrap::Clamp | Wrap::Border => wgpu::AddressMode::ClampToEdge,
+        Wrap::Mirror => wgpu::AddressMode::MirrorRepeat,
+    };
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: mode(wrap[0]),
+        address_mode_v: mode(wrap[1]),
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    })
+}
+
+// A one-layer array would otherwise get a plain 2D view
+fn view_dimension(texture: &TextureBinding) -> wgpu::TextureViewDimension {
+    if texture.arrayed { wgpu::TextureViewDimension::D2Array } else { wgpu::TextureViewDimension::D2 }
+}
+
+impl Bindings {
+    pub fn new(device: &wgpu::Device, interface: &ShaderInterface) -> Bindings {
+        let buffers: Vec<wgpu::Buffer> = interface.uniform_buffers.iter()
+            .map(|block| device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(&block.name),
+                size: block.size as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }))
+            .collect();
+        // Two layers for arrays, which the GL backend would otherwise view as plain 2D
+        let textures = interface.textures.iter().map(|t| create_texture(device, &t.name, 1, 1, if t.arrayed { 2 } else { 1 })).collect();
+        let samplers = interface.samplers.iter().map(|_| (create_sampler(device, [Wrap::Repeat; 2]), [Wrap::Repeat; 2])).collect();
+
+        let set_count = interface.uniform_buffers.iter().map(|b| b.set)
+            .chain(interface.textures.iter().map(|t| t.set))
+            .chain(interface.samplers.iter().map(|s| s.set))
+            .max()
+            .map_or(0, |max| max + 1);
+
+        let mut layouts = Vec::new();
+        for set in 0..set_count {
+            let mut layout_entries = Vec::new();
+            for block in interface.uniform_buffers.iter().filter(|b| b.set == set) {
+                layout_entries.push(wgpu::BindGroupLayoutEntry {
+                    binding: block.binding,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(block.size as u64),
+                    },
+                    count: None,
+                });
+            }
+            for texture in interface.textures.iter().filter(|t| t.set == set) {
+                assert!(!texture.combined, "texture '{}' is a combined image sampler, wgpu needs them separate", texture.name);
+                assert_eq!(texture.dimension, TextureDimension::D2, "texture '{}' is not 2D", texture.name);
+                layout_entries.push(wgpu::BindGroupLayoutEntry {
+                    binding: texture.binding,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: view_dimension(texture),
+                        multisampled: false,
+                    },
+                    count: None,
+                });
+            }
+            for s in interface.samplers.iter().filter(|s| s.set == set) {
+                layout_entries.push(wgpu::BindGroupLayoutEntry {
+                    binding: s.binding,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                });
+            }
+            layouts.push(device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("reflected bind group layout"),
+                entries: &layout_entries,
+            }));
+        }
+
+        let mut bindings = Bindings { layouts, groups: Vec::new(), interface: interface.clone(), buffers, textures, samplers };
+        bindings.create_groups(device);
+        bindings
+    }
+
+    // Groups reference the current textures, so they are rebuilt whenever one is replaced
+    fn create_groups(&mut self, device: &wgpu::Device) {
+        let views: Vec<wgpu::TextureView> = self.textures.iter().zip(&self.interface.textures)
+            .map(|(t, binding)| t.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(view_dimension(binding)),
+                ..Default::default()
+            }))
+            .collect();
+        self.groups = self.layouts.iter().enumerate().map(|(set, layout)| {
+            let set = set as u32;
+            let mut entries = Vec::new();
+            for (block, buffer) in self.interface.uniform_buffers.iter().zip(&self.buffers).filter(|(b, _)| b.set == set) {
+                entries.push(wgpu::BindGroupEntry { binding: block.binding, resource: buffer.as_entire_binding() });
+            }
+            for (texture, view) in self.interface.textures.iter().zip(&views).filter(|(t, _)| t.set == set) {
+                entries.push(wgpu::BindGroupEntry { binding: texture.binding, resource: wgpu::BindingResource::TextureView(view) });
+            }
+            for (s, (sampler, _)) in self.interface.samplers.iter().zip(&self.samplers).filter(|(s, _)| s.set == set) {
+                entries.push(wgpu::BindGroupEntry { binding: s.binding, resource: wgpu::BindingResource::Sampler(sampler) });
+            }
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("reflected bind group"),
+                layout,
+                entries: &entries,
+            })
+        }).collect();
+    }
+
+    // Uploads RGBA8 rows to the texture named `name` (e.g. `s0`), resizing it if needed;
+    // false if the shader has no such texture
+    pub fn upload_texture(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, name: &str, width: u32, height: u32, rgba: &[u8]) -> bool {
+        let Some(index) = self.interface.textures.iter().position(|t| t.name == name) else {
+            return false;
+        };
+        let size = wgpu::Extent3d { width, height, depth_or_array_layers: 1 };
+        if self.textures[index].size() != size {
+            self.textures[index] = create_texture(device, name, width, height, 1);
+            self.create_groups(device);
+        }
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.textures[index],
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            rgba,
+            wgpu::TexelCopyBufferLayout { offset: 0, bytes_per_row: Some(width * 4), rows_per_image: Some(height) },
+            size,
+        );
+        true
+    }
+
+    // Uploads an external source's frame to `s<slot>` and gives `s<slot>_sampler` its wrap modes
+    pub fn bind_source(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, source: &ExternalSource, frame: &Frame) {
+        self.upload_texture(device, queue, &external::texture_name(source.slot), frame.width, frame.height, &frame.rgba);
+        if self.set_sampler(device, &external::sampler_name(source.slot), source.wrap) {
+            self.create_groups(device);
+        }
+    }
+
+    // Binds a pass target (see live_lang::passes) to `pass<index>` with a clamp-to-edge sampler;
+    // false if the shader does not read that pass
+    pub fn bind_pass(&mut self, device: &wgpu::Device, index: u32, target: &wgpu::Texture) -> bool {
+        self.bind_target(device, &passes::texture_name(index), &passes::sampler_name(index), target)
+    }
+
+    // Binds the history ring of pass `index` to `pass<index>_history`, as bind_pass
+    pub fn bind_history(&mut self, device: &wgpu::Device, index: u32, ring: &wgpu::Texture) -> bool {
+        self.bind_target(device, &passes::history_texture_name(index), &passes::history_sampler_name(index), ring)
+    }
+
+    fn bind_target(&mut self, device: &wgpu::Device, name: &str, sampler: &str, texture: &wgpu::Texture) -> bool {
+        let Some(position) = self.interface.textures.iter().position(|t| t.name == name) else {
+            return false;
+        };
+        self.textures[position] = texture.clone();
+        self.set_sampler(device, sampler, [Wrap::Clamp; 2]);
+        self.create_groups(device);
+        true
+    }
+
+    // True when the sampler was replaced and the groups need rebuilding
+    fn set_sampler(&mut self, device: &wgpu::Device, name: &str, wrap: [Wrap; 2]) -> bool {
+        match self.interface.samplers.iter().position(|s| s.name == name) {
+            Some(index) if self.samplers[index].1 != wrap => {
+                self.samplers[index] = (create_sampler(device, wrap), wrap);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn layout_refs(&self) -> Vec<&wgpu::BindGroupLayout> {
+        self.layouts.iter().collect()
+    }
+
+    // Writes a whole uniform block, e.g. live_lang::globals::Globals; false if the shader has no such block
+    pub fn write_block(&self, queue: &wgpu::Queue, block: &str, data: &[u8]) -> bool {
+        let Some(index) = self.interface.uniform_buffers.iter().position(|b| b.name == block) else {
+            return false;
+        };
+        queue.write_buffer(&self.buffers[index], 0, data);
+        true
+    }
+
+    // Writes `data` at the reflected offset of `block.member`; false if the shader has no such member
+    pub fn write_member(&self, queue: &wgpu::Queue, block: &str, member: &str, data: &[u8]) -> bool {
+        let Some(index) = self.interface.uniform_buffers.iter().position(|b| b.name == block) else {
+            return false;
+        };
+        let Some(member) = self.interface.uniform_buffers[index].member(member) else {
+            return false;
+        };
+        queue.write_buffer(&self.buffers[index], member.offset as u64, data);
+        true
+    }
+
+    pub fn set_bind_groups(&self, renderpass: &mut wgpu::RenderPass) {
+        for (set, group) in self.groups.iter().enumerate() {
+            renderpass.set_bind_group(set as u32, group, &[]);
+        }
+    }
+}
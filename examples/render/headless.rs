@@ -0,0 +1,135 @@
+use std::fs;
+
+use live_lang::backend::spirv_reflect::ShaderInterface;
+use live_lang::globals::Globals;
+
+use live_lang::external::ExternalSource;
+
+use super::media::Media;
+use super::passes::{PassChain, Stage};
+
+// Offscreen renderer: draws a sketch's passes and fragment shader into an RGBA8 texture and reads
+// it back. Shares the vertex stage (wgsl/screen.wgsl) and passes::PassChain with window.rs.
+// Sketches with temporal effects are drawn for this many frames at 60 fps leading up to the
+// requested time, so their history holds earlier frames rather than transparent black
+const WARMUP_FRAMES: u32 = 32;
+const WARMUP_FPS: f32 = 60.0;
+
+pub struct Headless {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    vertex_module: wgpu::ShaderModule,
+    pub adapter_info: wgpu::AdapterInfo,
+}
+
+impl Headless {
+    // None when no adapter is available (e.g. CI without a GPU or software rasterizer)
+    pub async fn new() -> Option<Headless> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await
+            .ok()?;
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor::default())
+            .await
+            .ok()?;
+
+        let path = format!("{}/examples/wgsl/screen.wgsl", env!("CARGO_MANIFEST_DIR"));
+        let source = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read vertex shader ({}): {}", path, e));
+        let vertex_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("vertex"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        Some(Headless { device, queue, vertex_module, adapter_info: adapter.get_info() })
+    }
+
+    // Tightly packed RGBA8 rows, `width * height * 4` bytes
+    pub fn render(&self, fragment: wgpu::ShaderSource, interface: &ShaderInterface, width: u32, height: u32, time: f32) -> Vec<u8> {
+        self.render_with_sources(vec![Stage { fragment, interface, history: 0 }], &[], width, height, time)
+    }
+
+    // As render, for every stage of a sketch (passes first, see passes::spirv_stages) with the
+    // current frame of each external source bound (see media::load_sources)
+    pub fn render_with_sources(&self, stages: Vec<Stage>, sources: &[(ExternalSource, Media)], width: u32, height: u32, time: f32) -> Vec<u8> {
+        let device = &self.device;
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let mut chain = PassChain::new(device, &self.vertex_module, stages, format, width, height);
+        for (source, media) in sources {
+            chain.bind_source(device, &self.queue, source, &media.frame);
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("headless target"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+
+        // Rows are padded to 256 bytes for the copy
+        let row_bytes = width * 4;
+        let padded_row_bytes = row_bytes.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback buffer"),
+            size: (padded_row_bytes * height) as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut globals = Globals { time, ..Globals::new(width as f32, height as f32) };
+        if chain.history_frames() > 0 {
+            globals.time = time - (WARMUP_FRAMES + 1) as f32 / WARMUP_FPS;
+            for i in (1..=WARMUP_FRAMES).rev() {
+                globals.advance(time - i as f32 / WARMUP_FPS);
+                chain.write_globals(&self.queue, &globals);
+                let mut encoder = device.create_command_encoder(&Default::default());
+                chain.encode(&mut encoder, &view);
+                self.queue.submit([encoder.finish()]);
+            }
+            globals.advance(time);
+        }
+        chain.write_globals(&self.queue, &globals);
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        chain.encode(&mut encoder, &view);
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+        self.queue.submit([encoder.finish()]);
+
+        let slice = readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| result.expect("failed to map readback buffer"));
+        device.poll(wgpu::PollType::Wait).expect("failed to wait for readback");
+
+        let data = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity((row_bytes * height) as usize);
+        for row in data.chunks(padded_row_bytes as usize) {
+            pixels.extend_from_slice(&row[..row_bytes as usize]);
+        }
+        pixels
+    }
+}
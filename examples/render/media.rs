@@ -0,0 +1,248 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use live_lang::external::ExternalSource;
+
+use super::ppm;
+
+// Loader for the external sources a sketch declares with `s0.initImage(...)` / `s0.initVideo(...)`.
+// Stills: PNG, JPEG or PPM. Video: an uncompressed Y4M file (e.g. `ffmpeg -i clip.mp4 -pix_fmt yuv420p clip.y4m`)
+// or a directory of stills played as an image sequence. Video frames are read on demand and loop.
+
+// Frame rate of image sequences
+const SEQUENCE_FPS: f32 = 30.0;
+
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    // Tightly packed RGBA8 rows, top row first
+    pub rgba: Vec<u8>,
+}
+
+enum Source {
+    Still,
+    Y4m { file: BufReader<File>, header: Y4mHeader, offsets: Vec<u64> },
+    Sequence { paths: Vec<PathBuf>, fps: f32 },
+}
+
+// Every source a compiled sketch declares (CompiledShader::sources), see Bindings::bind_source
+pub fn load_sources(sources: &[ExternalSource], sketch: &Path) -> Result<Vec<(ExternalSource, Media)>, String> {
+    sources.iter().map(|s| Ok((s.clone(), Media::open_source(s, sketch)?))).collect()
+}
+
+pub struct Media {
+    source: Source,
+    index: usize,
+    // The frame at the last `advance` time, initially the first one
+    pub frame: Frame,
+}
+
+impl Media {
+    // `path` as written in the sketch, resolved against the sketch's directory
+    pub fn open_source(source: &ExternalSource, sketch: &Path) -> Result<Media, String> {
+        let dir = sketch.parent().unwrap_or(Path::new("."));
+        Media::open(&dir.join(&source.path))
+    }
+
+    pub fn open(path: &Path) -> Result<Media, String> {
+        if path.is_dir() {
+            let mut paths: Vec<PathBuf> = fs::read_dir(path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| is_still(p))
+                .collect();
+            paths.sort();
+            let first = paths.first().ok_or_else(|| format!("{}: no PNG, JPEG or PPM frames", path.display()))?;
+            let frame = read_still(first)?;
+            return Ok(Media { source: Source::Sequence { paths, fps: SEQUENCE_FPS }, index: 0, frame });
+        }
+        if extension(path) == "y4m" {
+            let mut file = BufReader::new(File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?);
+            let header = Y4mHeader::read(&mut file).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let offsets = header.frame_offsets(&mut file).map_err(|e| format!("{}: {}", path.display(), e))?;
+            if offsets.is_empty() { return Err(format!("{}: no frames", path.display())); }
+            let mut source = Source::Y4m { file, header, offsets };
+            let frame = read_frame(&mut source, 0).map_err(|e| format!("{}: {}", path.display(), e))?;
+            return Ok(Media { source, index: 0, frame });
+        }
+        Ok(Media { source: Source::Still, index: 0, frame: read_still(path)? })
+    }
+
+    // Moves to the frame shown `time` seconds in, looping; true when `frame` changed
+    pub fn advance(&mut self, time: f32) -> Result<bool, String> {
+        let (count, fps) = match &self.source {
+            Source::Still => return Ok(false),
+            Source::Y4m { header, offsets, .. } => (offsets.len(), header.fps),
+            Source::Sequence { paths, fps } => (paths.len(), *fps),
+        };
+        let index = ((time.max(0.0) * fps) as usize) % count;
+        if index == self.index { return Ok(false); }
+        self.frame = read_frame(&mut self.source, index)?;
+        self.index = index;
+        Ok(true)
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension().map(|e| e.to_string_lossy().to_ascii_lowercase()).unwrap_or_default()
+}
+
+fn is_still(path: &Path) -> bool {
+    matches!(extension(path).as_str(), "png" | "jpg" | "jpeg" | "ppm")
+}
+
+fn read_frame(source: &mut Source, index: usize) -> Result<Frame, String> {
+    match source {
+        Source::Still => unreachable!("stills have a single frame"),
+        Source::Y4m { file, header, offsets } => header.read_frame(file, offsets[index]).map_err(|e| e.to_string()),
+        Source::Sequence { paths, .. } => read_still(&paths[index]),
+    }
+}
+
+pub fn read_still(path: &Path) -> Result<Frame, String> {
+    let frame = match extension(path).as_str() {
+        "png" => read_png(path),
+        "jpg" | "jpeg" => read_jpeg(path),
+        "ppm" => ppm::Image::read(path).map(|image| Frame {
+            width: image.width,
+            height: image.height,
+            rgba: image.rgb.chunks(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        }),
+        other => Err(format!("unsupported image format '{}'", other)),
+    };
+    frame.map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn read_png(path: &Path) -> Result<Frame, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut decoder = png::Decoder::new(file);
+    // Palette and low bit depths expanded, 16-bit reduced to 8
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(|e| e.to_string())?;
+    let data = &data[..info.buffer_size()];
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => data.to_vec(),
+        png::ColorType::Rgb => data.chunks(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => data.chunks(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => data.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        png::ColorType::Indexed => return Err("palette was not expanded".into()),
+    };
+    Ok(Frame { width: info.width, height: info.height, rgba })
+}
+
+fn read_jpeg(path: &Path) -> Result<Frame, String> {
+    use zune_jpeg::zune_core::colorspace::ColorSpace;
+    use zune_jpeg::zune_core::options::DecoderOptions;
+
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+    let options = DecoderOptions::default().jpeg_set_out_colorspace(ColorSpace::RGBA);
+    let mut decoder = zune_jpeg::JpegDecoder::new_with_options(data.as_slice(), options);
+    let rgba = decoder.decode().map_err(|e| format!("{:?}", e))?;
+    let info = decoder.info().ok_or("missing JPEG header")?;
+    Ok(Frame { width: info.width as u32, height: info.height as u32, rgba })
+}
+
+// `YUV4MPEG2 W640 H360 F30:1 C420jpeg ...`, 8-bit 4:2:0, 4:2:2, 4:4:4 or mono
+struct Y4mHeader {
+    width: u32,
+    height: u32,
+    fps: f32,
+    // Chroma plane size as a divisor of the luma size; None for mono
+    chroma: Option<(u32, u32)>,
+}
+
+impl Y4mHeader {
+    fn read(file: &mut BufReader<File>) -> std::io::Result<Y4mHeader> {
+        let invalid = |m: String| std::io::Error::new(std::io::ErrorKind::InvalidData, m);
+        let mut line = String::new();
+        file.read_line(&mut line)?;
+        let mut fields = line.split_whitespace();
+        if fields.next() != Some("YUV4MPEG2") { return Err(invalid("not a Y4M file".into())); }
+        let mut header = Y4mHeader { width: 0, height: 0, fps: 25.0, chroma: Some((2, 2)) };
+        for field in fields {
+            // Tags are one ASCII letter; anything else is not a field we read
+            let Some((tag, value)) = field.split_at_checked(1) else { continue };
+            match tag {
+                "W" => header.width = value.parse().map_err(|_| invalid(format!("bad width '{}'", value)))?,
+                "H" => header.height = value.parse().map_err(|_| invalid(format!("bad height '{}'", value)))?,
+                "F" => {
+                    let (num, den) = value.split_once(':').unwrap_or((value, "1"));
+                    let (num, den): (f32, f32) = (num.parse().unwrap_or(0.0), den.parse().unwrap_or(0.0));
+                    if num > 0.0 && den > 0.0 { header.fps = num / den; }
+                }
+                "C" => header.chroma = match value {
+                    "420" | "420jpeg" | "420paldv" | "420mpeg2" => Some((2, 2)),
+                    "422" => Some((2, 1)),
+                    "444" => Some((1, 1)),
+                    "mono" => None,
+                    other => return Err(invalid(format!("unsupported colour space '{}' (8-bit 420, 422, 444 or mono only)", other))),
+                },
+                _ => {}
+            }
+        }
+        if header.width == 0 || header.height == 0 { return Err(invalid("missing frame size".into())); }
+        Ok(header)
+    }
+
+    fn chroma_size(&self) -> (u32, u32) {
+        self.chroma.map_or((0, 0), |(dx, dy)| (self.width.div_ceil(dx), self.height.div_ceil(dy)))
+    }
+
+    fn frame_bytes(&self) -> u64 {
+        let (cw, ch) = self.chroma_size();
+        self.width as u64 * self.height as u64 + 2 * cw as u64 * ch as u64
+    }
+
+    // Start of each frame's pixel data; `FRAME` lines may carry parameters, so they are scanned
+    fn frame_offsets(&self, file: &mut BufReader<File>) -> std::io::Result<Vec<u64>> {
+        let mut offsets = Vec::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if file.read_line(&mut line)? == 0 || !line.starts_with("FRAME") { break; }
+            let start = file.stream_position()?;
+            offsets.push(start);
+            file.seek(SeekFrom::Start(start + self.frame_bytes()))?;
+        }
+        // A truncated last frame is dropped
+        let end = file.seek(SeekFrom::End(0))?;
+        offsets.retain(|o| o + self.frame_bytes() <= end);
+        Ok(offsets)
+    }
+
+    // BT.601 limited range, the Y4M default
+    fn read_frame(&self, file: &mut BufReader<File>, offset: u64) -> std::io::Result<Frame> {
+        let mut data = vec![0; self.frame_bytes() as usize];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut data)?;
+        let (w, h) = (self.width as usize, self.height as usize);
+        let (cw, ch) = self.chroma_size();
+        let (y_plane, chroma) = data.split_at(w * h);
+        let (u_plane, v_plane) = chroma.split_at(cw as usize * ch as usize);
+
+        let mut rgba = Vec::with_capacity(w * h * 4);
+        for row in 0..h {
+            for col in 0..w {
+                let y = (y_plane[row * w + col] as f32 - 16.0) * 1.164;
+                let (u, v) = match self.chroma {
+                    Some((dx, dy)) => {
+                        let i = (row / dy as usize) * cw as usize + col / dx as usize;
+                        (u_plane[i] as f32 - 128.0, v_plane[i] as f32 - 128.0)
+                    }
+                    None => (0.0, 0.0),
+                };
+                let to_u8 = |c: f32| c.round().clamp(0.0, 255.0) as u8;
+                rgba.extend_from_slice(&[
+                    to_u8(y + 1.596 * v),
+                    to_u8(y - 0.392 * u - 0.813 * v),
+                    to_u8(y + 2.017 * u),
+                    255,
+                ]);
+            }
+        }
+        Ok(Frame { width: self.width, height: self.height, rgba })
+    }
+}
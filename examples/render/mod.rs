@@ -1,3 +1,9 @@
-pub mod window;
+// Shared by the examples; each one uses only part of it
+#![allow(dead_code)]
 
-use self::window::*;
+pub mod bindings;
+pub mod headless;
+pub mod media;
+pub mod passes;
+pub mod ppm;
+pub mod window;
@@ -0,0 +1,188 @@
+use live_lang::backend::spirv_reflect::ShaderInterface;
+use live_lang::compiler::CompiledShader;
+use live_lang::external::ExternalSource;
+use live_lang::globals::Globals;
+use live_lang::passes;
+
+use super::bindings::Bindings;
+use super::media::Frame;
+
+// Intermediate targets are float, so values above 1 survive from one pass to the next (bloom)
+pub const PASS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+// One fragment shader of a sketch with its reflected interface
+pub struct Stage<'a> {
+    pub fragment: wgpu::ShaderSource<'a>,
+    pub interface: &'a ShaderInterface,
+    // Frames of this pass kept for temporal effects (CompiledPass::history); 0 for the main shader
+    pub history: u32,
+}
+
+// The passes of a compiled sketch as SPIR-V, main shader last
+pub fn spirv_stages(shader: &CompiledShader) -> Vec<Stage<'_>> {
+    shader.passes.iter().map(|p| (&p.spirv, &p.interface, p.history))
+        .chain([(&shader.spirv, &shader.interface, 0)])
+        .map(|(spirv, interface, history)| Stage { fragment: wgpu::ShaderSource::SpirV(spirv.as_slice().into()), interface, history })
+        .collect()
+}
+
+// The intermediate passes of a sketch (CompiledShader::passes) followed by its main shader.
+// Pass i draws into its own target, bound as `pass<i>` in every later stage that reads it.
+// Passes with history also get a ring of past frames, bound as `pass<i>_history` in every stage
+// and written after each frame (see live_lang::passes).
+pub struct PassChain {
+    pipelines: Vec<wgpu::RenderPipeline>,
+    // Parallel to pipelines
+    bindings: Vec<Bindings>,
+    // One per pass, the size of the output
+    targets: Vec<wgpu::Texture>,
+    // Ring size per pass, and the ring itself for those that keep history
+    rings: Vec<u32>,
+    histories: Vec<Option<wgpu::Texture>>,
+    // Globals::frame of the frame being drawn, which picks the ring layer written
+    frame: u32,
+}
+
+fn create_pipeline(device: &wgpu::Device, vertex: &wgpu::ShaderModule, fragment: &wgpu::ShaderModule, bindings: &Bindings, format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("pipeline layout"),
+        bind_group_layouts: &bindings.layout_refs(),
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("render pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: vertex,
+            entry_point: Some("main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: fragment,
+            entry_point: Some("main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            cull_mode: None,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+impl PassChain {
+    // `stages` are the passes in order, then the main shader, which draws into `format`
+    pub fn new(device: &wgpu::Device, vertex: &wgpu::ShaderModule, stages: Vec<Stage>, format: wgpu::TextureFormat, width: u32, height: u32) -> PassChain {
+        let count = stages.len();
+        let rings = stages.iter().take(count.saturating_sub(1)).map(|s| s.history).collect();
+        let mut chain = PassChain { pipelines: Vec::new(), bindings: Vec::new(), targets: Vec::new(), rings, histories: Vec::new(), frame: 0 };
+        for (i, stage) in stages.into_iter().enumerate() {
+            let fragment = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("fragment"),
+                source: stage.fragment,
+            });
+            let bindings = Bindings::new(device, stage.interface);
+            let format = if i + 1 == count { format } else { PASS_FORMAT };
+            chain.pipelines.push(create_pipeline(device, vertex, &fragment, &bindings, format));
+            chain.bindings.push(bindings);
+        }
+        chain.resize(device, width, height);
+        chain
+    }
+
+    // Recreates the pass targets at the output size; history starts over as transparent black.
+    // Rings get at least two layers, as the GL backend views a one-layer texture as plain 2D.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        let texture = |label: &str, layers: u32, usage: wgpu::TextureUsages| device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d { width: width.max(1), height: height.max(1), depth_or_array_layers: layers },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: PASS_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | usage,
+            view_formats: &[],
+        });
+        self.targets = (0..self.rings.len() as u32)
+            .map(|i| texture(&passes::texture_name(i), 1, wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC))
+            .collect();
+        self.histories = self.rings.iter().enumerate()
+            .map(|(i, &ring)| (ring > 0).then(|| texture(&passes::history_texture_name(i as u32), ring.max(2), wgpu::TextureUsages::COPY_DST)))
+            .collect();
+        for bindings in &mut self.bindings {
+            for (i, target) in self.targets.iter().enumerate() {
+                bindings.bind_pass(device, i as u32, target);
+            }
+            for (i, ring) in self.histories.iter().enumerate() {
+                if let Some(ring) = ring { bindings.bind_history(device, i as u32, ring); }
+            }
+        }
+    }
+
+    // Longest history any pass keeps, in frames
+    pub fn history_frames(&self) -> u32 {
+        self.rings.iter().copied().max().unwrap_or(0)
+    }
+
+    pub fn bind_source(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, source: &ExternalSource, frame: &Frame) {
+        for bindings in &mut self.bindings {
+            bindings.bind_source(device, queue, source, frame);
+        }
+    }
+
+    pub fn write_globals(&mut self, queue: &wgpu::Queue, globals: &Globals) {
+        self.frame = globals.frame as u32;
+        for bindings in &self.bindings {
+            bindings.write_block(queue, "Globals", bytemuck::bytes_of(globals));
+        }
+    }
+
+    // Draws every pass into its target, then the main shader into `view`, then records history
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let target_views: Vec<wgpu::TextureView> = self.targets.iter().map(|t| t.create_view(&Default::default())).collect();
+        for (i, (pipeline, bindings)) in self.pipelines.iter().zip(&self.bindings).enumerate() {
+            let mut renderpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target_views.get(i).unwrap_or(view),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            renderpass.set_pipeline(pipeline);
+            bindings.set_bind_groups(&mut renderpass);
+            renderpass.draw(0..4, 0..1);
+        }
+        // This frame becomes layer `frame % ring` once every stage has read the older ones
+        for (i, ring) in self.histories.iter().enumerate() {
+            let Some(ring) = ring else { continue };
+            let layer = self.frame % self.rings[i];
+            encoder.copy_texture_to_texture(
+                self.targets[i].as_image_copy(),
+                wgpu::TexelCopyTextureInfo {
+                    texture: ring,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x: 0, y: 0, z: layer },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                self.targets[i].size(),
+            );
+        }
+    }
+}
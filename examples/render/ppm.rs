@@ -0,0 +1,65 @@
+use std::fs;
+use std::path::Path;
+
+// Binary PPM (P6, 8-bit RGB): the reference image format for tests/parity.rs.
+// No decoder dependency needed; convert canvas PNGs with e.g. `magick ref.png ref.ppm`.
+
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    // Tightly packed RGB rows, top row first
+    pub rgb: Vec<u8>,
+}
+
+impl Image {
+    // Drops alpha from the RGBA8 rows returned by Headless::render
+    pub fn from_rgba(width: u32, height: u32, rgba: &[u8]) -> Image {
+        let rgb = rgba.chunks(4).flat_map(|p| [p[0], p[1], p[2]]).collect();
+        Image { width, height, rgb }
+    }
+
+    // Rows in reverse order
+    pub fn flipped(&self) -> Image {
+        let rgb = self.rgb.chunks(self.width as usize * 3).rev().flatten().copied().collect();
+        Image { width: self.width, height: self.height, rgb }
+    }
+
+    pub fn read(path: &Path) -> Result<Image, String> {
+        let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        // Header: magic, width, height, maxval, separated by whitespace with optional # comments
+        let mut fields = Vec::new();
+        let mut pos = 0;
+        while fields.len() < 4 {
+            while pos < data.len() && data[pos].is_ascii_whitespace() { pos += 1; }
+            if data.get(pos) == Some(&b'#') {
+                while pos < data.len() && data[pos] != b'\n' { pos += 1; }
+                continue;
+            }
+            let start = pos;
+            while pos < data.len() && !data[pos].is_ascii_whitespace() { pos += 1; }
+            if start == pos { return Err(format!("{}: truncated PPM header", path.display())); }
+            fields.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+        }
+        // Exactly one whitespace byte before the pixels
+        pos += 1;
+        if fields[0] != "P6" {
+            return Err(format!("{}: not a binary PPM (P6)", path.display()));
+        }
+        let number = |s: &str| s.parse::<u32>().map_err(|_| format!("{}: bad PPM header field '{}'", path.display(), s));
+        let (width, height, maxval) = (number(&fields[1])?, number(&fields[2])?, number(&fields[3])?);
+        if maxval != 255 {
+            return Err(format!("{}: only 8-bit PPM is supported (maxval {})", path.display(), maxval));
+        }
+        let len = (width * height * 3) as usize;
+        let rgb = data.get(pos..pos + len)
+            .ok_or_else(|| format!("{}: truncated PPM data", path.display()))?
+            .to_vec();
+        Ok(Image { width, height, rgb })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let mut data = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        data.extend_from_slice(&self.rgb);
+        fs::write(path, data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
@@ -2,13 +2,19 @@ use std::fs;
 use std::sync::Arc;
 use std::time::Instant;
 
+use live_lang::backend::spirv_reflect::ShaderInterface;
+use live_lang::external::ExternalSource;
+use live_lang::globals::Globals;
 use winit::{
     application::ApplicationHandler,
-    event::WindowEvent,
+    event::{ElementState, MouseButton, WindowEvent},
     event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
     window::{Window, WindowId},
 };
 
+use super::media::Media;
+use super::passes::{PassChain, Stage};
+
 struct State {
     window: Arc<Window>,
     device: wgpu::Device,
@@ -16,15 +22,17 @@ struct State {
     size: winit::dpi::PhysicalSize<u32>,
     surface: wgpu::Surface<'static>,
     surface_format: wgpu::TextureFormat,
-    render_pipeline: wgpu::RenderPipeline,
-    // Combined globals uniform buffer: vec4(time, width, height, pad)
-    globals_buffer: wgpu::Buffer,
-    bind_group: wgpu::BindGroup,
+    alpha_mode: wgpu::CompositeAlphaMode,
+    // The sketch's passes and main shader, bindings built from the reflected interfaces
+    chain: PassChain,
+    // External sources with their loaded media
+    media: Vec<(ExternalSource, Media)>,
+    globals: Globals,
     start_instant: Instant,
 }
 
 impl State {
-    async fn new(window: Arc<Window>) -> State {
+    async fn new(window: Arc<Window>, stages: Vec<(String, ShaderInterface, u32)>, media: Vec<(ExternalSource, Media)>, transparent: bool, hdr: bool) -> State {
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions::default())
@@ -39,112 +47,45 @@ impl State {
 
         let surface = instance.create_surface(window.clone()).unwrap();
         let cap = surface.get_capabilities(&adapter);
-        let surface_format = cap.formats[0];
+        // An Rgba16Float surface is extended-range linear (scRGB) where the platform supports HDR
+        let surface_format = if !hdr {
+            cap.formats[0]
+        } else if cap.formats.contains(&wgpu::TextureFormat::Rgba16Float) {
+            wgpu::TextureFormat::Rgba16Float
+        } else {
+            eprintln!("surface has no Rgba16Float format (supports {:?}), highlights will clip", cap.formats);
+            cap.formats[0]
+        };
+        // The shader premultiplies when compiled with AlphaMode::Premultiplied
+        let alpha_mode = if !transparent {
+            wgpu::CompositeAlphaMode::Auto
+        } else if cap.alpha_modes.contains(&wgpu::CompositeAlphaMode::PreMultiplied) {
+            wgpu::CompositeAlphaMode::PreMultiplied
+        } else {
+            eprintln!("surface has no premultiplied alpha mode (supports {:?}), drawing opaque", cap.alpha_modes);
+            wgpu::CompositeAlphaMode::Auto
+        };
 
+        // The vertex stage is loaded as WGSL at runtime instead of embedding
+        let path = format!("{}/examples/wgsl/screen.wgsl", env!("CARGO_MANIFEST_DIR"));
+        let source = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read vertex shader ({}): {}", path, e));
         let vertex_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("vertex"),
-            source: wgpu::ShaderSource::Glsl {
-                // Load vertex shader source at runtime instead of embedding.
-                shader: std::borrow::Cow::Owned(
-                    fs::read_to_string(format!(
-                        "{}/examples/glsl/screen.vert",
-                        env!("CARGO_MANIFEST_DIR")
-                    ))
-                    .expect("Failed to read vertex shader (examples/glsl/screen.vert)"),
-                ),
-                stage: wgpu::naga::ShaderStage::Vertex,
-                defines: &[],
-            },
-        });
-        //
-        // let vertex_module = device.create_shader_module(wgpu::include_spirv!("spv/vert.spv"));
-
-        let fragment_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("fragment"),
-            source: wgpu::ShaderSource::Glsl {
-                // Load fragment shader source at runtime instead of embedding.
-                shader: std::borrow::Cow::Owned(
-                    fs::read_to_string(format!(
-                        "{}/examples/glsl/fragment.frag",
-                        env!("CARGO_MANIFEST_DIR")
-                    ))
-                    .expect("Failed to read fragment shader (examples/glsl/fragment.frag)"),
-                ),
-                stage: wgpu::naga::ShaderStage::Fragment,
-                defines: &[],
-            },
-        });
-
-        // Create single globals uniform buffer (16 bytes for vec4)
-        let globals_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("globals uniform buffer"),
-            size: 16,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-        // Initialize (time=0)
-        let initial_globals: [f32; 4] = [0.0, size.width as f32, size.height as f32, 0.0];
-        queue.write_buffer(&globals_buffer, 0, bytemuck::cast_slice(&initial_globals));
-
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("globals bind group layout"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }
-            ],
+            source: wgpu::ShaderSource::Wgsl(source.into()),
         });
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("globals bind group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry { binding: 0, resource: globals_buffer.as_entire_binding() }
-            ],
-        });
-
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("pipeline layout"),
-            bind_group_layouts: &[&bind_group_layout],
-            push_constant_ranges: &[],
-        });
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("render pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &vertex_module,
-                entry_point: Some("main"),
-                buffers: &[],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &fragment_module,
-                entry_point: Some("main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_format.add_srgb_suffix(),
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleStrip,
-                cull_mode: None,
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            cache: None,
-        });
+        let stages = stages.iter().map(|(wgsl, interface, history)| Stage {
+            fragment: wgpu::ShaderSource::Wgsl(wgsl.as_str().into()),
+            interface,
+            history: *history,
+        }).collect();
+        let mut chain = PassChain::new(&device, &vertex_module, stages, surface_format.add_srgb_suffix(), size.width, size.height);
+        for (source, m) in &media {
+            chain.bind_source(&device, &queue, source, &m.frame);
+        }
+        let globals = Globals::new(size.width as f32, size.height as f32);
+        chain.write_globals(&queue, &globals);
 
         let state = State {
             window,
@@ -153,9 +94,10 @@ impl State {
             size,
             surface,
             surface_format,
-            render_pipeline,
-            globals_buffer,
-            bind_group,
+            alpha_mode,
+            chain,
+            media,
+            globals,
             start_instant: Instant::now(),
         };
 
@@ -175,7 +117,7 @@ impl State {
             format: self.surface_format,
             // Request compatibility with the sRGB-format texture view we‘re going to create later.
             view_formats: vec![self.surface_format.add_srgb_suffix()],
-            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            alpha_mode: self.alpha_mode,
             width: self.size.width,
             height: self.size.height,
             desired_maximum_frame_latency: 2,
@@ -187,6 +129,7 @@ impl State {
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         self.size = new_size;
         self.configure_surface();
+        self.chain.resize(&self.device, new_size.width, new_size.height);
         // No immediate buffer write; will update globals in next render()
     }
 
@@ -203,32 +146,20 @@ impl State {
                 ..Default::default()
             });
 
-        // Update globals uniform (time, width, height, pad)
-        let elapsed = self.start_instant.elapsed().as_secs_f32();
-        let globals: [f32; 4] = [elapsed, self.size.width as f32, self.size.height as f32, 0.0];
-        self.queue.write_buffer(&self.globals_buffer, 0, bytemuck::cast_slice(&globals));
+        // Update globals uniform; mouse fields are kept current by window_event
+        self.globals.resolution = [self.size.width as f32, self.size.height as f32];
+        self.globals.advance(self.start_instant.elapsed().as_secs_f32());
+        self.chain.write_globals(&self.queue, &self.globals);
+        for (source, m) in &mut self.media {
+            match m.advance(self.globals.time) {
+                Ok(true) => self.chain.bind_source(&self.device, &self.queue, source, &m.frame),
+                Ok(false) => {}
+                Err(e) => eprintln!("{}: {}", source.path, e),
+            }
+        }
 
         let mut encoder = self.device.create_command_encoder(&Default::default());
-        {
-            let mut renderpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &texture_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-
-            renderpass.set_pipeline(&self.render_pipeline);
-            renderpass.set_bind_group(0, &self.bind_group, &[]);
-            renderpass.draw(0..4, 0..1);
-        }
+        self.chain.encode(&mut encoder, &texture_view);
 
         self.queue.submit([encoder.finish()]);
         self.window.pre_present_notify();
@@ -236,8 +167,12 @@ impl State {
     }
 }
 
-#[derive(Default)]
 struct App {
+    // Both moved into State on first resume
+    stages: Vec<(String, ShaderInterface, u32)>,
+    media: Vec<(ExternalSource, Media)>,
+    transparent: bool,
+    hdr: bool,
     state: Option<State>,
 }
 
@@ -246,11 +181,11 @@ impl ApplicationHandler for App {
         // Create window object
         let window = Arc::new(
             event_loop
-                .create_window(Window::default_attributes())
+                .create_window(Window::default_attributes().with_transparent(self.transparent))
                 .unwrap(),
         );
 
-        let state = pollster::block_on(State::new(window.clone()));
+        let state = pollster::block_on(State::new(window.clone(), std::mem::take(&mut self.stages), std::mem::take(&mut self.media), self.transparent, self.hdr));
         self.state = Some(state);
 
         window.request_redraw();
@@ -273,12 +208,32 @@ impl ApplicationHandler for App {
                 // here as this event is always followed up by redraw request.
                 state.resize(size);
             }
+            WindowEvent::CursorMoved { position, .. } => {
+                state.globals.mouse = [position.x as f32, position.y as f32];
+            }
+            WindowEvent::MouseInput { state: button_state, button, .. } => {
+                let bit = match button {
+                    MouseButton::Left => 1,
+                    MouseButton::Right => 2,
+                    MouseButton::Middle => 4,
+                    _ => 0,
+                };
+                let mut buttons = state.globals.mouse_buttons as u32;
+                if button_state == ElementState::Pressed { buttons |= bit } else { buttons &= !bit }
+                state.globals.mouse_buttons = buttons as f32;
+            }
             _ => (),
         }
     }
 }
 
-pub fn render_window() {
+// `stages` are the WGSL of each pass and then the main fragment shader, with their reflected
+// interfaces and history frames (CompiledShader::passes and ::interface); `media` are the loaded
+// external sources (media::load_sources). `transparent` asks for a window the desktop shows
+// through, for shaders compiled with AlphaMode::Premultiplied. The main shader draws through an
+// sRGB view, so it should be compiled with `output_space: ColorSpace::Linear`; `hdr` asks for an
+// Rgba16Float surface instead, for shaders compiled with OutputTransform::ScRgb.
+pub fn render_window(stages: Vec<(String, ShaderInterface, u32)>, media: Vec<(ExternalSource, Media)>, transparent: bool, hdr: bool) {
     // wgpu uses `log` for all of our logging, so we initialize a logger with the `env_logger` crate.
     //
     // To change the log level, set the `RUST_LOG` environment variable. See the `env_logger`
@@ -299,6 +254,6 @@ pub fn render_window() {
     // the background.
     // event_loop.set_control_flow(ControlFlow::Wait);
 
-    let mut app = App::default();
+    let mut app = App { stages, media, transparent, hdr, state: None };
     event_loop.run_app(&mut app).unwrap();
 }
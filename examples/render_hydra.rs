@@ -2,71 +2,100 @@ use std::fs::File;
 use std::io::Write;
 use std::fs;
 use std::env;
+use std::path::Path;
 
 mod render;
 
-// Frontend: JavaScript parser using SWC (src/frontend/hydra_ecma.rs)
-use live_lang::frontend::hydra_ecma::*;
-
-// Backend: SPIR-V emitter that converts Hydra AST to SPIR-V (src/backend/spirv_visitor.rs)
-use live_lang::backend::spirv_visitor::SpirvEmitter;
-
-// Backend: SPIR-V to GLSL cross-compiler using spirv-cross2 (src/backend/spirv_glsl.rs)
-use live_lang::backend::spirv_glsl::compile_to_glsl;
+// Compiler facade: parse (src/frontend), build IR (src/ir), emit SPIR-V and cross-compile (src/backend)
+use live_lang::compiler::{CompileOptions, Compiler};
+use live_lang::output::{AlphaMode, ColorSpace, OutputTransform, REFERENCE_WHITE};
+use live_lang::backend::spirv_wgsl::compile_to_wgsl;
 
 // Render: Window manager and wgpu renderer (example/render/window.rs)
 use crate::render::window::render_window;
+use crate::render::media::load_sources;
 
 
 // Default Hydra source file (can be overridden via first CLI argument)
 const DEFAULT_HYDRA_SOURCE: &str = "examples/hydra/sources_simple.js";
 
 fn main() {
-    // Resolve hydra source file (CLI arg overrides default)
-    let args: Vec<String> = env::args().collect();
-    let source_path = if args.len() > 1 { &args[1] } else { DEFAULT_HYDRA_SOURCE };
-
-    println!("Step 1: Building AST from hydra source...");
+    // Resolve hydra source file (CLI arg overrides default); `--transparent` premultiplies the
+    // output and draws into a transparent window, `--linear` works in linear light, `--hdr` keeps
+    // values above 1.0 and draws scRGB into an Rgba16Float surface
+    let args: Vec<String> = env::args().skip(1).collect();
+    let transparent = args.iter().any(|a| a == "--transparent");
+    let linear = args.iter().any(|a| a == "--linear");
+    let hdr = args.iter().any(|a| a == "--hdr");
+    let source_path = args.iter().find(|a| !a.starts_with("--")).map_or(DEFAULT_HYDRA_SOURCE, |a| a.as_str());
+
+    println!("Step 1: Reading hydra source...");
     println!("Using Hydra source file: {}", source_path);
 
     // Read the hydra source file
-    let source = fs::read_to_string(&source_path)
+    let source = fs::read_to_string(source_path)
         .unwrap_or_else(|e| panic!("Failed to read hydra source '{}': {}", source_path, e));
 
-    let ast = hydra_ecma(&source);
-    println!("AST built successfully!");
-
-    println!("\nStep 2: Compiling AST to SPIR-V...");
-    let emitter = SpirvEmitter::new();
-    let spirv_words = emitter.emit_pipeline(&ast);
+    println!("\nStep 2: Compiling to SPIR-V and GLSL...");
+    let alpha = if transparent { AlphaMode::Premultiplied } else { AlphaMode::Straight };
+    let color_space = if linear { ColorSpace::Linear } else { ColorSpace::Srgb };
+    // The window draws through an sRGB view, which encodes on write
+    let output_transform = if hdr { OutputTransform::ScRgb { paper_white: REFERENCE_WHITE } } else { OutputTransform::Sdr };
+    let options = CompileOptions { debug_info: true, alpha, color_space, output_space: ColorSpace::Linear, output_transform, ..Default::default() };
+    let shader = Compiler::new(options).compile_named(source_path, &source)
+        .unwrap_or_else(|e| panic!("{}: {}", source_path, e));
+    for d in &shader.diagnostics {
+        let (line, col) = d.line_col(&source);
+        println!("warning: {} at {}:{}:{}", d, source_path, line, col);
+    }
+    let spirv_words = &shader.spirv;
     println!("SPIR-V generated: {} words", spirv_words.len());
 
     // Ensure output directories exist
     fs::create_dir_all("examples/spv").ok();
     fs::create_dir_all("examples/glsl").ok();
+    fs::create_dir_all("examples/wgsl").ok();
 
     // Write SPIR-V binary for inspection
     let mut spv_file = File::create("examples/spv/fragment.spv").unwrap();
-    for word in &spirv_words { spv_file.write_all(&word.to_le_bytes()).unwrap(); }
+    for word in spirv_words { spv_file.write_all(&word.to_le_bytes()).unwrap(); }
     println!("SPIR-V binary saved to examples/spv/fragment.spv");
 
-    println!("\nStep 3: Compiling SPIR-V to GLSL...");
-    let glsl = compile_to_glsl(&spirv_words).expect("GLSL cross compile failed");
+    let glsl = shader.source.expect("GLSL target produces source");
 
     // Save GLSL to file
     let mut file = File::create("examples/glsl/fragment.frag").unwrap();
-    file.write_all(glsl.to_string().as_bytes()).unwrap();
+    file.write_all(glsl.as_bytes()).unwrap();
     println!("GLSL shader saved to examples/glsl/fragment.frag");
 
+    // The render window loads WGSL, translated from the same SPIR-V by naga
+    let wgsl = compile_to_wgsl(spirv_words).unwrap_or_else(|e| panic!("{}: {}", source_path, e));
+    fs::write("examples/wgsl/fragment.wgsl", &wgsl).unwrap();
+    println!("WGSL shader saved to examples/wgsl/fragment.wgsl");
+
     println!("\n{}", "=".repeat(60));
     println!("Generated GLSL Fragment Shader:");
     println!("{}", "=".repeat(60));
-    println!("{}", glsl.to_string());
+    println!("{}", glsl);
     println!("{}", "=".repeat(60));
 
-    println!("\nStep 4: Launching render window...");
+    // External sources (`s0.initImage(...)`), paths relative to the sketch
+    let media = load_sources(&shader.sources, Path::new(source_path)).unwrap_or_else(|e| panic!("{}", e));
+    for (source, m) in &media {
+        println!("Loaded {} into s{} ({}x{})", source.path, source.slot, m.frame.width, m.frame.height);
+    }
+
+    // Multi-pass sketches (blur, edges, bloom, temporal effects) draw their passes first
+    let mut stages = Vec::new();
+    for pass in &shader.passes {
+        let wgsl = compile_to_wgsl(&pass.spirv).unwrap_or_else(|e| panic!("{}: {}", source_path, e));
+        stages.push((wgsl, pass.interface.clone(), pass.history));
+    }
+    stages.push((wgsl, shader.interface, 0));
+
+    println!("\nStep 3: Launching render window...");
     println!("Close the window to exit.\n");
 
     // Run the render window
-    render_window();
+    render_window(stages, media, transparent, hdr);
 }
@@ -0,0 +1,13 @@
+// Full-screen rectangle (quad) vertex shader using a triangle strip.
+// WGSL counterpart of glsl/screen.vert; no vertex buffers required.
+
+@vertex
+fn main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    var positions = array<vec2<f32>, 4>(
+        vec2<f32>(-1.0, -1.0), // bottom-left
+        vec2<f32>( 1.0, -1.0), // bottom-right
+        vec2<f32>(-1.0,  1.0), // top-left
+        vec2<f32>( 1.0,  1.0), // top-right
+    );
+    return vec4<f32>(positions[index], 0.0, 1.0);
+}
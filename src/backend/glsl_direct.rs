@@ -0,0 +1,868 @@
+// Direct IR -> GLSL backend for Target::GlslDirect: no SPIR-V is emitted, and the interface comes
+// from spirv_reflect::interface_from_ir, so the spirv-cross feature is not needed.
+// Modelled on Hydra's glsl-functions.js (one GLSL function per Hydra function, composed in main);
+// the math mirrors the SPIR-V emitters in hydra_sources/hydra_effects/spirv_visitor so both
+// backends render the same image (see tests/cross_check.rs).
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use crate::backend::spirv_context::Aspect;
+use crate::backend::spirv_filter::{blur_weights, supersample_count, supersample_offsets, BLUR_TAPS, SOBEL};
+use crate::error::CompileError;
+use crate::external::{self, Wrap};
+use crate::ir::*;
+use crate::passes;
+use crate::output::{AlphaMode, ColorSpace, OutputTransform};
+use crate::post_process::{PostProcess, ToneMap};
+
+const HEADER: &str = "#version 460
+
+layout(binding = 0, std140) uniform Globals
+{
+    float time;
+    float frame;
+    vec2 resolution;
+    vec2 mouse;
+    float mouseButtons;
+    float dt;
+    float fps;
+    float bpm;
+    float beat;
+    vec4 custom[2];
+} globals;
+
+layout(location = 0) out vec4 fragColor;
+
+float time;
+";
+
+const HELPERS: &str = "
+float luma(vec4 c) { return c.r * 0.299 + c.g * 0.587 + c.b * 0.114; }
+
+// Hydra's _rgbToHsv / _hsvToRgb
+vec3 _rgbToHsv(vec3 c) {
+    vec4 K = vec4(0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0);
+    vec4 p = mix(vec4(c.bg, K.wz), vec4(c.gb, K.xy), step(c.b, c.g));
+    vec4 q = mix(vec4(p.xyw, c.r), vec4(c.r, p.yzx), step(p.x, c.r));
+    float d = q.x - min(q.w, q.y);
+    float e = 1.0e-10;
+    return vec3(abs(q.z + (q.w - q.y) / (6.0 * d + e)), d / (q.x + e), q.x);
+}
+
+vec3 _hsvToRgb(vec3 c) {
+    vec4 K = vec4(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0);
+    vec3 p = abs(fract(c.xxx + K.xyz) * 6.0 - K.www);
+    return c.z * mix(K.xxx, clamp(p - K.xxx, 0.0, 1.0), c.y);
+}
+
+// W3C non-separable blend helpers, with luma's weights
+float _sat(vec3 c) { return max(max(c.r, c.g), c.b) - min(min(c.r, c.g), c.b); }
+
+vec3 _setSat(vec3 c, float s) {
+    float n = min(min(c.r, c.g), c.b);
+    float x = max(max(c.r, c.g), c.b);
+    return (c - n) * (s / max(x - n, 1.0e-5));
+}
+
+vec3 _clipColor(vec3 c) {
+    float l = luma(vec4(c, 1.0));
+    float n = min(min(c.r, c.g), c.b);
+    float x = max(max(c.r, c.g), c.b);
+    c = mix(c, l + (c - l) * (l / max(l - n, 1.0e-5)), step(n, 0.0));
+    return mix(c, l + (c - l) * ((1.0 - l) / max(x - l, 1.0e-5)), step(1.0, x));
+}
+
+vec3 _setLum(vec3 c, float l) { return _clipColor(c + (l - luma(vec4(c, 1.0)))); }
+
+// Union of both alphas, as SpirvContext::cover
+float _cover(vec4 _c0, vec4 _c1) { return _c0.a + _c1.a - _c0.a * _c1.a; }
+
+// The sRGB transfer function and its inverse, as SpirvContext::srgb_to_linear / linear_to_srgb
+vec4 _srgbToLinear(vec4 c) {
+    vec3 hi = pow(max((c.rgb + 0.055) * (1.0 / 1.055), 0.0), vec3(2.4));
+    return vec4(mix(c.rgb * (1.0 / 12.92), hi, step(vec3(0.04045), c.rgb)), c.a);
+}
+
+vec4 _linearToSrgb(vec4 c) {
+    vec3 hi = pow(max(c.rgb, 0.0), vec3(1.0 / 2.4)) * 1.055 - 0.055;
+    return vec4(mix(c.rgb * 12.92, hi, step(vec3(0.0031308), c.rgb)), c.a);
+}
+
+// HDR output encodings, as SpirvContext::scrgb_encode / pq_encode
+vec4 _scRgb(vec4 c, float scale) { return vec4(max(c.rgb, 0.0) * scale, c.a); }
+
+vec4 _pq(vec4 c, float scale) {
+    vec3 l = max(c.rgb, 0.0);
+    vec3 y = vec3(dot(l, vec3(0.627404, 0.329283, 0.043313)), dot(l, vec3(0.069097, 0.91954, 0.011362)), dot(l, vec3(0.016391, 0.088013, 0.895595)));
+    vec3 p = pow(min(y * scale, 1.0), vec3(0.1593017578125));
+    return vec4(pow((0.8359375 + 18.8515625 * p) / (1.0 + 18.6875 * p), vec3(78.84375)), c.a);
+}
+";
+
+const CENTER: &str = "
+vec2 center(vec2 _st) { return _st - 0.5; }
+
+vec2 uncenter(vec2 p) { return p + 0.5; }
+";
+
+const CENTER_CORRECTED: &str = "
+vec2 center(vec2 _st) { return (_st - 0.5) * vec2(globals.resolution.x / globals.resolution.y, 1.0); }
+
+vec2 uncenter(vec2 p) { return p / vec2(globals.resolution.x / globals.resolution.y, 1.0) + 0.5; }
+";
+
+// (function name, definition) per IR operation
+fn snippet(kind: &IRKind, aspect: Aspect) -> Option<(&'static str, &'static str)> {
+    Some(match kind {
+        IRKind::Source { ty, .. } => match ty {
+            SourceType::Osc => ("osc", "vec4 osc(vec2 _st, float frequency, float sync, float offset) {
+    vec2 st = _st;
+    float r = sin((st.x - offset / frequency + time * sync) * frequency) * 0.5 + 0.5;
+    float g = sin((st.x + time * sync) * frequency) * 0.5 + 0.5;
+    float b = sin((st.x + offset / frequency + time * sync) * frequency) * 0.5 + 0.5;
+    return vec4(r, g, b, 1.0);
+}"),
+            SourceType::Noise => ("noise", "vec4 _permute(vec4 x) { return mod(((x * 34.0) + 1.0) * x, 289.0); }
+
+vec4 _taylorInvSqrt(vec4 r) { return 1.79284291400159 - 0.85373472095314 * r; }
+
+float _noise(vec3 v) {
+    const vec2 C = vec2(1.0 / 6.0, 1.0 / 3.0);
+    const vec4 D = vec4(0.0, 0.5, 1.0, 2.0);
+    // First corner
+    vec3 i = floor(v + dot(v, C.yyy));
+    vec3 x0 = v - i + dot(i, C.xxx);
+    // Other corners
+    vec3 g = step(x0.yzx, x0.xyz);
+    vec3 l = 1.0 - g;
+    vec3 i1 = min(g.xyz, l.zxy);
+    vec3 i2 = max(g.xyz, l.zxy);
+    vec3 x1 = x0 - i1 + 1.0 * C.xxx;
+    vec3 x2 = x0 - i2 + 2.0 * C.xxx;
+    vec3 x3 = x0 - 1.0 + 3.0 * C.xxx;
+    // Permutations
+    i = mod(i, 289.0);
+    vec4 p = _permute(_permute(_permute(
+        i.z + vec4(0.0, i1.z, i2.z, 1.0))
+        + i.y + vec4(0.0, i1.y, i2.y, 1.0))
+        + i.x + vec4(0.0, i1.x, i2.x, 1.0));
+    // Gradients: 7x7 points over a square, mapped onto an octahedron
+    float n_ = 1.0 / 7.0;
+    vec3 ns = n_ * D.wyz - D.xzx;
+    vec4 j = p - 49.0 * floor(p * ns.z * ns.z);
+    vec4 x_ = floor(j * ns.z);
+    vec4 y_ = floor(j - 7.0 * x_);
+    vec4 x = x_ * ns.x + ns.yyyy;
+    vec4 y = y_ * ns.x + ns.yyyy;
+    vec4 h = 1.0 - abs(x) - abs(y);
+    vec4 b0 = vec4(x.xy, y.xy);
+    vec4 b1 = vec4(x.zw, y.zw);
+    vec4 s0 = floor(b0) * 2.0 + 1.0;
+    vec4 s1 = floor(b1) * 2.0 + 1.0;
+    vec4 sh = -step(h, vec4(0.0));
+    vec4 a0 = b0.xzyw + s0.xzyw * sh.xxyy;
+    vec4 a1 = b1.xzyw + s1.xzyw * sh.zzww;
+    vec3 p0 = vec3(a0.xy, h.x);
+    vec3 p1 = vec3(a0.zw, h.y);
+    vec3 p2 = vec3(a1.xy, h.z);
+    vec3 p3 = vec3(a1.zw, h.w);
+    // Normalise gradients
+    vec4 norm = _taylorInvSqrt(vec4(dot(p0, p0), dot(p1, p1), dot(p2, p2), dot(p3, p3)));
+    p0 *= norm.x;
+    p1 *= norm.y;
+    p2 *= norm.z;
+    p3 *= norm.w;
+    // Mix final noise value
+    vec4 m = max(0.6 - vec4(dot(x0, x0), dot(x1, x1), dot(x2, x2), dot(x3, x3)), 0.0);
+    m = m * m;
+    return 42.0 * dot(m * m, vec4(dot(p0, x0), dot(p1, x1), dot(p2, x2), dot(p3, x3)));
+}
+
+vec4 noise(vec2 _st, float scale, float offset) {
+    return vec4(vec3(_noise(vec3(_st * scale, offset * time))), 1.0);
+}"),
+            SourceType::Solid | SourceType::Src => ("solid", "vec4 solid(float r, float g, float b, float a) {
+    return vec4(r, g, b, a);
+}"),
+            SourceType::Gradient => ("gradient", "vec4 gradient(vec2 _st, float speed) {
+    return vec4(_st, sin(time * speed), 1.0);
+}"),
+            SourceType::Shape => ("shape", "vec4 shape(vec2 _st, float sides, float radius, float smoothing) {
+    vec2 st = center(_st) * 2.0;
+    float a = atan(st.x, st.y) + 3.1416;
+    float r = 6.2832 / max(sides, 1.0);
+    float d = cos(floor(0.5 + a / r) * r - a) * length(st);
+    return vec4(1.0 - smoothstep(radius, radius + smoothing + 0.0000001, d));
+}"),
+            SourceType::Voronoi => ("voronoi", "vec4 voronoi(vec2 _st, float scale, float speed, float blending) {
+    vec3 color = vec3(0.0);
+    _st *= scale;
+    vec2 i_st = floor(_st);
+    vec2 f_st = fract(_st);
+    float m_dist = 10.0;
+    vec2 m_point;
+    for (int j = -1; j <= 1; j++) {
+        for (int i = -1; i <= 1; i++) {
+            vec2 neighbor = vec2(float(i), float(j));
+            vec2 p = i_st + neighbor;
+            vec2 point = fract(sin(vec2(dot(p, vec2(127.1, 311.7)), dot(p, vec2(269.5, 183.3)))) * 43758.5453);
+            point = 0.5 + 0.5 * sin(time * speed + 6.2831 * point);
+            vec2 diff = neighbor + point - f_st;
+            float dist = length(diff);
+            if (dist < m_dist) {
+                m_dist = dist;
+                m_point = point;
+            }
+        }
+    }
+    color += dot(m_point, vec2(0.3, 0.6));
+    color *= 1.0 - blending * m_dist;
+    return vec4(color, 1.0);
+}"),
+            // Sampled inline in main, see GlslEmitter::emit_node
+            SourceType::External | SourceType::Pass => return None,
+        },
+        IRKind::Spatial { ty, .. } => match ty {
+            SpatialType::Scale => ("scale", "vec2 scale(vec2 _st, float x, float y) {
+    return uncenter(center(_st) / max(vec2(x, y), 1e-6));
+}"),
+            // Hydra's own kaleid under Stretch, see SpirvEmitter::kaleid_coord
+            SpatialType::Kaleid if aspect == Aspect::Stretch => ("kaleid", "vec2 kaleid(vec2 _st, float sides) {
+    vec2 p = center(_st);
+    float sector = 6.2832 / max(sides, 1.0);
+    float a = abs(mod(atan(p.y, p.x), sector) - sector * 0.5);
+    return vec2(cos(a), sin(a)) * length(p);
+}"),
+            SpatialType::Kaleid => ("kaleid", "vec2 kaleid(vec2 _st, float sides) {
+    vec2 p = center(_st);
+    float sector = 6.2831855 / max(sides, 1.0);
+    float a = abs(mod(mod(atan(p.y, p.x), 6.2831855), sector) - sector * 0.5);
+    return uncenter(vec2(cos(a), sin(a)) * length(p));
+}"),
+            SpatialType::Rotate => ("rotate", "vec2 rotate(vec2 _st, float angle, float speed) {
+    vec2 p = center(_st);
+    float a = angle + time * speed;
+    return uncenter(vec2(p.x * cos(a) + p.y * sin(a), p.y * cos(a) - p.x * sin(a)));
+}"),
+            SpatialType::ScrollX | SpatialType::ScrollY | SpatialType::Scroll => ("scroll", "vec2 scroll(vec2 _st, float x, float y, float speedX, float speedY) {
+    return fract(_st + vec2(x, y) + time * vec2(speedX, speedY));
+}"),
+            SpatialType::Repeat | SpatialType::RepeatX | SpatialType::RepeatY => ("repeat", "vec2 repeat(vec2 _st, float x, float y) {
+    return fract(_st * max(vec2(x, y), 1e-4));
+}"),
+            SpatialType::Pixelate => ("pixelate", "vec2 pixelate(vec2 _st, float x, float y) {
+    vec2 n = max(vec2(x, y), 1.0);
+    return floor(_st * n) / n + 0.5 / n;
+}"),
+            SpatialType::Polar => ("polar", "vec2 polar(vec2 _st) {
+    vec2 p = center(_st);
+    return vec2(atan(p.y, p.x) * 0.15915494 + 0.5, length(p) * 2.0);
+}"),
+            SpatialType::Cartesian => ("cartesian", "vec2 cartesian(vec2 _st) {
+    float a = (_st.x - 0.5) * 6.2831855;
+    return uncenter(vec2(cos(a), sin(a)) * (_st.y * 0.5));
+}"),
+            SpatialType::Mirror => ("mirror", "vec2 mirror(vec2 _st, float axis) {
+    return mix(_st, 0.5 - abs(_st - 0.5), vec2(step(0.5, abs(axis - 1.0)), step(0.5, axis)));
+}"),
+            SpatialType::Tile => ("tile", "vec2 tile(vec2 _st, float x, float y, float offset) {
+    vec2 p = _st * max(vec2(x, y), 1e-4);
+    return fract(vec2(p.x + offset * floor(p.y), p.y));
+}"),
+            SpatialType::Skew | SpatialType::Transform => ("transform", "vec2 transform(vec2 _st, float a, float b, float c, float d, float tx, float ty) {
+    vec2 p = center(_st);
+    return uncenter(vec2(a * p.x + b * p.y + tx, c * p.x + d * p.y + ty));
+}"),
+        },
+        IRKind::UnaryColor { ty, .. } => match ty {
+            UnaryColorType::Invert => ("invert", "vec4 invert(vec4 _c, float amount) {
+    return vec4(mix(_c.rgb, 1.0 - _c.rgb, amount), _c.a);
+}"),
+            UnaryColorType::Color => ("color", "vec4 color(vec4 _c, float r, float g, float b, float a) {
+    return _c * vec4(r, g, b, a);
+}"),
+            UnaryColorType::Brightness => ("brightness", "vec4 brightness(vec4 _c, float amount) {
+    return vec4(_c.rgb * amount, _c.a);
+}"),
+            UnaryColorType::Contrast => ("contrast", "vec4 contrast(vec4 _c, float amount) {
+    return vec4((_c.rgb - 0.5) * amount + 0.5, _c.a);
+}"),
+            UnaryColorType::Saturate => ("saturate", "vec4 saturate(vec4 _c, float amount) {
+    return vec4(mix(vec3(dot(_c.rgb, vec3(0.2125, 0.7154, 0.0721))), _c.rgb, amount), _c.a);
+}"),
+            UnaryColorType::Posterize => ("posterize", "vec4 posterize(vec4 _c, float bins, float gamma) {
+    vec3 c = pow(max(_c.rgb, 0.0), vec3(gamma));
+    c = floor(c * bins) / bins;
+    return vec4(pow(c, vec3(1.0 / gamma)), _c.a);
+}"),
+            UnaryColorType::Thresh => ("thresh", "vec4 thresh(vec4 _c, float threshold, float amount) {
+    return vec4(mix(_c.rgb, step(threshold, _c.rgb), amount), _c.a);
+}"),
+            UnaryColorType::Hue => ("hue", "vec4 hue(vec4 _c0, float hue) {
+    vec3 c = _rgbToHsv(_c0.rgb);
+    c.r += hue;
+    c = _hsvToRgb(c);
+    return vec4(c, _c0.a);
+}"),
+            UnaryColorType::Colorama => ("colorama", "vec4 colorama(vec4 _c0, float amount) {
+    vec3 c = _rgbToHsv(_c0.rgb);
+    c += vec3(amount);
+    c = _hsvToRgb(c);
+    c = fract(c);
+    return vec4(c, _c0.a);
+}"),
+            UnaryColorType::Luma => ("luma_fx", "vec4 luma_fx(vec4 _c) {
+    return vec4(vec3(luma(_c)), _c.a);
+}"),
+            UnaryColorType::Shift => ("shift", "vec4 shift(vec4 _c, float r, float g, float b, float a) {
+    return clamp(_c + vec4(r, g, b, a), 0.0, 1.0);
+}"),
+            UnaryColorType::BrightPass => ("bright_pass", "vec4 bright_pass(vec4 _c, float threshold) {
+    return vec4(max(_c.rgb - threshold, 0.0), 0.0);
+}"),
+        },
+        IRKind::Binary { ty, .. } => match ty {
+            BinaryType::Add => ("add", "vec4 add(vec4 _c0, vec4 _c1, float amount) {
+    return mix(_c0, vec4(_c0.rgb + _c1.rgb, _cover(_c0, _c1)), amount);
+}"),
+            BinaryType::Sub => ("sub", "vec4 sub(vec4 _c0, vec4 _c1, float amount) {
+    return mix(_c0, vec4(_c0.rgb - _c1.rgb, _cover(_c0, _c1)), amount);
+}"),
+            BinaryType::Mult => ("mult", "vec4 mult(vec4 _c0, vec4 _c1, float amount) {
+    return mix(_c0, _c0 * _c1, amount);
+}"),
+            BinaryType::Blend => ("blend", "vec4 blend(vec4 _c0, vec4 _c1, float amount) {
+    return mix(_c0, _c1, amount);
+}"),
+            BinaryType::Diff => ("diff", "vec4 diff(vec4 _c0, vec4 _c1) {
+    return vec4(abs(_c0.rgb - _c1.rgb), _cover(_c0, _c1));
+}"),
+            BinaryType::Layer => ("layer", "vec4 layer(vec4 _c0, vec4 _c1) {
+    return vec4(mix(_c0.rgb, _c1.rgb, _c1.a), _cover(_c0, _c1));
+}"),
+            BinaryType::Mask => ("mask", "vec4 mask(vec4 _c0, vec4 _c1) {
+    return _c0 * luma(_c1);
+}"),
+            BinaryType::Modulate => ("modulate", "vec2 modulate(vec2 _st, vec4 _c1, float amount) {
+    return _st + _c1.xy * amount;
+}"),
+            BinaryType::ModulateScale => ("modulateScale", "vec2 modulateScale(vec2 _st, vec4 _c1, float amount) {
+    return uncenter(center(_st) / (1.0 + luma(_c1) * amount));
+}"),
+            BinaryType::Screen => ("screen", "vec4 screen(vec4 _c0, vec4 _c1, float amount) {
+    return mix(_c0, vec4(_c0.rgb + _c1.rgb - _c0.rgb * _c1.rgb, _cover(_c0, _c1)), amount);
+}"),
+            BinaryType::Overlay => ("overlay", "vec4 overlay(vec4 _c0, vec4 _c1, float amount) {
+    vec3 x = _c0.rgb, y = _c1.rgb;
+    vec3 c = mix(2.0 * (x * y), 1.0 - 2.0 * ((1.0 - x) * (1.0 - y)), step(0.5, x));
+    return mix(_c0, vec4(c, _cover(_c0, _c1)), amount);
+}"),
+            BinaryType::SoftLight => ("softLight", "vec4 softLight(vec4 _c0, vec4 _c1, float amount) {
+    vec3 x = _c0.rgb, y = _c1.rgb;
+    vec3 d = mix(((16.0 * x - 12.0) * x + 4.0) * x, sqrt(max(x, 0.0)), step(0.25, x));
+    vec3 c = mix(x - (1.0 - 2.0 * y) * x * (1.0 - x), x + (2.0 * y - 1.0) * (d - x), step(0.5, y));
+    return mix(_c0, vec4(c, _cover(_c0, _c1)), amount);
+}"),
+            BinaryType::HardLight => ("hardLight", "vec4 hardLight(vec4 _c0, vec4 _c1, float amount) {
+    vec3 x = _c0.rgb, y = _c1.rgb;
+    vec3 c = mix(2.0 * (x * y), 1.0 - 2.0 * ((1.0 - x) * (1.0 - y)), step(0.5, y));
+    return mix(_c0, vec4(c, _cover(_c0, _c1)), amount);
+}"),
+            BinaryType::ColorDodge => ("colorDodge", "vec4 colorDodge(vec4 _c0, vec4 _c1, float amount) {
+    vec3 c = min(vec3(1.0), _c0.rgb / max(1.0 - _c1.rgb, 1.0e-5));
+    return mix(_c0, vec4(c, _cover(_c0, _c1)), amount);
+}"),
+            BinaryType::ColorBurn => ("colorBurn", "vec4 colorBurn(vec4 _c0, vec4 _c1, float amount) {
+    vec3 c = 1.0 - min(vec3(1.0), (1.0 - _c0.rgb) / max(_c1.rgb, 1.0e-5));
+    return mix(_c0, vec4(c, _cover(_c0, _c1)), amount);
+}"),
+            BinaryType::Darken => ("darken", "vec4 darken(vec4 _c0, vec4 _c1, float amount) {
+    return mix(_c0, vec4(min(_c0.rgb, _c1.rgb), _cover(_c0, _c1)), amount);
+}"),
+            BinaryType::Lighten => ("lighten", "vec4 lighten(vec4 _c0, vec4 _c1, float amount) {
+    return mix(_c0, vec4(max(_c0.rgb, _c1.rgb), _cover(_c0, _c1)), amount);
+}"),
+            BinaryType::Exclusion => ("exclusion", "vec4 exclusion(vec4 _c0, vec4 _c1, float amount) {
+    return mix(_c0, vec4(_c0.rgb + _c1.rgb - 2.0 * (_c0.rgb * _c1.rgb), _cover(_c0, _c1)), amount);
+}"),
+            BinaryType::Hue => ("blendHue", "vec4 blendHue(vec4 _c0, vec4 _c1, float amount) {
+    return mix(_c0, vec4(_setLum(_setSat(_c1.rgb, _sat(_c0.rgb)), luma(_c0)), _cover(_c0, _c1)), amount);
+}"),
+            BinaryType::Saturation => ("saturation", "vec4 saturation(vec4 _c0, vec4 _c1, float amount) {
+    return mix(_c0, vec4(_setLum(_setSat(_c0.rgb, _sat(_c1.rgb)), luma(_c0)), _cover(_c0, _c1)), amount);
+}"),
+            BinaryType::Color => ("blendColor", "vec4 blendColor(vec4 _c0, vec4 _c1, float amount) {
+    return mix(_c0, vec4(_setLum(_c1.rgb, luma(_c0)), _cover(_c0, _c1)), amount);
+}"),
+            BinaryType::Luminosity => ("luminosity", "vec4 luminosity(vec4 _c0, vec4 _c1, float amount) {
+    return mix(_c0, vec4(_setLum(_c0.rgb, luma(_c1)), _cover(_c0, _c1)), amount);
+}"),
+        },
+        // Taps and history reads composed in main, see GlslEmitter::emit_filter and emit_temporal
+        IRKind::Filter { .. } | IRKind::Temporal { .. } | IRKind::Output { .. } => return None,
+    })
+}
+
+// Arguments as passed to the snippet, defaults matching the SPIR-V emitters
+fn call_args(kind: &IRKind) -> Vec<Arg> {
+    let fill = |args: &[Arg], defaults: &[f32]| -> Vec<Arg> {
+        defaults.iter().enumerate().map(|(i, d)| args.get(i).copied().unwrap_or(Arg::Const(*d))).collect()
+    };
+    let arg = |args: &[Arg], i: usize, d: f32| args.get(i).copied().unwrap_or(Arg::Const(d));
+    let c = Arg::Const;
+    match kind {
+        IRKind::Source { ty, args } => match ty {
+            SourceType::Osc => fill(args, &[60.0, 0.1, 0.0]),
+            SourceType::Noise => fill(args, &[10.0, 0.1]),
+            SourceType::Solid | SourceType::Src => fill(args, &[0.0, 0.0, 0.0, 1.0]),
+            SourceType::Gradient => fill(args, &[0.0]),
+            SourceType::External | SourceType::Pass => vec![],
+            SourceType::Shape => fill(args, &[3.0, 0.3, 0.01]),
+            SourceType::Voronoi => fill(args, &[5.0, 0.3, 0.3]),
+        },
+        IRKind::Spatial { ty, args, .. } => match ty {
+            SpatialType::Scale | SpatialType::Pixelate => {
+                let x = arg(args, 0, if matches!(ty, SpatialType::Scale) { 1.0 } else { 10.0 });
+                vec![x, args.get(1).copied().unwrap_or(x)]
+            }
+            SpatialType::Kaleid => fill(args, &[4.0]),
+            SpatialType::Rotate => fill(args, &[0.0, 0.0]),
+            SpatialType::ScrollX => vec![arg(args, 0, 0.0), c(0.0), arg(args, 1, 0.0), c(0.0)],
+            SpatialType::ScrollY => vec![c(0.0), arg(args, 0, 0.0), c(0.0), arg(args, 1, 0.0)],
+            SpatialType::Scroll => fill(args, &[0.0, 0.0, 0.0, 0.0]),
+            SpatialType::Repeat => { let x = arg(args, 0, 3.0); vec![x, args.get(1).copied().unwrap_or(x)] }
+            SpatialType::RepeatX => vec![arg(args, 0, 3.0), c(1.0)],
+            SpatialType::RepeatY => vec![c(1.0), arg(args, 0, 3.0)],
+            SpatialType::Polar | SpatialType::Cartesian => vec![],
+            SpatialType::Mirror => fill(args, &[0.0]),
+            SpatialType::Tile => { let x = arg(args, 0, 3.0); vec![x, args.get(1).copied().unwrap_or(x), arg(args, 2, 0.5)] }
+            SpatialType::Skew => vec![c(1.0), arg(args, 0, 0.0), arg(args, 1, 0.0), c(1.0), c(0.0), c(0.0)],
+            SpatialType::Transform => fill(args, &[1.0, 0.0, 0.0, 1.0, 0.0, 0.0]),
+        },
+        IRKind::UnaryColor { ty, args, .. } => match ty {
+            UnaryColorType::Invert | UnaryColorType::Brightness | UnaryColorType::Contrast | UnaryColorType::Saturate => fill(args, &[1.0]),
+            UnaryColorType::Color => fill(args, &[1.0, 1.0, 1.0, 1.0]),
+            UnaryColorType::Posterize => fill(args, &[4.0, 0.6]),
+            UnaryColorType::Thresh => fill(args, &[0.5, 1.0]),
+            UnaryColorType::Hue => fill(args, &[0.4]),
+            UnaryColorType::Colorama => fill(args, &[0.005]),
+            UnaryColorType::Luma => vec![],
+            UnaryColorType::Shift => fill(args, &[0.0, 0.0, 0.0, 0.0]),
+            UnaryColorType::BrightPass => fill(args, &[0.6]),
+        },
+        IRKind::Binary { ty, args, .. } => match ty {
+            BinaryType::Diff | BinaryType::Layer | BinaryType::Mask => vec![],
+            BinaryType::Modulate => fill(args, &[0.1]),
+            BinaryType::ModulateScale => fill(args, &[0.5]),
+            _ => fill(args, &[1.0]),
+        },
+        IRKind::Filter { args, .. } | IRKind::Temporal { args, .. } => args.clone(),
+        IRKind::Output { .. } => vec![],
+    }
+}
+
+// GLSL expression for an argument; globals read the block declared in HEADER
+fn arg_expr(arg: Arg) -> String {
+    match arg {
+        Arg::Const(v) => format!("{:?}", v),
+        Arg::Global(g) => match g {
+            Global::Time => "time".into(),
+            Global::Frame => "globals.frame".into(),
+            Global::ResolutionX => "globals.resolution.x".into(),
+            Global::ResolutionY => "globals.resolution.y".into(),
+            Global::MouseX => "globals.mouse.x".into(),
+            Global::MouseY => "globals.mouse.y".into(),
+            Global::MouseButtons => "globals.mouseButtons".into(),
+            Global::Dt => "globals.dt".into(),
+            Global::Fps => "globals.fps".into(),
+            Global::Bpm => "globals.bpm".into(),
+            Global::Beat => "globals.beat".into(),
+            Global::Custom(i) => format!("globals.custom[{}][{}]", i / 4, i % 4),
+        },
+    }
+}
+
+pub struct GlslEmitter {
+    post_process: PostProcess,
+    alpha: AlphaMode,
+    color_space: ColorSpace,
+    output_space: ColorSpace,
+    output_transform: OutputTransform,
+    supersample: u32,
+    aspect: Aspect,
+    // Snippets in first-use order
+    functions: Vec<&'static str>,
+    body: String,
+    temps: usize,
+    outputs: HashMap<u32, String>,
+    // External source slots, pass targets and history rings sampled by the sketch, declared after HEADER
+    externals: BTreeSet<u32>,
+    passes: BTreeSet<u32>,
+    histories: BTreeSet<u32>,
+    // See SpirvEmitter::with_intermediate_output
+    intermediate: bool,
+}
+
+impl Default for GlslEmitter {
+    fn default() -> Self { Self::new() }
+}
+
+impl GlslEmitter {
+    pub fn new() -> Self {
+        Self { post_process: PostProcess::default(), alpha: AlphaMode::default(), color_space: ColorSpace::default(), output_space: ColorSpace::default(), output_transform: OutputTransform::default(), supersample: 1, aspect: Aspect::default(), functions: Vec::new(), body: String::new(), temps: 0, outputs: HashMap::new(), externals: BTreeSet::new(), passes: BTreeSet::new(), histories: BTreeSet::new(), intermediate: false }
+    }
+
+    pub fn with_aspect(mut self, aspect: Aspect) -> Self {
+        self.aspect = aspect;
+        self
+    }
+
+    pub fn with_post_process(mut self, post_process: PostProcess) -> Self {
+        self.post_process = post_process;
+        self
+    }
+
+    pub fn with_alpha(mut self, alpha: AlphaMode) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    pub fn with_output_space(mut self, output_space: ColorSpace) -> Self {
+        self.output_space = output_space;
+        self
+    }
+
+    pub fn with_output_transform(mut self, output_transform: OutputTransform) -> Self {
+        self.output_transform = output_transform;
+        self
+    }
+
+    pub fn with_supersample(mut self, samples: u32) -> Self {
+        self.supersample = samples;
+        self
+    }
+
+    pub fn with_intermediate_output(mut self) -> Self {
+        self.intermediate = true;
+        self
+    }
+
+    pub fn emit_ir(mut self, ir: &IRBuilder, root: Option<NodeId>) -> Result<String, CompileError> {
+        let root = root.ok_or(CompileError::EmptyProgram)?;
+        let color = if self.supersample > 1 {
+            // Same samples and mean as SpirvEmitter::emit_ir
+            let node = match ir.nodes.get(root.0).map(|n| &n.kind) { Some(IRKind::Output { child, .. }) => *child, _ => root };
+            let mut colors = Vec::new();
+            for (x, y) in supersample_offsets(self.supersample) {
+                let st = self.temp("vec2", "st", self.uv(&format!("(gl_FragCoord.xy + vec2({:?}, {:?}))", x, y)));
+                colors.push(self.emit_node(ir, node, &st)?);
+            }
+            self.temp("vec4", "supersample", format!("({}) * {:?}", colors.join(" + "), 1.0 / colors.len() as f32))
+        } else {
+            self.emit_node(ir, root, "st")?
+        };
+
+        let mut out = String::from(HEADER);
+        // Same bindings as SpirvContext::declare_external, declare_pass and declare_history
+        for &slot in &self.externals {
+            let _ = writeln!(out, "layout(binding = {}) uniform texture2D {};", external::texture_binding(slot), external::texture_name(slot));
+            let _ = writeln!(out, "layout(binding = {}) uniform sampler {};", external::sampler_binding(slot), external::sampler_name(slot));
+        }
+        for &index in &self.passes {
+            let _ = writeln!(out, "layout(binding = {}) uniform texture2D {};", passes::texture_binding(index), passes::texture_name(index));
+            let _ = writeln!(out, "layout(binding = {}) uniform sampler {};", passes::sampler_binding(index), passes::sampler_name(index));
+        }
+        for &index in &self.histories {
+            let _ = writeln!(out, "layout(binding = {}) uniform texture2DArray {};", passes::history_texture_binding(index), passes::history_texture_name(index));
+            let _ = writeln!(out, "layout(binding = {}) uniform sampler {};", passes::history_sampler_binding(index), passes::history_sampler_name(index));
+        }
+        out.push_str(HELPERS);
+        // Same spaces as SpirvContext::compute_uv and center_coord
+        out.push_str(if self.aspect == Aspect::Corrected { CENTER_CORRECTED } else { CENTER });
+        for f in &self.functions {
+            let _ = write!(out, "\n{}\n", f);
+        }
+        out.push_str("\nvoid main()\n{\n    time = globals.time;\n");
+        let _ = writeln!(out, "    vec2 st = {};", self.uv("gl_FragCoord.xy"));
+        out.push_str(&self.body);
+        let _ = writeln!(out, "    vec4 _out = {};", color);
+        if self.intermediate {
+            out.push_str("    fragColor = _out;\n}\n");
+            return Ok(out);
+        }
+        self.emit_post_process(&mut out);
+        // Same order as SpirvEmitter: clamp, convert to the output space (or encode HDR), premultiply
+        let linear = match self.color_space {
+            ColorSpace::Srgb => "_srgbToLinear(_out)",
+            ColorSpace::Linear => "_out",
+        };
+        let color = match self.output_transform {
+            OutputTransform::Sdr => {
+                let clamped = "vec4(clamp(_out.rgb, 0.0, 1.0), _out.a)";
+                match (self.color_space, self.output_space) {
+                    (ColorSpace::Srgb, ColorSpace::Linear) => format!("_srgbToLinear({})", clamped),
+                    (ColorSpace::Linear, ColorSpace::Srgb) => format!("_linearToSrgb({})", clamped),
+                    _ => clamped.to_string(),
+                }
+            }
+            OutputTransform::ScRgb { paper_white } => format!("_scRgb({}, {:?})", linear, paper_white / 80.0),
+            OutputTransform::Pq { paper_white } => format!("_pq({}, {:?})", linear, paper_white / 10000.0),
+        };
+        match self.alpha {
+            AlphaMode::Straight => { let _ = writeln!(out, "    fragColor = {};\n}}", color); }
+            AlphaMode::Premultiplied => {
+                let _ = writeln!(out, "    vec4 _color = {};", color);
+                out.push_str("    float _alpha = clamp(_color.a, 0.0, 1.0);\n    fragColor = vec4(_color.rgb * _alpha, _alpha);\n}\n");
+            }
+        }
+        Ok(out)
+    }
+
+    // Same stages and constants as SpirvEmitter::apply_post_process
+    fn emit_post_process(&self, out: &mut String) {
+        let pp = &self.post_process;
+        if let Some(max_gain) = pp.auto_exposure {
+            let _ = writeln!(out, "    _out.rgb *= min(1.0 / (luma(_out) + 0.02), {:?});", max_gain);
+        }
+        // SpirvEmitter::apply_post_process skips tone mapping for HDR outputs too
+        let tone_map = if self.output_transform.is_hdr() { ToneMap::None } else { pp.tone_map };
+        match tone_map {
+            ToneMap::None => {}
+            ToneMap::Aces => out.push_str("    _out.rgb = (_out.rgb * (2.51 * _out.rgb + 0.03)) / (_out.rgb * (2.43 * _out.rgb + 0.59) + 0.14);\n"),
+            ToneMap::Reinhard => out.push_str("    _out.rgb = max(_out.rgb, 0.0) / (max(_out.rgb, 0.0) + 1.0);\n"),
+        }
+        if pp.gamma != 1.0 {
+            let _ = writeln!(out, "    _out.rgb = pow(max(_out.rgb, 0.0), vec3({:?}));", 1.0 / pp.gamma);
+        }
+        if pp.lut.len() >= 2 {
+            let vec3 = |c: [f32; 3]| format!("vec3({:?}, {:?}, {:?})", c[0], c[1], c[2]);
+            let _ = writeln!(out, "    vec3 _lut_x = clamp(_out.rgb, 0.0, 1.0) * {:?};", (pp.lut.len() - 1) as f32);
+            let _ = write!(out, "    _out.rgb = {}", vec3(pp.lut[0]));
+            for k in 1..pp.lut.len() {
+                let (prev, next) = (pp.lut[k - 1], pp.lut[k]);
+                let delta = [next[0] - prev[0], next[1] - prev[1], next[2] - prev[2]];
+                if delta == [0.0; 3] { continue; }
+                let _ = write!(out, "\n        + clamp(_lut_x - {:?}, 0.0, 1.0) * {}", (k - 1) as f32, vec3(delta));
+            }
+            out.push_str(";\n");
+        }
+    }
+
+    // Appends `ty name = expr;` to main and returns the variable name
+    fn temp(&mut self, ty: &str, prefix: &str, expr: String) -> String {
+        let name = format!("{}_{}", prefix, self.temps);
+        self.temps += 1;
+        let _ = writeln!(self.body, "    {} {} = {};", ty, name, expr);
+        name
+    }
+
+    // `name(first, args...)`, registering the snippet on first use
+    fn call(&mut self, kind: &IRKind, leading: &[&str]) -> String {
+        let Some((name, def)) = snippet(kind, self.aspect) else { return String::new() };
+        if !self.functions.contains(&def) { self.functions.push(def); }
+        let args = leading.iter().map(|s| s.to_string()).chain(call_args(kind).into_iter().map(arg_expr));
+        format!("{}({})", name, args.collect::<Vec<_>>().join(", "))
+    }
+
+    // Same evaluation order as SpirvEmitter::emit_ir_node
+    fn emit_node(&mut self, ir: &IRBuilder, id: NodeId, coord: &str) -> Result<String, CompileError> {
+        let node = ir.nodes.get(id.0)
+            .ok_or_else(|| CompileError::InvalidProgram(format!("dangling node id {}", id.0)))?;
+        let kind = &node.kind;
+        let prefix = kind.name();
+        match kind {
+            IRKind::Source { ty: SourceType::Src, args } => {
+                let idx = args.first().and_then(Arg::as_const).unwrap_or(0.0) as u32;
+                if let Some(var) = self.outputs.get(&idx) { return Ok(var.clone()); }
+                let expr = self.call(kind, &[]);
+                Ok(self.temp("vec4", prefix, expr))
+            }
+            IRKind::Source { ty: SourceType::External, args } => {
+                // Same addressing as SpirvContext::emit_external
+                let slot = args.first().and_then(Arg::as_const).unwrap_or(0.0) as u32;
+                self.externals.insert(slot);
+                let wrap = ir.external_wrap(slot);
+                let axes = [format!("{}.x", coord), format!("{}.y", coord)];
+                let address = |x: &str, wrap: Wrap| match wrap {
+                    Wrap::Repeat => format!("fract({})", x),
+                    Wrap::Clamp | Wrap::Border => format!("clamp({}, 0.0, 1.0)", x),
+                    Wrap::Mirror => format!("1.0 - abs(mod({}, 2.0) - 1.0)", x),
+                };
+                let mut expr = format!(
+                    "texture(sampler2D({}, {}), vec2({}, {}))",
+                    external::texture_name(slot), external::sampler_name(slot), address(&axes[0], wrap[0]), address(&axes[1], wrap[1])
+                );
+                if self.color_space == ColorSpace::Linear { expr = format!("_srgbToLinear({})", expr); }
+                for (x, w) in axes.iter().zip(wrap) {
+                    if w == Wrap::Border { let _ = write!(expr, " * (step(0.0, {x}) * step({x}, 1.0))"); }
+                }
+                Ok(self.temp("vec4", prefix, expr))
+            }
+            IRKind::Source { ty: SourceType::Pass, args } => {
+                let index = args.first().and_then(Arg::as_const).unwrap_or(0.0) as u32;
+                self.passes.insert(index);
+                let uv = self.target_uv(coord);
+                let expr = format!("texture(sampler2D({}, {}), {})", passes::texture_name(index), passes::sampler_name(index), uv);
+                Ok(self.temp("vec4", prefix, expr))
+            }
+            IRKind::Source { ty, .. } => {
+                let leading: &[&str] = if matches!(ty, SourceType::Solid) { &[] } else { &[coord] };
+                let expr = self.call(kind, leading);
+                Ok(self.temp("vec4", prefix, expr))
+            }
+            IRKind::Spatial { child, .. } => {
+                let expr = self.call(kind, &[coord]);
+                let new_coord = self.temp("vec2", prefix, expr);
+                self.emit_node(ir, *child, &new_coord)
+            }
+            IRKind::UnaryColor { child, .. } => {
+                let base = self.emit_node(ir, *child, coord)?;
+                let expr = self.call(kind, &[&base]);
+                Ok(self.temp("vec4", prefix, expr))
+            }
+            IRKind::Binary { ty: BinaryType::Modulate | BinaryType::ModulateScale, left, right, .. } => {
+                let mod_color = self.emit_node(ir, *right, coord)?;
+                let expr = self.call(kind, &[coord, &mod_color]);
+                let new_coord = self.temp("vec2", prefix, expr);
+                self.emit_node(ir, *left, &new_coord)
+            }
+            IRKind::Binary { left, right, .. } => {
+                let a = self.emit_node(ir, *left, coord)?;
+                let b = self.emit_node(ir, *right, coord)?;
+                let expr = self.call(kind, &[&a, &b]);
+                Ok(self.temp("vec4", prefix, expr))
+            }
+            IRKind::Filter { ty, child, .. } => self.emit_filter(ir, kind, *ty, *child, coord),
+            IRKind::Temporal { ty, child, history, .. } => {
+                let current = self.emit_node(ir, *child, coord)?;
+                self.emit_temporal(ir, kind, *ty, *history, &current, coord)
+            }
+            IRKind::Output { child, index } => {
+                let c = self.emit_node(ir, *child, coord)?;
+                let var = format!("o{}", index);
+                let _ = writeln!(self.body, "    vec4 {} = {};", var, c);
+                self.outputs.insert(*index, var.clone());
+                Ok(var)
+            }
+        }
+    }
+
+    // `st` at window position `xy`, as SpirvContext::compute_uv
+    fn uv(&self, xy: &str) -> String {
+        if self.aspect == Aspect::Square {
+            format!("({} - globals.resolution * 0.5) / vec2(min(globals.resolution.x, globals.resolution.y)) + 0.5", xy)
+        } else {
+            format!("{} / globals.resolution", xy)
+        }
+    }
+
+    // Same mapping as SpirvContext::st_to_target
+    fn target_uv(&self, coord: &str) -> String {
+        if self.aspect == Aspect::Square {
+            format!("((({}) - 0.5) * min(globals.resolution.x, globals.resolution.y) + globals.resolution * 0.5) / globals.resolution", coord)
+        } else {
+            coord.to_string()
+        }
+    }
+
+    // Same history reads and combination as SpirvContext::emit_temporal
+    fn emit_temporal(&mut self, ir: &IRBuilder, kind: &IRKind, ty: TemporalType, history: Option<u32>, current: &str, coord: &str) -> Result<String, CompileError> {
+        let pass = history.ok_or_else(|| CompileError::InvalidProgram(format!("`{}` without a history pass", ty.name())))?;
+        self.histories.insert(pass);
+        let ring = ir.history_frames(pass);
+        let uv = self.temp("vec2", "uv", self.target_uv(coord));
+        let past = |k: u32| format!(
+            "texture(sampler2DArray({}, {}), vec3({}, float((uint(globals.frame) % {}u + {}u) % {}u)))",
+            passes::history_texture_name(pass), passes::history_sampler_name(pass), uv, ring, ring - k % ring, ring
+        );
+        let args = call_args(kind);
+        let count = |i: usize| args.get(i).and_then(Arg::as_const).unwrap_or(4.0) as u32;
+        let expr = match ty {
+            TemporalType::Trails => {
+                let decay = arg_expr(args.first().copied().unwrap_or(Arg::Const(0.9)));
+                format!("max({}, {} * {})", current, past(1), decay)
+            }
+            TemporalType::Echo => {
+                let (frames, spacing) = (count(0), count(1));
+                let mut color = current.to_string();
+                for k in 1..frames {
+                    let fade = 1.0 - k as f32 / frames as f32;
+                    color = self.temp("vec4", "echo", format!("max({}, {} * {:?})", color, past(k * spacing), fade));
+                }
+                return Ok(color);
+            }
+            TemporalType::MotionBlur => {
+                let samples = count(0);
+                let terms: Vec<String> = [current.to_string()].into_iter().chain((1..samples).map(past)).collect();
+                format!("({}) * {:?}", terms.join(" + "), 1.0 / samples.max(1) as f32)
+            }
+        };
+        Ok(self.temp("vec4", kind.name(), expr))
+    }
+
+    // Same taps and sums as SpirvContext::filter_taps and combine_taps
+    fn emit_filter(&mut self, ir: &IRBuilder, kind: &IRKind, ty: FilterType, child: NodeId, coord: &str) -> Result<String, CompileError> {
+        let prefix = kind.name();
+        let pixel = if self.aspect == Aspect::Square {
+            "vec2(1.0) / vec2(min(globals.resolution.x, globals.resolution.y))"
+        } else {
+            "vec2(1.0) / globals.resolution"
+        };
+        let pixel = match ty {
+            FilterType::Supersample => String::new(),
+            _ => self.temp("vec2", "pixel", pixel.into()),
+        };
+        let offsets: Vec<String> = match ty {
+            FilterType::BlurX | FilterType::BlurY => {
+                let radius = arg_expr(call_args(kind).first().copied().unwrap_or(Arg::Const(4.0)));
+                let axis = if matches!(ty, FilterType::BlurX) { "x" } else { "y" };
+                let step = self.temp("float", "step", format!("{}.{} * {} * {:?}", pixel, axis, radius, 1.0 / BLUR_TAPS as f32));
+                (-BLUR_TAPS..=BLUR_TAPS).map(|k| {
+                    let d = format!("{} * {:?}", step, k as f32);
+                    if matches!(ty, FilterType::BlurX) { format!("vec2({}, 0.0)", d) } else { format!("vec2(0.0, {})", d) }
+                }).collect()
+            }
+            FilterType::Edges => SOBEL.iter()
+                .map(|(dx, dy, _, _)| format!("vec2({}.x * {:?}, {}.y * {:?})", pixel, dx, pixel, dy))
+                .collect(),
+            FilterType::Supersample => {
+                let ddx = self.temp("vec2", "ddx", format!("dFdx({})", coord));
+                let ddy = self.temp("vec2", "ddy", format!("dFdy({})", coord));
+                supersample_offsets(supersample_count(&call_args(kind))).into_iter()
+                    .map(|(jx, jy)| format!("({} * {:?} + {} * {:?})", ddx, jx, ddy, jy))
+                    .collect()
+            }
+        };
+        let mut colors = Vec::new();
+        for offset in offsets {
+            let tap = self.temp("vec2", "tap", format!("{} + {}", coord, offset));
+            colors.push(self.emit_node(ir, child, &tap)?);
+        }
+        let expr = match ty {
+            FilterType::BlurX | FilterType::BlurY => colors.iter().zip(blur_weights())
+                .map(|(c, w)| format!("{} * {:?}", c, w))
+                .collect::<Vec<_>>()
+                .join(" + "),
+            FilterType::Edges => {
+                let gradient = |axis: usize| {
+                    let terms: Vec<String> = colors.iter().zip(SOBEL)
+                        .map(|(c, k)| (c, if axis == 0 { k.2 } else { k.3 }))
+                        .filter(|(_, w)| *w != 0.0)
+                        .map(|(c, w)| format!("luma({}) * {:?}", c, w))
+                        .collect();
+                    terms.join(" + ")
+                };
+                let gx = self.temp("float", "gx", gradient(0));
+                let gy = self.temp("float", "gy", gradient(1));
+                format!("vec4(vec3(length(vec2({}, {}))), 1.0)", gx, gy)
+            }
+            FilterType::Supersample => format!("({}) * {:?}", colors.join(" + "), 1.0 / colors.len() as f32),
+        };
+        Ok(self.temp("vec4", prefix, expr))
+    }
+}
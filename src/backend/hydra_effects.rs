@@ -1,369 +1,718 @@
 // Hydra effect/modifier functions (rotate, scale, invert, color, etc.)
 
 use rspirv::spirv::Word;
-use swc_ecma_ast::CallExpr;
 use super::spirv_context::SpirvContext;
+use crate::error::CompileError;
+use crate::ir::Arg;
 
 impl SpirvContext {
     // Implement coordinate transforms later; current rotate/scale act as hue/contrast stand-ins if needed.
-    pub fn emit_rotate(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
-        let angle = self.get_arg_or_default(call, 0, 0.0);
-        let speed = self.get_arg_or_default(call, 1, 0.0);
-        let time = self.load_time();
-        let mul = self.builder.f_mul(self.types.f32_ty, None, time, speed).unwrap();
-        let dyn_angle = self.builder.f_add(self.types.f32_ty, None, angle, mul).unwrap();
-        Some(self.hue_rotate(color, dyn_angle))
-    }
-
-    pub fn emit_scale(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
-        let sx = self.get_arg_or_default(call, 0, 1.0);
-        let sy = self.get_arg_or_default(call, 1, 1.0);
-        let sum = self.builder.f_add(self.types.f32_ty, None, sx, sy).unwrap();
+    pub fn emit_rotate(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        let angle = self.get_arg_or_default(args, 0, 0.0)?;
+        let speed = self.get_arg_or_default(args, 1, 0.0)?;
+        let time = self.load_time()?;
+        let mul = self.builder.f_mul(self.types.f32_ty, None, time, speed)?;
+        let dyn_angle = self.builder.f_add(self.types.f32_ty, None, angle, mul)?;
+        self.hue_rotate(color, dyn_angle)
+    }
+
+    pub fn emit_scale(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        let sx = self.get_arg_or_default(args, 0, 1.0)?;
+        let sy = self.get_arg_or_default(args, 1, 1.0)?;
+        let sum = self.builder.f_add(self.types.f32_ty, None, sx, sy)?;
         let half = self.emit_f32_constant(0.5);
-        let avg = self.builder.f_mul(self.types.f32_ty, None, sum, half).unwrap();
-        Some(self.contrast_amount(color, avg))
+        let avg = self.builder.f_mul(self.types.f32_ty, None, sum, half)?;
+        self.contrast_amount(color, avg)
     }
 
-    pub fn emit_invert(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
-        let amount = self.get_arg_or_default(call, 0, 1.0);
+    pub fn emit_invert(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        let amount = self.get_arg_or_default(args, 0, 1.0)?;
         let one = self.emit_f32_constant(1.0);
-        let r = self.extract_component(color,0);
-        let g = self.extract_component(color,1);
-        let b = self.extract_component(color,2);
-        let a = self.extract_component(color,3);
-        let inv_r = self.builder.f_sub(self.types.f32_ty, None, one, r).unwrap();
-        let inv_g = self.builder.f_sub(self.types.f32_ty, None, one, g).unwrap();
-        let inv_b = self.builder.f_sub(self.types.f32_ty, None, one, b).unwrap();
-        let fr = self.mix(r, inv_r, amount);
-        let fg = self.mix(g, inv_g, amount);
-        let fb = self.mix(b, inv_b, amount);
-        Some(self.construct_vec4(fr, fg, fb, a))
-    }
-
-    pub fn emit_color(&mut self, input: Word, call: &CallExpr) -> Option<Word> {
-        let r = self.get_arg_or_default(call, 0, 1.0);
-        let g = self.get_arg_or_default(call, 1, 1.0);
-        let b = self.get_arg_or_default(call, 2, 1.0);
-        let a = self.get_arg_or_default(call, 3, 1.0);
-        let ir = self.extract_component(input,0);
-        let ig = self.extract_component(input,1);
-        let ib = self.extract_component(input,2);
-        let ia = self.extract_component(input,3);
-        let fr = self.builder.f_mul(self.types.f32_ty, None, ir, r).unwrap();
-        let fg = self.builder.f_mul(self.types.f32_ty, None, ig, g).unwrap();
-        let fb = self.builder.f_mul(self.types.f32_ty, None, ib, b).unwrap();
-        let fa = self.builder.f_mul(self.types.f32_ty, None, ia, a).unwrap();
-        Some(self.construct_vec4(fr, fg, fb, fa))
+        let r = self.extract_component(color,0)?;
+        let g = self.extract_component(color,1)?;
+        let b = self.extract_component(color,2)?;
+        let a = self.extract_component(color,3)?;
+        let inv_r = self.builder.f_sub(self.types.f32_ty, None, one, r)?;
+        let inv_g = self.builder.f_sub(self.types.f32_ty, None, one, g)?;
+        let inv_b = self.builder.f_sub(self.types.f32_ty, None, one, b)?;
+        let fr = self.mix(r, inv_r, amount)?;
+        let fg = self.mix(g, inv_g, amount)?;
+        let fb = self.mix(b, inv_b, amount)?;
+        self.construct_vec4(fr, fg, fb, a)
+    }
+
+    pub fn emit_color(&mut self, input: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        let r = self.get_arg_or_default(args, 0, 1.0)?;
+        let g = self.get_arg_or_default(args, 1, 1.0)?;
+        let b = self.get_arg_or_default(args, 2, 1.0)?;
+        let a = self.get_arg_or_default(args, 3, 1.0)?;
+        let ir = self.extract_component(input,0)?;
+        let ig = self.extract_component(input,1)?;
+        let ib = self.extract_component(input,2)?;
+        let ia = self.extract_component(input,3)?;
+        let fr = self.builder.f_mul(self.types.f32_ty, None, ir, r)?;
+        let fg = self.builder.f_mul(self.types.f32_ty, None, ig, g)?;
+        let fb = self.builder.f_mul(self.types.f32_ty, None, ib, b)?;
+        let fa = self.builder.f_mul(self.types.f32_ty, None, ia, a)?;
+        self.construct_vec4(fr, fg, fb, fa)
     }
 
     // Color space helpers
-    fn hue_rotate(&mut self, color: Word, angle: Word) -> Word {
+    fn hue_rotate(&mut self, color: Word, angle: Word) -> Result<Word, CompileError> {
         // Convert RGB to YIQ, rotate I/Q, convert back.
-        let r = self.extract_component(color, 0);
-        let g = self.extract_component(color, 1);
-        let b = self.extract_component(color, 2);
+        let r = self.extract_component(color, 0)?;
+        let g = self.extract_component(color, 1)?;
+        let b = self.extract_component(color, 2)?;
 
         let y = {
             let w_r = self.emit_f32_constant(0.299);
             let w_g = self.emit_f32_constant(0.587);
             let w_b = self.emit_f32_constant(0.114);
-            let rg = self.builder.f_mul(self.types.f32_ty, None, r, w_r).unwrap();
-            let gg = self.builder.f_mul(self.types.f32_ty, None, g, w_g).unwrap();
-            let bg = self.builder.f_mul(self.types.f32_ty, None, b, w_b).unwrap();
-            let sum = self.builder.f_add(self.types.f32_ty, None, rg, gg).unwrap();
-            self.builder.f_add(self.types.f32_ty, None, sum, bg).unwrap()
+            let rg = self.builder.f_mul(self.types.f32_ty, None, r, w_r)?;
+            let gg = self.builder.f_mul(self.types.f32_ty, None, g, w_g)?;
+            let bg = self.builder.f_mul(self.types.f32_ty, None, b, w_b)?;
+            let sum = self.builder.f_add(self.types.f32_ty, None, rg, gg)?;
+            self.builder.f_add(self.types.f32_ty, None, sum, bg)?
         };
         // I
         let i = {
             let c1 = self.emit_f32_constant(0.596);
             let c2 = self.emit_f32_constant(-0.275);
             let c3 = self.emit_f32_constant(-0.321);
-            let t1 = self.builder.f_mul(self.types.f32_ty, None, r, c1).unwrap();
-            let t2 = self.builder.f_mul(self.types.f32_ty, None, g, c2).unwrap();
-            let t3 = self.builder.f_mul(self.types.f32_ty, None, b, c3).unwrap();
-            let sum = self.builder.f_add(self.types.f32_ty, None, t1, t2).unwrap();
-            self.builder.f_add(self.types.f32_ty, None, sum, t3).unwrap()
+            let t1 = self.builder.f_mul(self.types.f32_ty, None, r, c1)?;
+            let t2 = self.builder.f_mul(self.types.f32_ty, None, g, c2)?;
+            let t3 = self.builder.f_mul(self.types.f32_ty, None, b, c3)?;
+            let sum = self.builder.f_add(self.types.f32_ty, None, t1, t2)?;
+            self.builder.f_add(self.types.f32_ty, None, sum, t3)?
         };
         // Q
         let q = {
             let c1 = self.emit_f32_constant(0.212);
             let c2 = self.emit_f32_constant(-0.523);
             let c3 = self.emit_f32_constant(0.311);
-            let t1 = self.builder.f_mul(self.types.f32_ty, None, r, c1).unwrap();
-            let t2 = self.builder.f_mul(self.types.f32_ty, None, g, c2).unwrap();
-            let t3 = self.builder.f_mul(self.types.f32_ty, None, b, c3).unwrap();
-            let sum = self.builder.f_add(self.types.f32_ty, None, t1, t2).unwrap();
-            self.builder.f_add(self.types.f32_ty, None, sum, t3).unwrap()
+            let t1 = self.builder.f_mul(self.types.f32_ty, None, r, c1)?;
+            let t2 = self.builder.f_mul(self.types.f32_ty, None, g, c2)?;
+            let t3 = self.builder.f_mul(self.types.f32_ty, None, b, c3)?;
+            let sum = self.builder.f_add(self.types.f32_ty, None, t1, t2)?;
+            self.builder.f_add(self.types.f32_ty, None, sum, t3)?
         };
-        let cos_a = self.emit_glsl_cos(angle);
-        let sin_a = self.emit_glsl_sin(angle);
+        let cos_a = self.emit_glsl_cos(angle)?;
+        let sin_a = self.emit_glsl_sin(angle)?;
         let i2 = {
-            let ic = self.builder.f_mul(self.types.f32_ty, None, i, cos_a).unwrap();
-            let qs = self.builder.f_mul(self.types.f32_ty, None, q, sin_a).unwrap();
-            self.builder.f_sub(self.types.f32_ty, None, ic, qs).unwrap()
+            let ic = self.builder.f_mul(self.types.f32_ty, None, i, cos_a)?;
+            let qs = self.builder.f_mul(self.types.f32_ty, None, q, sin_a)?;
+            self.builder.f_sub(self.types.f32_ty, None, ic, qs)?
         };
         let q2 = {
-            let is_ = self.builder.f_mul(self.types.f32_ty, None, i, sin_a).unwrap();
-            let qc = self.builder.f_mul(self.types.f32_ty, None, q, cos_a).unwrap();
-            self.builder.f_add(self.types.f32_ty, None, is_, qc).unwrap()
+            let is_ = self.builder.f_mul(self.types.f32_ty, None, i, sin_a)?;
+            let qc = self.builder.f_mul(self.types.f32_ty, None, q, cos_a)?;
+            self.builder.f_add(self.types.f32_ty, None, is_, qc)?
         };
         // Back to RGB
         let r2 = {
             let c1 = self.emit_f32_constant(0.956);
             let c2 = self.emit_f32_constant(0.621);
-            let t1 = self.builder.f_mul(self.types.f32_ty, None, i2, c1).unwrap();
-            let t2 = self.builder.f_mul(self.types.f32_ty, None, q2, c2).unwrap();
-            let sum = self.builder.f_add(self.types.f32_ty, None, y, t1).unwrap();
-            self.builder.f_add(self.types.f32_ty, None, sum, t2).unwrap()
+            let t1 = self.builder.f_mul(self.types.f32_ty, None, i2, c1)?;
+            let t2 = self.builder.f_mul(self.types.f32_ty, None, q2, c2)?;
+            let sum = self.builder.f_add(self.types.f32_ty, None, y, t1)?;
+            self.builder.f_add(self.types.f32_ty, None, sum, t2)?
         };
         let g2 = {
             let c1 = self.emit_f32_constant(-0.272);
             let c2 = self.emit_f32_constant(-0.647);
-            let t1 = self.builder.f_mul(self.types.f32_ty, None, i2, c1).unwrap();
-            let t2 = self.builder.f_mul(self.types.f32_ty, None, q2, c2).unwrap();
-            let sum = self.builder.f_add(self.types.f32_ty, None, y, t1).unwrap();
-            self.builder.f_add(self.types.f32_ty, None, sum, t2).unwrap()
+            let t1 = self.builder.f_mul(self.types.f32_ty, None, i2, c1)?;
+            let t2 = self.builder.f_mul(self.types.f32_ty, None, q2, c2)?;
+            let sum = self.builder.f_add(self.types.f32_ty, None, y, t1)?;
+            self.builder.f_add(self.types.f32_ty, None, sum, t2)?
         };
         let b2 = {
             let c1 = self.emit_f32_constant(-1.105);
             let c2 = self.emit_f32_constant(1.702);
-            let t1 = self.builder.f_mul(self.types.f32_ty, None, i2, c1).unwrap();
-            let t2 = self.builder.f_mul(self.types.f32_ty, None, q2, c2).unwrap();
-            let sum = self.builder.f_add(self.types.f32_ty, None, y, t1).unwrap();
-            self.builder.f_add(self.types.f32_ty, None, sum, t2).unwrap()
+            let t1 = self.builder.f_mul(self.types.f32_ty, None, i2, c1)?;
+            let t2 = self.builder.f_mul(self.types.f32_ty, None, q2, c2)?;
+            let sum = self.builder.f_add(self.types.f32_ty, None, y, t1)?;
+            self.builder.f_add(self.types.f32_ty, None, sum, t2)?
         };
-        let a = self.extract_component(color, 3);
+        let a = self.extract_component(color, 3)?;
         self.construct_vec4(r2, g2, b2, a)
     }
 
     // Basic adjustments
-    fn contrast_amount(&mut self, color: Word, amount: Word) -> Word {
+    fn contrast_amount(&mut self, color: Word, amount: Word) -> Result<Word, CompileError> {
         // (c-0.5)*amount + 0.5
         let half = self.emit_f32_constant(0.5);
-        let apply = |ctx: &mut SpirvContext, ch: Word| {
-            let sub = ctx.builder.f_sub(ctx.types.f32_ty, None, ch, half).unwrap();
-            let mul = ctx.builder.f_mul(ctx.types.f32_ty, None, sub, amount).unwrap();
-            ctx.builder.f_add(ctx.types.f32_ty, None, mul, half).unwrap()
+        let apply = |ctx: &mut SpirvContext, ch: Word| -> Result<Word, CompileError> {
+            let sub = ctx.builder.f_sub(ctx.types.f32_ty, None, ch, half)?;
+            let mul = ctx.builder.f_mul(ctx.types.f32_ty, None, sub, amount)?;
+            Ok(ctx.builder.f_add(ctx.types.f32_ty, None, mul, half)?)
         };
         self.apply_rgb(color, apply)
     }
 
-    fn brightness_amount(&mut self, color: Word, amount: Word) -> Word {
+    fn brightness_amount(&mut self, color: Word, amount: Word) -> Result<Word, CompileError> {
         // multiply rgb by amount
-        let r = self.extract_component(color, 0);
-        let g = self.extract_component(color, 1);
-        let b = self.extract_component(color, 2);
-        let a = self.extract_component(color, 3);
-        let r2 = self.builder.f_mul(self.types.f32_ty, None, r, amount).unwrap();
-        let g2 = self.builder.f_mul(self.types.f32_ty, None, g, amount).unwrap();
-        let b2 = self.builder.f_mul(self.types.f32_ty, None, b, amount).unwrap();
+        let r = self.extract_component(color, 0)?;
+        let g = self.extract_component(color, 1)?;
+        let b = self.extract_component(color, 2)?;
+        let a = self.extract_component(color, 3)?;
+        let r2 = self.builder.f_mul(self.types.f32_ty, None, r, amount)?;
+        let g2 = self.builder.f_mul(self.types.f32_ty, None, g, amount)?;
+        let b2 = self.builder.f_mul(self.types.f32_ty, None, b, amount)?;
         self.construct_vec4(r2, g2, b2, a)
     }
 
-    pub fn emit_brightness(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
-        let amt = self.get_arg_or_default(call, 0, 1.0);
-        Some(self.brightness_amount(color, amt))
+    pub fn emit_brightness(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        let amt = self.get_arg_or_default(args, 0, 1.0)?;
+        self.brightness_amount(color, amt)
     }
 
-    pub fn emit_contrast(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
-        let amt = self.get_arg_or_default(call, 0, 1.0);
-        Some(self.contrast_amount(color, amt))
+    pub fn emit_contrast(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        let amt = self.get_arg_or_default(args, 0, 1.0)?;
+        self.contrast_amount(color, amt)
     }
 
-    pub fn emit_saturate(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
-        let amt = self.get_arg_or_default(call, 0, 1.0); // 0 -> grayscale, 1 -> original, >1 oversaturate
-        let luma = self.emit_luma(color);
-        let r = self.extract_component(color, 0);
-        let g = self.extract_component(color, 1);
-        let b = self.extract_component(color, 2);
-        let a = self.extract_component(color, 3);
-        // mix(luma, color, amt)
-        let r2 = self.mix(luma, r, amt);
-        let g2 = self.mix(luma, g, amt);
-        let b2 = self.mix(luma, b, amt);
-        Some(self.construct_vec4(r2, g2, b2, a))
+    pub fn emit_saturate(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        let amt = self.get_arg_or_default(args, 0, 1.0)?; // 0 -> grayscale, 1 -> original, >1 oversaturate
+        // Hydra's intensity weights, not the Rec. 601 ones luma() uses
+        let weights = [0.2125, 0.7154, 0.0721].map(|w| self.emit_f32_constant(w));
+        let weights = self.builder.composite_construct(self.types.vec3_ty, None, weights)?;
+        let r = self.extract_component(color, 0)?;
+        let g = self.extract_component(color, 1)?;
+        let b = self.extract_component(color, 2)?;
+        let a = self.extract_component(color, 3)?;
+        let rgb = self.builder.composite_construct(self.types.vec3_ty, None, [r, g, b])?;
+        let intensity = self.builder.dot(self.types.f32_ty, None, rgb, weights)?;
+        // mix(intensity, color, amt)
+        let r2 = self.mix(intensity, r, amt)?;
+        let g2 = self.mix(intensity, g, amt)?;
+        let b2 = self.mix(intensity, b, amt)?;
+        self.construct_vec4(r2, g2, b2, a)
     }
 
-    pub fn emit_posterize(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
-        let levels = self.get_arg_or_default(call, 0, 4.0);
-        let gamma = self.get_arg_or_default(call, 1, 0.6);
+    pub fn emit_posterize(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        let levels = self.get_arg_or_default(args, 0, 4.0)?;
+        let gamma = self.get_arg_or_default(args, 1, 0.6)?;
         let one = self.emit_f32_constant(1.0);
-        let inv_gamma = self.builder.f_div(self.types.f32_ty, None, one, gamma).unwrap();
-        // safe pow -> quantize -> safe pow
-        let linearized = self.apply_rgb(color, |ctx, ch| ctx.safe_pow(ch, inv_gamma));
-        let quantized = self.apply_rgb(linearized, |ctx, ch| ctx.emit_quantize(ch, levels));
-        let restored = self.apply_rgb(quantized, |ctx, ch| ctx.safe_pow(ch, gamma));
-        Some(restored)
-    }
-
-    pub fn emit_thresh(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
-        let threshold = self.get_arg_or_default(call, 0, 0.5);
-        let amount = self.get_arg_or_default(call, 1, 1.0);
-        let r = self.extract_component(color, 0);
-        let g = self.extract_component(color, 1);
-        let b = self.extract_component(color, 2);
-        let a = self.extract_component(color, 3);
+        let zero = self.emit_f32_constant(0.0);
+        let inv_gamma = self.builder.f_div(self.types.f32_ty, None, one, gamma)?;
+        // As in Hydra: pow by gamma, quantize, pow back. Negative channels go to 0 rather than NaN;
+        // values above 1 are left alone
+        let curved = self.apply_rgb(color, |ctx, ch| {
+            let ch = ctx.emit_glsl_fmax(ch, zero)?;
+            ctx.emit_glsl_pow(ch, gamma)
+        })?;
+        let quantized = self.apply_rgb(curved, |ctx, ch| ctx.emit_quantize(ch, levels))?;
+        let restored = self.apply_rgb(quantized, |ctx, ch| ctx.emit_glsl_pow(ch, inv_gamma))?;
+        Ok(restored)
+    }
+
+    pub fn emit_thresh(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        let threshold = self.get_arg_or_default(args, 0, 0.5)?;
+        let amount = self.get_arg_or_default(args, 1, 1.0)?;
+        let r = self.extract_component(color, 0)?;
+        let g = self.extract_component(color, 1)?;
+        let b = self.extract_component(color, 2)?;
+        let a = self.extract_component(color, 3)?;
         let zero = self.emit_f32_constant(0.0);
         let one = self.emit_f32_constant(1.0);
         let th = |ctx: &mut SpirvContext, ch: Word| {
-            let cmp = ctx.builder.f_ord_less_than(ctx.types.bool_ty, None, ch, threshold).unwrap();
-            let bw = ctx.builder.select(ctx.types.f32_ty, None, cmp, zero, one).unwrap();
+            let cmp = ctx.builder.f_ord_less_than(ctx.types.bool_ty, None, ch, threshold)?;
+            let bw = ctx.builder.select(ctx.types.f32_ty, None, cmp, zero, one)?;
             ctx.mix(ch, bw, amount)
         };
-        let r2 = th(self, r);
-        let g2 = th(self, g);
-        let b2 = th(self, b);
-        Some(self.construct_vec4(r2, g2, b2, a))
+        let r2 = th(self, r)?;
+        let g2 = th(self, g)?;
+        let b2 = th(self, b)?;
+        self.construct_vec4(r2, g2, b2, a)
+    }
+
+    pub fn emit_hue(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        // hue(amount=0.4): shifts HSV hue by `amount` turns, as in Hydra
+        let amount = self.get_arg_or_default(args, 0, 0.4)?;
+        let hsv = self.rgb_to_hsv(color)?;
+        let h = self.extract_component(hsv, 0)?;
+        let h = self.builder.f_add(self.types.f32_ty, None, h, amount)?;
+        let hsv = self.builder.composite_insert(self.types.vec3_ty, None, h, hsv, [0])?;
+        let rgb = self.hsv_to_rgb(hsv)?;
+        self.with_alpha(rgb, color)
+    }
+
+    pub fn emit_colorama(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        // colorama(amount=0.005): adds `amount` to h, s and v, converts back and wraps with fract, as in Hydra
+        let amount = self.get_arg_or_default(args, 0, 0.005)?;
+        let hsv = self.rgb_to_hsv(color)?;
+        let amount = self.splat(self.types.vec3_ty, 3, amount)?;
+        let hsv = self.builder.f_add(self.types.vec3_ty, None, hsv, amount)?;
+        let rgb = self.hsv_to_rgb(hsv)?;
+        let rgb = self.emit_glsl_op(self.types.vec3_ty, 10, &[rgb])?; // Fract
+        self.with_alpha(rgb, color)
     }
 
-    pub fn emit_hue(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
-        let angle = self.get_arg_or_default(call, 0, 0.0);
-        Some(self.hue_rotate(color, angle))
+    // vec4(rgb, source.a)
+    fn with_alpha(&mut self, rgb: Word, source: Word) -> Result<Word, CompileError> {
+        Ok(self.builder.vector_shuffle(self.types.vec4_ty, None, rgb, source, [0, 1, 2, 6])?)
     }
 
-    pub fn emit_colorama(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
-        let speed = self.get_arg_or_default(call, 0, 0.005);
-        let time = self.load_time();
-        let angle = self.builder.f_mul(self.types.f32_ty, None, time, speed).unwrap();
-        Some(self.hue_rotate(color, angle))
+    // Hydra's _rgbToHsv: vec4 colour in, vec3 (h, s, v) out
+    fn rgb_to_hsv(&mut self, color: Word) -> Result<Word, CompileError> {
+        let f32_ty = self.types.f32_ty;
+        let vec4_ty = self.types.vec4_ty;
+        // K = vec4(0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0)
+        let k = self.emit_vec_constant(vec4_ty, &[0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0]);
+        let r = self.extract_component(color, 0)?;
+        let g = self.extract_component(color, 1)?;
+        let b = self.extract_component(color, 2)?;
+        // p = mix(vec4(c.bg, K.wz), vec4(c.gb, K.xy), step(c.b, c.g))
+        let bg_kwz = self.builder.vector_shuffle(vec4_ty, None, color, k, [2, 1, 7, 6])?;
+        let gb_kxy = self.builder.vector_shuffle(vec4_ty, None, color, k, [1, 2, 4, 5])?;
+        let t = self.emit_glsl_op(f32_ty, 48, &[b, g])?; // Step
+        let t = self.splat(vec4_ty, 4, t)?;
+        let p = self.emit_glsl_op(vec4_ty, 46, &[bg_kwz, gb_kxy, t])?; // FMix
+        // q = mix(vec4(p.xyw, c.r), vec4(c.r, p.yzx), step(p.x, c.r))
+        let px = self.extract_component(p, 0)?;
+        let py = self.extract_component(p, 1)?;
+        let pz = self.extract_component(p, 2)?;
+        let pw = self.extract_component(p, 3)?;
+        let q_a = self.builder.composite_construct(vec4_ty, None, [px, py, pw, r])?;
+        let q_b = self.builder.composite_construct(vec4_ty, None, [r, py, pz, px])?;
+        let t = self.emit_glsl_op(f32_ty, 48, &[px, r])?;
+        let t = self.splat(vec4_ty, 4, t)?;
+        let q = self.emit_glsl_op(vec4_ty, 46, &[q_a, q_b, t])?;
+        let qx = self.extract_component(q, 0)?;
+        let qy = self.extract_component(q, 1)?;
+        let qz = self.extract_component(q, 2)?;
+        let qw = self.extract_component(q, 3)?;
+        // d = q.x - min(q.w, q.y); e = 1.0e-10
+        let min_wy = self.emit_glsl_fmin(qw, qy)?;
+        let d = self.builder.f_sub(f32_ty, None, qx, min_wy)?;
+        let e = self.emit_f32_constant(1.0e-10);
+        // vec3(abs(q.z + (q.w - q.y) / (6.0 * d + e)), d / (q.x + e), q.x)
+        let six = self.emit_f32_constant(6.0);
+        let six_d = self.builder.f_mul(f32_ty, None, six, d)?;
+        let denom = self.builder.f_add(f32_ty, None, six_d, e)?;
+        let wy = self.builder.f_sub(f32_ty, None, qw, qy)?;
+        let h = self.builder.f_div(f32_ty, None, wy, denom)?;
+        let h = self.builder.f_add(f32_ty, None, qz, h)?;
+        let h = self.emit_glsl_abs(h)?;
+        let qx_e = self.builder.f_add(f32_ty, None, qx, e)?;
+        let s = self.builder.f_div(f32_ty, None, d, qx_e)?;
+        Ok(self.builder.composite_construct(self.types.vec3_ty, None, [h, s, qx])?)
     }
 
-    pub fn emit_luma_effect(&mut self, color: Word, _call: &CallExpr) -> Option<Word> {
-        let l = self.emit_luma(color);
-        let a = self.extract_component(color, 3);
-        let vec = self.construct_vec4(l, l, l, a);
-        Some(vec)
+    // Hydra's _hsvToRgb: vec3 in, vec3 out
+    fn hsv_to_rgb(&mut self, hsv: Word) -> Result<Word, CompileError> {
+        let vec3_ty = self.types.vec3_ty;
+        // K = vec4(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0)
+        let k_xyz = self.emit_vec_constant(vec3_ty, &[1.0, 2.0 / 3.0, 1.0 / 3.0]);
+        let k_www = self.emit_vec_constant(vec3_ty, &[3.0; 3]);
+        let k_xxx = self.emit_vec_constant(vec3_ty, &[1.0; 3]);
+        // p = abs(fract(c.xxx + K.xyz) * 6.0 - K.www)
+        let h = self.extract_component(hsv, 0)?;
+        let s = self.extract_component(hsv, 1)?;
+        let v = self.extract_component(hsv, 2)?;
+        let hhh = self.splat(vec3_ty, 3, h)?;
+        let p = self.builder.f_add(vec3_ty, None, hhh, k_xyz)?;
+        let p = self.emit_glsl_op(vec3_ty, 10, &[p])?; // Fract
+        let six = self.emit_f32_constant(6.0);
+        let p = self.builder.vector_times_scalar(vec3_ty, None, p, six)?;
+        let p = self.builder.f_sub(vec3_ty, None, p, k_www)?;
+        let p = self.emit_glsl_op(vec3_ty, 4, &[p])?; // FAbs
+        // c.z * mix(K.xxx, clamp(p - K.xxx, 0.0, 1.0), c.y)
+        let p = self.builder.f_sub(vec3_ty, None, p, k_xxx)?;
+        let zero3 = self.emit_vec_constant(vec3_ty, &[0.0; 3]);
+        let p = self.emit_glsl_op(vec3_ty, 43, &[p, zero3, k_xxx])?; // FClamp
+        let sss = self.splat(vec3_ty, 3, s)?;
+        let mixed = self.emit_glsl_op(vec3_ty, 46, &[k_xxx, p, sss])?;
+        Ok(self.builder.vector_times_scalar(vec3_ty, None, mixed, v)?)
     }
 
-    pub fn emit_scroll_x(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
-        let x = self.get_arg_or_default(call, 0, 0.0);
-        let speed = self.get_arg_or_default(call, 1, 0.0);
-        let time = self.load_time();
-        let mul = self.builder.f_mul(self.types.f32_ty, None, time, speed).unwrap();
-        let delta = self.builder.f_add(self.types.f32_ty, None, x, mul).unwrap();
+    pub fn emit_luma_effect(&mut self, color: Word, _args: &[Arg]) -> Result<Word, CompileError> {
+        let l = self.emit_luma(color)?;
+        let a = self.extract_component(color, 3)?;
+        let vec = self.construct_vec4(l, l, l, a)?;
+        Ok(vec)
+    }
+
+    pub fn emit_scroll_x(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        let x = self.get_arg_or_default(args, 0, 0.0)?;
+        let speed = self.get_arg_or_default(args, 1, 0.0)?;
+        let time = self.load_time()?;
+        let mul = self.builder.f_mul(self.types.f32_ty, None, time, speed)?;
+        let delta = self.builder.f_add(self.types.f32_ty, None, x, mul)?;
         let one = self.emit_f32_constant(1.0);
-        let factor = self.builder.f_add(self.types.f32_ty, None, one, delta).unwrap();
-        Some(self.brightness_amount(color, factor))
+        let factor = self.builder.f_add(self.types.f32_ty, None, one, delta)?;
+        self.brightness_amount(color, factor)
     }
-    pub fn emit_scroll_y(&mut self, color: Word, call: &CallExpr) -> Option<Word> { self.emit_scroll_x(color, call) }
-    pub fn emit_scroll(&mut self, color: Word, call: &CallExpr) -> Option<Word> { self.emit_scroll_x(color, call) }
+    pub fn emit_scroll_y(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> { self.emit_scroll_x(color, args) }
+    pub fn emit_scroll(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> { self.emit_scroll_x(color, args) }
 
-    pub fn emit_repeat(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
-        let rx = self.get_arg_or_default(call, 0, 3.0);
-        let ry = self.get_arg_or_default(call, 1, 3.0);
-        let sum = self.builder.f_add(self.types.f32_ty, None, rx, ry).unwrap();
+    pub fn emit_repeat(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        let rx = self.get_arg_or_default(args, 0, 3.0)?;
+        let ry = self.get_arg_or_default(args, 1, 3.0)?;
+        let sum = self.builder.f_add(self.types.f32_ty, None, rx, ry)?;
         let half = self.emit_f32_constant(0.5);
-        let avg = self.builder.f_mul(self.types.f32_ty, None, sum, half).unwrap();
-        Some(self.contrast_amount(color, avg))
+        let avg = self.builder.f_mul(self.types.f32_ty, None, sum, half)?;
+        self.contrast_amount(color, avg)
     }
-    pub fn emit_repeat_x(&mut self, color: Word, call: &CallExpr) -> Option<Word> { self.emit_repeat(color, call) }
-    pub fn emit_repeat_y(&mut self, color: Word, call: &CallExpr) -> Option<Word> { self.emit_repeat(color, call) }
+    pub fn emit_repeat_x(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> { self.emit_repeat(color, args) }
+    pub fn emit_repeat_y(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> { self.emit_repeat(color, args) }
 
-    pub fn emit_kaleid(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
-        let sides = self.get_arg_or_default(call, 0, 4.0);
+    pub fn emit_kaleid(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        let sides = self.get_arg_or_default(args, 0, 4.0)?;
         let c = self.emit_f32_constant(0.1);
-        let factor = self.builder.f_mul(self.types.f32_ty, None, sides, c).unwrap();
-        let time = self.load_time();
-        let angle = self.builder.f_mul(self.types.f32_ty, None, time, factor).unwrap();
-        Some(self.hue_rotate(color, angle))
+        let factor = self.builder.f_mul(self.types.f32_ty, None, sides, c)?;
+        let time = self.load_time()?;
+        let angle = self.builder.f_mul(self.types.f32_ty, None, time, factor)?;
+        self.hue_rotate(color, angle)
     }
 
-    pub fn emit_pixelate(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
-        let size_x = self.get_arg_or_default(call, 0, 10.0);
+    pub fn emit_pixelate(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        let size_x = self.get_arg_or_default(args, 0, 10.0)?;
         let levels = size_x; // approximate
         let quant = |ctx: &mut SpirvContext, ch: Word| ctx.emit_quantize(ch, levels);
-        Some(self.apply_rgb(color, quant))
+        self.apply_rgb(color, quant)
     }
 
     // Modulate variants (placeholders applying brightness based on luma(other))
-    pub fn emit_modulate(&mut self, base: Word, other: Word, amount: Word) -> Word { self.binary_modulate(base, other, amount) }
-    pub fn emit_modulate_scale(&mut self, base: Word, other: Word, amount: Word) -> Word { self.binary_modulate(base, other, amount) }
-    pub fn emit_modulate_rotate(&mut self, base: Word, other: Word, amount: Word) -> Word { self.binary_modulate(base, other, amount) }
-    pub fn emit_modulate_repeat(&mut self, base: Word, other: Word, amount: Word) -> Word { self.binary_modulate(base, other, amount) }
-    pub fn emit_modulate_pixelate(&mut self, base: Word, other: Word, amount: Word) -> Word { self.binary_modulate(base, other, amount) }
-    pub fn emit_modulate_hue(&mut self, base: Word, other: Word, amount: Word) -> Word {
-        let l = self.emit_luma(other);
-        let angle = self.builder.f_mul(self.types.f32_ty, None, l, amount).unwrap();
+    pub fn emit_modulate(&mut self, base: Word, other: Word, amount: Word) -> Result<Word, CompileError> { self.binary_modulate(base, other, amount) }
+    pub fn emit_modulate_scale(&mut self, base: Word, other: Word, amount: Word) -> Result<Word, CompileError> { self.binary_modulate(base, other, amount) }
+    pub fn emit_modulate_rotate(&mut self, base: Word, other: Word, amount: Word) -> Result<Word, CompileError> { self.binary_modulate(base, other, amount) }
+    pub fn emit_modulate_repeat(&mut self, base: Word, other: Word, amount: Word) -> Result<Word, CompileError> { self.binary_modulate(base, other, amount) }
+    pub fn emit_modulate_pixelate(&mut self, base: Word, other: Word, amount: Word) -> Result<Word, CompileError> { self.binary_modulate(base, other, amount) }
+    pub fn emit_modulate_hue(&mut self, base: Word, other: Word, amount: Word) -> Result<Word, CompileError> {
+        let l = self.emit_luma(other)?;
+        let angle = self.builder.f_mul(self.types.f32_ty, None, l, amount)?;
         self.hue_rotate(base, angle)
     }
-    pub fn emit_modulate_kaleid(&mut self, base: Word, other: Word, amount: Word) -> Word { self.binary_modulate(base, other, amount) }
-    pub fn emit_modulate_scroll_x(&mut self, base: Word, other: Word, amount: Word) -> Word { self.binary_modulate(base, other, amount) }
-    pub fn emit_modulate_scroll_y(&mut self, base: Word, other: Word, amount: Word) -> Word { self.binary_modulate(base, other, amount) }
+    pub fn emit_modulate_kaleid(&mut self, base: Word, other: Word, amount: Word) -> Result<Word, CompileError> { self.binary_modulate(base, other, amount) }
+    pub fn emit_modulate_scroll_x(&mut self, base: Word, other: Word, amount: Word) -> Result<Word, CompileError> { self.binary_modulate(base, other, amount) }
+    pub fn emit_modulate_scroll_y(&mut self, base: Word, other: Word, amount: Word) -> Result<Word, CompileError> { self.binary_modulate(base, other, amount) }
 
     // Binary operations
-    pub fn binary_add(&mut self, a: Word, b: Word, amount: Word) -> Word { self.binary_mix(a, b, amount, |ctx, x, y| ctx.builder.f_add(ctx.types.f32_ty, None, x, y).unwrap()) }
-    pub fn binary_sub(&mut self, a: Word, b: Word, amount: Word) -> Word { self.binary_mix(a, b, amount, |ctx, x, y| ctx.builder.f_sub(ctx.types.f32_ty, None, x, y).unwrap()) }
-    pub fn binary_mult(&mut self, a: Word, b: Word, amount: Word) -> Word { self.binary_mix(a, b, amount, |ctx, x, y| ctx.builder.f_mul(ctx.types.f32_ty, None, x, y).unwrap()) }
-    pub fn binary_diff(&mut self, a: Word, b: Word) -> Word { self.binary_per_channel(a, b, |ctx, x, y| {
-        // abs(x-y) using |x-y| = sqrt((x-y)^2)
-        let d = ctx.builder.f_sub(ctx.types.f32_ty, None, x, y).unwrap();
-        let d2 = ctx.builder.f_mul(ctx.types.f32_ty, None, d, d).unwrap();
-        ctx.emit_glsl_sqrt(d2)
-    }) }
-    pub fn binary_blend(&mut self, a: Word, b: Word, amount: Word) -> Word { self.binary_mix(a, b, amount, |_ctx, _x, y| y) }
-    pub fn binary_layer(&mut self, a: Word, b: Word) -> Word { let ba = self.extract_component(b, 3); self.binary_mix(a, b, ba, |_ctx, _x, y| y) }
-    pub fn binary_mask(&mut self, a: Word, b: Word) -> Word { // multiply by mask luminance
-        let mask = self.emit_luma(b);
-        let ar = self.extract_component(a, 0);
-        let ag = self.extract_component(a, 1);
-        let ab = self.extract_component(a, 2);
-        let aa = self.extract_component(a, 3);
-        let r2 = self.builder.f_mul(self.types.f32_ty, None, ar, mask).unwrap();
-        let g2 = self.builder.f_mul(self.types.f32_ty, None, ag, mask).unwrap();
-        let b2 = self.builder.f_mul(self.types.f32_ty, None, ab, mask).unwrap();
+    pub fn binary_add(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> { self.binary_mix(a, b, amount, |ctx, x, y| Ok(ctx.builder.f_add(ctx.types.f32_ty, None, x, y)?)) }
+    pub fn binary_sub(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> { self.binary_mix(a, b, amount, |ctx, x, y| Ok(ctx.builder.f_sub(ctx.types.f32_ty, None, x, y)?)) }
+    // Alpha multiplies too, so `.mult(shape())` cuts out the shape
+    pub fn binary_mult(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> {
+        let product = self.builder.f_mul(self.types.vec4_ty, None, a, b)?;
+        self.mix_colors(a, product, amount)
+    }
+    pub fn binary_diff(&mut self, a: Word, b: Word) -> Result<Word, CompileError> {
+        let diff = self.binary_per_channel(a, b, |ctx, x, y| {
+            // abs(x-y) using |x-y| = sqrt((x-y)^2)
+            let d = ctx.builder.f_sub(ctx.types.f32_ty, None, x, y)?;
+            let d2 = ctx.builder.f_mul(ctx.types.f32_ty, None, d, d)?;
+            ctx.emit_glsl_sqrt(d2)
+        })?;
+        let cover = self.cover(a, b)?;
+        Ok(self.builder.composite_construct(self.types.vec4_ty, None, [diff, cover])?)
+    }
+    // Crossfade of every channel, alpha included
+    pub fn binary_blend(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> { self.mix_colors(a, b, amount) }
+    // Hydra's layer: colour mixed by the other chain's alpha
+    pub fn binary_layer(&mut self, a: Word, b: Word) -> Result<Word, CompileError> {
+        let (base, top) = (self.rgb(a)?, self.rgb(b)?);
+        let ba = self.extract_component(b, 3)?;
+        let t = self.splat(self.types.vec3_ty, 3, ba)?;
+        let rgb = self.emit_glsl_op(self.types.vec3_ty, 46, &[base, top, t])?; // FMix
+        let cover = self.cover(a, b)?;
+        Ok(self.builder.composite_construct(self.types.vec4_ty, None, [rgb, cover])?)
+    }
+    pub fn binary_mask(&mut self, a: Word, b: Word) -> Result<Word, CompileError> { // multiply by mask luminance
+        let mask = self.emit_luma(b)?;
+        let ar = self.extract_component(a, 0)?;
+        let ag = self.extract_component(a, 1)?;
+        let ab = self.extract_component(a, 2)?;
+        let aa = self.extract_component(a, 3)?;
+        let r2 = self.builder.f_mul(self.types.f32_ty, None, ar, mask)?;
+        let g2 = self.builder.f_mul(self.types.f32_ty, None, ag, mask)?;
+        let b2 = self.builder.f_mul(self.types.f32_ty, None, ab, mask)?;
         // also attenuate alpha by mask value for proper masking
-        let a2 = self.builder.f_mul(self.types.f32_ty, None, aa, mask).unwrap();
+        let a2 = self.builder.f_mul(self.types.f32_ty, None, aa, mask)?;
         self.construct_vec4(r2, g2, b2, a2)
     }
-    pub fn binary_modulate(&mut self, a: Word, b: Word, amount: Word) -> Word {
+    pub fn binary_modulate(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> {
         // a * (1 + luma(b)*amount)
-        let l = self.emit_luma(b);
-        let scaled = self.builder.f_mul(self.types.f32_ty, None, l, amount).unwrap();
+        let l = self.emit_luma(b)?;
+        let scaled = self.builder.f_mul(self.types.f32_ty, None, l, amount)?;
         let one = self.emit_f32_constant(1.0);
-        let factor = self.builder.f_add(self.types.f32_ty, None, one, scaled).unwrap();
-        let ar = self.extract_component(a,0);
-        let ag = self.extract_component(a,1);
-        let ab = self.extract_component(a,2);
-        let aa = self.extract_component(a,3);
-        let r = self.builder.f_mul(self.types.f32_ty, None, ar, factor).unwrap();
-        let g = self.builder.f_mul(self.types.f32_ty, None, ag, factor).unwrap();
-        let bch = self.builder.f_mul(self.types.f32_ty, None, ab, factor).unwrap();
+        let factor = self.builder.f_add(self.types.f32_ty, None, one, scaled)?;
+        let ar = self.extract_component(a,0)?;
+        let ag = self.extract_component(a,1)?;
+        let ab = self.extract_component(a,2)?;
+        let aa = self.extract_component(a,3)?;
+        let r = self.builder.f_mul(self.types.f32_ty, None, ar, factor)?;
+        let g = self.builder.f_mul(self.types.f32_ty, None, ag, factor)?;
+        let bch = self.builder.f_mul(self.types.f32_ty, None, ab, factor)?;
         self.construct_vec4(r,g,bch,aa)
     }
 
-    fn binary_per_channel<F: Fn(&mut SpirvContext, Word, Word) -> Word>(&mut self, a: Word, b: Word, f: F) -> Word {
-        let ar = self.extract_component(a, 0); let ag = self.extract_component(a, 1); let ab = self.extract_component(a, 2); let aa = self.extract_component(a, 3);
-        let br = self.extract_component(b, 0); let bg = self.extract_component(b, 1); let bb = self.extract_component(b, 2); let ba = self.extract_component(b, 3);
-        let r = f(self, ar, br); let g = f(self, ag, bg); let bch = f(self, ab, bb); let aout = f(self, aa, ba);
-        self.construct_vec4(r, g, bch, aout)
+    // Photoshop-style blend modes, after the W3C compositing spec with `a` as backdrop and `b` as
+    // source. Branches are step()/mix() and divisors are kept off zero, so no lane reads NaN.
+    pub fn binary_screen(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> { self.binary_mix(a, b, amount, |ctx, x, y| {
+        // x + y - x * y
+        let f32_ty = ctx.types.f32_ty;
+        let sum = ctx.builder.f_add(f32_ty, None, x, y)?;
+        let xy = ctx.builder.f_mul(f32_ty, None, x, y)?;
+        Ok(ctx.builder.f_sub(f32_ty, None, sum, xy)?)
+    }) }
+    pub fn binary_overlay(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> { self.binary_mix(a, b, amount, |ctx, x, y| ctx.hard_light(x, y, x)) }
+    pub fn binary_hard_light(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> { self.binary_mix(a, b, amount, |ctx, x, y| ctx.hard_light(x, y, y)) }
+    pub fn binary_soft_light(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> { self.binary_mix(a, b, amount, |ctx, x, y| {
+        let f32_ty = ctx.types.f32_ty;
+        let one = ctx.emit_f32_constant(1.0);
+        let two = ctx.emit_f32_constant(2.0);
+        let quarter = ctx.emit_f32_constant(0.25);
+        let half = ctx.emit_f32_constant(0.5);
+        // d = mix(((16x - 12)x + 4)x, sqrt(max(x, 0)), step(0.25, x))
+        let sixteen = ctx.emit_f32_constant(16.0);
+        let twelve = ctx.emit_f32_constant(12.0);
+        let four = ctx.emit_f32_constant(4.0);
+        let zero = ctx.emit_f32_constant(0.0);
+        let p = ctx.builder.f_mul(f32_ty, None, sixteen, x)?;
+        let p = ctx.builder.f_sub(f32_ty, None, p, twelve)?;
+        let p = ctx.builder.f_mul(f32_ty, None, p, x)?;
+        let p = ctx.builder.f_add(f32_ty, None, p, four)?;
+        let p = ctx.builder.f_mul(f32_ty, None, p, x)?;
+        let root = ctx.emit_glsl_fmax(x, zero)?;
+        let root = ctx.emit_glsl_sqrt(root)?;
+        let t = ctx.emit_step(quarter, x)?;
+        let d = ctx.mix(p, root, t)?;
+        // y < 0.5: x - (1 - 2y) x (1 - x), else x + (2y - 1)(d - x)
+        let y2 = ctx.builder.f_mul(f32_ty, None, two, y)?;
+        let k = ctx.builder.f_sub(f32_ty, None, one, y2)?;
+        let inv_x = ctx.builder.f_sub(f32_ty, None, one, x)?;
+        let lo = ctx.builder.f_mul(f32_ty, None, k, x)?;
+        let lo = ctx.builder.f_mul(f32_ty, None, lo, inv_x)?;
+        let lo = ctx.builder.f_sub(f32_ty, None, x, lo)?;
+        let k = ctx.builder.f_sub(f32_ty, None, y2, one)?;
+        let dx = ctx.builder.f_sub(f32_ty, None, d, x)?;
+        let hi = ctx.builder.f_mul(f32_ty, None, k, dx)?;
+        let hi = ctx.builder.f_add(f32_ty, None, x, hi)?;
+        let t = ctx.emit_step(half, y)?;
+        ctx.mix(lo, hi, t)
+    }) }
+    pub fn binary_color_dodge(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> { self.binary_mix(a, b, amount, |ctx, x, y| {
+        // min(1, x / max(1 - y, 1e-5))
+        let one = ctx.emit_f32_constant(1.0);
+        let eps = ctx.emit_f32_constant(1.0e-5);
+        let inv_y = ctx.builder.f_sub(ctx.types.f32_ty, None, one, y)?;
+        let inv_y = ctx.emit_glsl_fmax(inv_y, eps)?;
+        let q = ctx.builder.f_div(ctx.types.f32_ty, None, x, inv_y)?;
+        ctx.emit_glsl_fmin(one, q)
+    }) }
+    pub fn binary_color_burn(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> { self.binary_mix(a, b, amount, |ctx, x, y| {
+        // 1 - min(1, (1 - x) / max(y, 1e-5))
+        let one = ctx.emit_f32_constant(1.0);
+        let eps = ctx.emit_f32_constant(1.0e-5);
+        let inv_x = ctx.builder.f_sub(ctx.types.f32_ty, None, one, x)?;
+        let y = ctx.emit_glsl_fmax(y, eps)?;
+        let q = ctx.builder.f_div(ctx.types.f32_ty, None, inv_x, y)?;
+        let q = ctx.emit_glsl_fmin(one, q)?;
+        Ok(ctx.builder.f_sub(ctx.types.f32_ty, None, one, q)?)
+    }) }
+    pub fn binary_darken(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> { self.binary_mix(a, b, amount, |ctx, x, y| ctx.emit_glsl_fmin(x, y)) }
+    pub fn binary_lighten(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> { self.binary_mix(a, b, amount, |ctx, x, y| ctx.emit_glsl_fmax(x, y)) }
+    pub fn binary_exclusion(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> { self.binary_mix(a, b, amount, |ctx, x, y| {
+        // x + y - 2xy
+        let f32_ty = ctx.types.f32_ty;
+        let two = ctx.emit_f32_constant(2.0);
+        let sum = ctx.builder.f_add(f32_ty, None, x, y)?;
+        let xy = ctx.builder.f_mul(f32_ty, None, x, y)?;
+        let xy2 = ctx.builder.f_mul(f32_ty, None, two, xy)?;
+        Ok(ctx.builder.f_sub(f32_ty, None, sum, xy2)?)
+    }) }
+
+    // Non-separable modes, on the rgb of both colours with luma's weights for luminosity
+    pub fn binary_hue(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> {
+        // setLum(setSat(b, sat(a)), lum(a))
+        let (base, top) = (self.rgb(a)?, self.rgb(b)?);
+        let s = self.rgb_saturation(base)?;
+        let c = self.set_saturation(top, s)?;
+        let l = self.emit_luma(base)?;
+        let c = self.set_luminosity(c, l)?;
+        self.binary_blend_rgb(a, b, c, amount)
     }
-    fn binary_mix<F: Fn(&mut SpirvContext, Word, Word) -> Word>(&mut self, a: Word, b: Word, amount: Word, f: F) -> Word {
-        let blended = self.binary_per_channel(a, b, f);
-        // result = mix(a, blended, amount)
-        let ar = self.extract_component(a, 0); let ag = self.extract_component(a, 1); let ab = self.extract_component(a, 2); let aa = self.extract_component(a, 3);
-        let br = self.extract_component(blended, 0); let bg = self.extract_component(blended, 1); let bb = self.extract_component(blended, 2); let ba = self.extract_component(blended, 3);
-        let r = self.mix(ar, br, amount); let g = self.mix(ag, bg, amount); let bch = self.mix(ab, bb, amount); let aout = self.mix(aa, ba, amount);
-        self.construct_vec4(r,g,bch,aout)
+    pub fn binary_saturation(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> {
+        // setLum(setSat(a, sat(b)), lum(a))
+        let (base, top) = (self.rgb(a)?, self.rgb(b)?);
+        let s = self.rgb_saturation(top)?;
+        let c = self.set_saturation(base, s)?;
+        let l = self.emit_luma(base)?;
+        let c = self.set_luminosity(c, l)?;
+        self.binary_blend_rgb(a, b, c, amount)
+    }
+    pub fn binary_color(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> {
+        // setLum(b, lum(a))
+        let (base, top) = (self.rgb(a)?, self.rgb(b)?);
+        let l = self.emit_luma(base)?;
+        let c = self.set_luminosity(top, l)?;
+        self.binary_blend_rgb(a, b, c, amount)
+    }
+    pub fn binary_luminosity(&mut self, a: Word, b: Word, amount: Word) -> Result<Word, CompileError> {
+        // setLum(a, lum(b))
+        let (base, top) = (self.rgb(a)?, self.rgb(b)?);
+        let l = self.emit_luma(top)?;
+        let c = self.set_luminosity(base, l)?;
+        self.binary_blend_rgb(a, b, c, amount)
+    }
+
+    // f per colour channel, as a vec3
+    fn binary_per_channel<F: Fn(&mut SpirvContext, Word, Word) -> Result<Word, CompileError>>(&mut self, a: Word, b: Word, f: F) -> Result<Word, CompileError> {
+        let mut rgb = [0; 3];
+        for (i, c) in rgb.iter_mut().enumerate() {
+            let x = self.extract_component(a, i as u32)?;
+            let y = self.extract_component(b, i as u32)?;
+            *c = f(self, x, y)?;
+        }
+        Ok(self.builder.composite_construct(self.types.vec3_ty, None, rgb)?)
+    }
+    // mix(a, vec4(f(a, b), cover(a, b)), amount)
+    fn binary_mix<F: Fn(&mut SpirvContext, Word, Word) -> Result<Word, CompileError>>(&mut self, a: Word, b: Word, amount: Word, f: F) -> Result<Word, CompileError> {
+        let blended = self.binary_per_channel(a, b, f)?;
+        self.binary_blend_rgb(a, b, blended, amount)
+    }
+    fn binary_blend_rgb(&mut self, a: Word, b: Word, blended: Word, amount: Word) -> Result<Word, CompileError> {
+        let cover = self.cover(a, b)?;
+        let blended = self.builder.composite_construct(self.types.vec4_ty, None, [blended, cover])?;
+        self.mix_colors(a, blended, amount)
+    }
+    // Union of both alphas, a + b - ab
+    fn cover(&mut self, a: Word, b: Word) -> Result<Word, CompileError> {
+        let f32_ty = self.types.f32_ty;
+        let aa = self.extract_component(a, 3)?;
+        let ba = self.extract_component(b, 3)?;
+        let both = self.builder.f_mul(f32_ty, None, aa, ba)?;
+        let sum = self.builder.f_add(f32_ty, None, aa, ba)?;
+        Ok(self.builder.f_sub(f32_ty, None, sum, both)?)
+    }
+    // mix(a, b, t) per channel
+    fn mix_colors(&mut self, a: Word, b: Word, t: Word) -> Result<Word, CompileError> {
+        let mut out = [0; 4];
+        for (i, c) in out.iter_mut().enumerate() {
+            let x = self.extract_component(a, i as u32)?;
+            let y = self.extract_component(b, i as u32)?;
+            *c = self.mix(x, y, t)?;
+        }
+        self.construct_vec4(out[0], out[1], out[2], out[3])
+    }
+    // y < 0.5 ? 2xy : 1 - 2(1 - x)(1 - y), switching on `edge` (y for hard light, x for overlay)
+    fn hard_light(&mut self, x: Word, y: Word, edge: Word) -> Result<Word, CompileError> {
+        let f32_ty = self.types.f32_ty;
+        let one = self.emit_f32_constant(1.0);
+        let two = self.emit_f32_constant(2.0);
+        let half = self.emit_f32_constant(0.5);
+        let xy = self.builder.f_mul(f32_ty, None, x, y)?;
+        let lo = self.builder.f_mul(f32_ty, None, two, xy)?;
+        let inv_x = self.builder.f_sub(f32_ty, None, one, x)?;
+        let inv_y = self.builder.f_sub(f32_ty, None, one, y)?;
+        let hi = self.builder.f_mul(f32_ty, None, inv_x, inv_y)?;
+        let hi = self.builder.f_mul(f32_ty, None, two, hi)?;
+        let hi = self.builder.f_sub(f32_ty, None, one, hi)?;
+        let t = self.emit_step(half, edge)?;
+        self.mix(lo, hi, t)
+    }
+
+    fn rgb(&mut self, color: Word) -> Result<Word, CompileError> {
+        Ok(self.builder.vector_shuffle(self.types.vec3_ty, None, color, color, [0, 1, 2])?)
+    }
+    fn min_max3(&mut self, c: Word) -> Result<(Word, Word), CompileError> {
+        let r = self.extract_component(c, 0)?;
+        let g = self.extract_component(c, 1)?;
+        let b = self.extract_component(c, 2)?;
+        let n = self.emit_glsl_fmin(r, g)?;
+        let n = self.emit_glsl_fmin(n, b)?;
+        let x = self.emit_glsl_fmax(r, g)?;
+        let x = self.emit_glsl_fmax(x, b)?;
+        Ok((n, x))
+    }
+    // sat(c) = max - min
+    fn rgb_saturation(&mut self, c: Word) -> Result<Word, CompileError> {
+        let (n, x) = self.min_max3(c)?;
+        Ok(self.builder.f_sub(self.types.f32_ty, None, x, n)?)
+    }
+    // (c - min) * s / (max - min): the spec's setSat without sorting the channels
+    fn set_saturation(&mut self, c: Word, s: Word) -> Result<Word, CompileError> {
+        let f32_ty = self.types.f32_ty;
+        let (n, x) = self.min_max3(c)?;
+        let eps = self.emit_f32_constant(1.0e-5);
+        let range = self.builder.f_sub(f32_ty, None, x, n)?;
+        let range = self.emit_glsl_fmax(range, eps)?;
+        let k = self.builder.f_div(f32_ty, None, s, range)?;
+        let n = self.splat(self.types.vec3_ty, 3, n)?;
+        let c = self.builder.f_sub(self.types.vec3_ty, None, c, n)?;
+        Ok(self.builder.vector_times_scalar(self.types.vec3_ty, None, c, k)?)
+    }
+    // clipColor(c + (l - lum(c)))
+    fn set_luminosity(&mut self, c: Word, l: Word) -> Result<Word, CompileError> {
+        let lum = self.emit_luma(c)?;
+        let d = self.builder.f_sub(self.types.f32_ty, None, l, lum)?;
+        let d = self.splat(self.types.vec3_ty, 3, d)?;
+        let c = self.builder.f_add(self.types.vec3_ty, None, c, d)?;
+        self.clip_color(c)
+    }
+    // Pulls channels below 0 or above 1 back towards the luminosity, keeping it
+    fn clip_color(&mut self, c: Word) -> Result<Word, CompileError> {
+        let (f32_ty, vec3_ty) = (self.types.f32_ty, self.types.vec3_ty);
+        let l = self.emit_luma(c)?;
+        let (n, x) = self.min_max3(c)?;
+        let zero = self.emit_f32_constant(0.0);
+        let one = self.emit_f32_constant(1.0);
+        let eps = self.emit_f32_constant(1.0e-5);
+        let ls = self.splat(vec3_ty, 3, l)?;
+        // c = mix(c, l + (c - l) * (l / max(l - n, 1e-5)), step(n, 0))
+        let room = self.builder.f_sub(f32_ty, None, l, n)?;
+        let room = self.emit_glsl_fmax(room, eps)?;
+        let k = self.builder.f_div(f32_ty, None, l, room)?;
+        let t = self.emit_step(n, zero)?;
+        let c = self.clip_towards(c, ls, k, t)?;
+        // c = mix(c, l + (c - l) * ((1 - l) / max(x - l, 1e-5)), step(1, x))
+        let room = self.builder.f_sub(f32_ty, None, x, l)?;
+        let room = self.emit_glsl_fmax(room, eps)?;
+        let head = self.builder.f_sub(f32_ty, None, one, l)?;
+        let k = self.builder.f_div(f32_ty, None, head, room)?;
+        let t = self.emit_step(one, x)?;
+        self.clip_towards(c, ls, k, t)
+    }
+    fn clip_towards(&mut self, c: Word, ls: Word, k: Word, t: Word) -> Result<Word, CompileError> {
+        let vec3_ty = self.types.vec3_ty;
+        let d = self.builder.f_sub(vec3_ty, None, c, ls)?;
+        let d = self.builder.vector_times_scalar(vec3_ty, None, d, k)?;
+        let clipped = self.builder.f_add(vec3_ty, None, ls, d)?;
+        let t = self.splat(vec3_ty, 3, t)?;
+        self.emit_glsl_op(vec3_ty, 46, &[c, clipped, t]) // FMix
     }
 
-    pub fn emit_shift(&mut self, color: Word, call: &CallExpr) -> Option<Word> {
+    pub fn emit_shift(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
         // shift(rShift=0, gShift=0, bShift=0, aShift=0) simple additive offset then clamp
-        let rs = self.get_arg_or_default(call, 0, 0.0);
-        let gs = self.get_arg_or_default(call, 1, 0.0);
-        let bs = self.get_arg_or_default(call, 2, 0.0);
-        let a_shift = self.get_arg_or_default(call, 3, 0.0);
-        let r = self.extract_component(color,0);
-        let g = self.extract_component(color,1);
-        let b = self.extract_component(color,2);
-        let a = self.extract_component(color,3);
-        let r2_add = self.builder.f_add(self.types.f32_ty, None, r, rs).unwrap();
-        let g2_add = self.builder.f_add(self.types.f32_ty, None, g, gs).unwrap();
-        let b2_add = self.builder.f_add(self.types.f32_ty, None, b, bs).unwrap();
-        let a2_add = self.builder.f_add(self.types.f32_ty, None, a, a_shift).unwrap();
-        let r2 = self.clamp01(r2_add);
-        let g2 = self.clamp01(g2_add);
-        let b2 = self.clamp01(b2_add);
-        let a2 = self.clamp01(a2_add);
-        Some(self.construct_vec4(r2,g2,b2,a2))
+        let rs = self.get_arg_or_default(args, 0, 0.0)?;
+        let gs = self.get_arg_or_default(args, 1, 0.0)?;
+        let bs = self.get_arg_or_default(args, 2, 0.0)?;
+        let a_shift = self.get_arg_or_default(args, 3, 0.0)?;
+        let r = self.extract_component(color,0)?;
+        let g = self.extract_component(color,1)?;
+        let b = self.extract_component(color,2)?;
+        let a = self.extract_component(color,3)?;
+        let r2_add = self.builder.f_add(self.types.f32_ty, None, r, rs)?;
+        let g2_add = self.builder.f_add(self.types.f32_ty, None, g, gs)?;
+        let b2_add = self.builder.f_add(self.types.f32_ty, None, b, bs)?;
+        let a2_add = self.builder.f_add(self.types.f32_ty, None, a, a_shift)?;
+        let r2 = self.clamp01(r2_add)?;
+        let g2 = self.clamp01(g2_add)?;
+        let b2 = self.clamp01(b2_add)?;
+        let a2 = self.clamp01(a2_add)?;
+        self.construct_vec4(r2,g2,b2,a2)
+    }
+
+    pub fn emit_bright_pass(&mut self, color: Word, args: &[Arg]) -> Result<Word, CompileError> {
+        // bloom's max(c.rgb - threshold, 0); alpha 0 so adding the glow leaves alpha alone
+        let threshold = self.get_arg_or_default(args, 0, 0.6)?;
+        let zero = self.emit_f32_constant(0.0);
+        let mut rgb = [zero; 3];
+        for (i, c) in rgb.iter_mut().enumerate() {
+            let v = self.extract_component(color, i as u32)?;
+            let d = self.builder.f_sub(self.types.f32_ty, None, v, threshold)?;
+            *c = self.emit_glsl_fmax(d, zero)?;
+        }
+        self.construct_vec4(rgb[0], rgb[1], rgb[2], zero)
     }
 }
@@ -0,0 +1,188 @@
+// 3D simplex noise, an op-for-op port of `_noise` in hydra-synth's glsl-functions.js
+// (Ian McEwan, Ashima Arts). glsl_direct prints the GLSL original, so keep the two in step.
+
+use rspirv::spirv::Word;
+use super::spirv_context::SpirvContext;
+use crate::error::CompileError;
+
+// GLSL.std.450 instructions
+const FABS: u32 = 4;
+const FLOOR: u32 = 8;
+const FMIN: u32 = 37;
+const FMAX: u32 = 40;
+const STEP: u32 = 48;
+
+impl SpirvContext {
+    // `v` is a vec3; result is a float in roughly -1..1
+    pub fn emit_simplex3(&mut self, v: Word) -> Result<Word, CompileError> {
+        let f32_ty = self.types.f32_ty;
+        let vec3_ty = self.types.vec3_ty;
+        let vec4_ty = self.types.vec4_ty;
+        let c_x = 1.0f32 / 6.0;
+        let c_y = 1.0f32 / 3.0;
+        let c_xxx = self.emit_vec_constant(vec3_ty, &[c_x; 3]);
+        let c_yyy = self.emit_vec_constant(vec3_ty, &[c_y; 3]);
+        let one3 = self.emit_vec_constant(vec3_ty, &[1.0; 3]);
+
+        // First corner
+        let d = self.builder.dot(f32_ty, None, v, c_yyy)?;
+        let d = self.splat(vec3_ty, 3, d)?;
+        let vd = self.builder.f_add(vec3_ty, None, v, d)?;
+        let i = self.emit_glsl_op(vec3_ty, FLOOR, &[vd])?;
+        let v_i = self.builder.f_sub(vec3_ty, None, v, i)?;
+        let d = self.builder.dot(f32_ty, None, i, c_xxx)?;
+        let d = self.splat(vec3_ty, 3, d)?;
+        let x0 = self.builder.f_add(vec3_ty, None, v_i, d)?;
+
+        // Other corners
+        let x0_yzx = self.builder.vector_shuffle(vec3_ty, None, x0, x0, [1, 2, 0])?;
+        let g = self.emit_glsl_op(vec3_ty, STEP, &[x0_yzx, x0])?;
+        let l = self.builder.f_sub(vec3_ty, None, one3, g)?;
+        let l_zxy = self.builder.vector_shuffle(vec3_ty, None, l, l, [2, 0, 1])?;
+        let i1 = self.emit_glsl_op(vec3_ty, FMIN, &[g, l_zxy])?;
+        let i2 = self.emit_glsl_op(vec3_ty, FMAX, &[g, l_zxy])?;
+        let x1 = self.builder.f_sub(vec3_ty, None, x0, i1)?;
+        let x1 = self.builder.f_add(vec3_ty, None, x1, c_xxx)?;
+        let c2 = self.emit_vec_constant(vec3_ty, &[2.0 * c_x; 3]);
+        let x2 = self.builder.f_sub(vec3_ty, None, x0, i2)?;
+        let x2 = self.builder.f_add(vec3_ty, None, x2, c2)?;
+        let c3 = self.emit_vec_constant(vec3_ty, &[3.0 * c_x; 3]);
+        let x3 = self.builder.f_sub(vec3_ty, None, x0, one3)?;
+        let x3 = self.builder.f_add(vec3_ty, None, x3, c3)?;
+
+        // Permutations
+        let i = self.emit_mod_splat(vec3_ty, 3, i, 289.0)?;
+        let p = self.emit_permute_axis(None, i, i1, i2, 2)?;
+        let p = self.emit_permute_axis(Some(p), i, i1, i2, 1)?;
+        let p = self.emit_permute_axis(Some(p), i, i1, i2, 0)?;
+
+        // Gradients: 7x7 points over a square, mapped onto an octahedron
+        let n_ = 1.0f32 / 7.0;
+        let ns_x = self.emit_f32_constant(n_ * 2.0);
+        let ns_y = self.emit_vec_constant(vec4_ty, &[n_ * 0.5 - 1.0; 4]);
+        let ns_z = self.emit_f32_constant(n_);
+        let pz = self.builder.vector_times_scalar(vec4_ty, None, p, ns_z)?;
+        let pzz = self.builder.vector_times_scalar(vec4_ty, None, pz, ns_z)?;
+        let fl = self.emit_glsl_op(vec4_ty, FLOOR, &[pzz])?;
+        let c49 = self.emit_f32_constant(49.0);
+        let fl49 = self.builder.vector_times_scalar(vec4_ty, None, fl, c49)?;
+        let j = self.builder.f_sub(vec4_ty, None, p, fl49)?;
+        let jz = self.builder.vector_times_scalar(vec4_ty, None, j, ns_z)?;
+        let x_ = self.emit_glsl_op(vec4_ty, FLOOR, &[jz])?;
+        let c7 = self.emit_f32_constant(7.0);
+        let x7 = self.builder.vector_times_scalar(vec4_ty, None, x_, c7)?;
+        let jx = self.builder.f_sub(vec4_ty, None, j, x7)?;
+        let y_ = self.emit_glsl_op(vec4_ty, FLOOR, &[jx])?;
+        let x = self.builder.vector_times_scalar(vec4_ty, None, x_, ns_x)?;
+        let x = self.builder.f_add(vec4_ty, None, x, ns_y)?;
+        let y = self.builder.vector_times_scalar(vec4_ty, None, y_, ns_x)?;
+        let y = self.builder.f_add(vec4_ty, None, y, ns_y)?;
+        let one4 = self.emit_vec_constant(vec4_ty, &[1.0; 4]);
+        let abs_x = self.emit_glsl_op(vec4_ty, FABS, &[x])?;
+        let abs_y = self.emit_glsl_op(vec4_ty, FABS, &[y])?;
+        let h = self.builder.f_sub(vec4_ty, None, one4, abs_x)?;
+        let h = self.builder.f_sub(vec4_ty, None, h, abs_y)?;
+
+        let b0 = self.builder.vector_shuffle(vec4_ty, None, x, y, [0, 1, 4, 5])?;
+        let b1 = self.builder.vector_shuffle(vec4_ty, None, x, y, [2, 3, 6, 7])?;
+        let two = self.emit_f32_constant(2.0);
+        let s0 = self.emit_glsl_op(vec4_ty, FLOOR, &[b0])?;
+        let s0 = self.builder.vector_times_scalar(vec4_ty, None, s0, two)?;
+        let s0 = self.builder.f_add(vec4_ty, None, s0, one4)?;
+        let s1 = self.emit_glsl_op(vec4_ty, FLOOR, &[b1])?;
+        let s1 = self.builder.vector_times_scalar(vec4_ty, None, s1, two)?;
+        let s1 = self.builder.f_add(vec4_ty, None, s1, one4)?;
+        let zero4 = self.emit_vec_constant(vec4_ty, &[0.0; 4]);
+        let sh = self.emit_glsl_op(vec4_ty, STEP, &[h, zero4])?;
+        let sh = self.builder.f_negate(vec4_ty, None, sh)?;
+
+        let mut a = Vec::new();
+        for (b, s, sh_swizzle) in [(b0, s0, [0, 0, 1, 1]), (b1, s1, [2, 2, 3, 3])] {
+            let b_xzyw = self.builder.vector_shuffle(vec4_ty, None, b, b, [0, 2, 1, 3])?;
+            let s_xzyw = self.builder.vector_shuffle(vec4_ty, None, s, s, [0, 2, 1, 3])?;
+            let sh_sw = self.builder.vector_shuffle(vec4_ty, None, sh, sh, sh_swizzle)?;
+            let ssh = self.builder.f_mul(vec4_ty, None, s_xzyw, sh_sw)?;
+            a.push(self.builder.f_add(vec4_ty, None, b_xzyw, ssh)?);
+        }
+        let gradients = [
+            self.builder.vector_shuffle(vec3_ty, None, a[0], h, [0, 1, 4])?,
+            self.builder.vector_shuffle(vec3_ty, None, a[0], h, [2, 3, 5])?,
+            self.builder.vector_shuffle(vec3_ty, None, a[1], h, [0, 1, 6])?,
+            self.builder.vector_shuffle(vec3_ty, None, a[1], h, [2, 3, 7])?,
+        ];
+
+        // Normalise gradients (taylorInvSqrt)
+        let mut lengths = Vec::new();
+        for g in gradients { lengths.push(self.builder.dot(f32_ty, None, g, g)?); }
+        let r = self.builder.composite_construct(vec4_ty, None, lengths)?;
+        let k1 = self.emit_vec_constant(vec4_ty, &[1.79284291400159_f64 as f32; 4]);
+        let k2 = self.emit_f32_constant(0.85373472095314_f64 as f32);
+        let kr = self.builder.vector_times_scalar(vec4_ty, None, r, k2)?;
+        let norm = self.builder.f_sub(vec4_ty, None, k1, kr)?;
+        let mut normalised = Vec::new();
+        for (i, g) in gradients.into_iter().enumerate() {
+            let n = self.extract_component(norm, i as u32)?;
+            normalised.push(self.builder.vector_times_scalar(vec3_ty, None, g, n)?);
+        }
+
+        // Mix final noise value
+        let corners = [x0, x1, x2, x3];
+        let mut dists = Vec::new();
+        for c in corners { dists.push(self.builder.dot(f32_ty, None, c, c)?); }
+        let dists = self.builder.composite_construct(vec4_ty, None, dists)?;
+        let c06 = self.emit_vec_constant(vec4_ty, &[0.6; 4]);
+        let m = self.builder.f_sub(vec4_ty, None, c06, dists)?;
+        let m = self.emit_glsl_op(vec4_ty, FMAX, &[m, zero4])?;
+        let m = self.builder.f_mul(vec4_ty, None, m, m)?;
+        let m4 = self.builder.f_mul(vec4_ty, None, m, m)?;
+        let mut contributions = Vec::new();
+        for (g, c) in normalised.into_iter().zip(corners) { contributions.push(self.builder.dot(f32_ty, None, g, c)?); }
+        let contributions = self.builder.composite_construct(vec4_ty, None, contributions)?;
+        let sum = self.builder.dot(f32_ty, None, m4, contributions)?;
+        let c42 = self.emit_f32_constant(42.0);
+        Ok(self.builder.f_mul(f32_ty, None, c42, sum)?)
+    }
+
+    // permute([p +] i.a + vec4(0.0, i1.a, i2.a, 1.0)) for axis a
+    fn emit_permute_axis(&mut self, p: Option<Word>, i: Word, i1: Word, i2: Word, axis: u32) -> Result<Word, CompileError> {
+        let vec4_ty = self.types.vec4_ty;
+        let zero = self.emit_f32_constant(0.0);
+        let one = self.emit_f32_constant(1.0);
+        let i_a = self.extract_component(i, axis)?;
+        let i_a = self.splat(vec4_ty, 4, i_a)?;
+        let i1_a = self.extract_component(i1, axis)?;
+        let i2_a = self.extract_component(i2, axis)?;
+        let offsets = self.builder.composite_construct(vec4_ty, None, [zero, i1_a, i2_a, one])?;
+        let base = match p {
+            Some(p) => self.builder.f_add(vec4_ty, None, p, i_a)?,
+            None => i_a,
+        };
+        let sum = self.builder.f_add(vec4_ty, None, base, offsets)?;
+        self.emit_permute(sum)
+    }
+
+    // mod(((x * 34.0) + 1.0) * x, 289.0) on a vec4
+    fn emit_permute(&mut self, x: Word) -> Result<Word, CompileError> {
+        let vec4_ty = self.types.vec4_ty;
+        let c34 = self.emit_f32_constant(34.0);
+        let one4 = self.emit_vec_constant(vec4_ty, &[1.0; 4]);
+        let t = self.builder.vector_times_scalar(vec4_ty, None, x, c34)?;
+        let t = self.builder.f_add(vec4_ty, None, t, one4)?;
+        let t = self.builder.f_mul(vec4_ty, None, t, x)?;
+        self.emit_mod_splat(vec4_ty, 4, t, 289.0)
+    }
+
+    // GLSL mod(x, y) with a constant y: x - y * floor(x / y)
+    fn emit_mod_splat(&mut self, ty: Word, count: usize, x: Word, y: f32) -> Result<Word, CompileError> {
+        let y_vec = self.emit_vec_constant(ty, &vec![y; count]);
+        let div = self.builder.f_div(ty, None, x, y_vec)?;
+        let fl = self.emit_glsl_op(ty, FLOOR, &[div])?;
+        let mul = self.builder.f_mul(ty, None, y_vec, fl)?;
+        Ok(self.builder.f_sub(ty, None, x, mul)?)
+    }
+
+    // Vector with every component set to the scalar `x`
+    pub fn splat(&mut self, ty: Word, count: usize, x: Word) -> Result<Word, CompileError> {
+        Ok(self.builder.composite_construct(ty, None, vec![x; count])?)
+    }
+}
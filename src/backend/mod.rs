@@ -1,7 +1,23 @@
 pub mod hydra_effects;
+pub mod hydra_noise;
 pub mod hydra_sources;
 
+pub mod glsl_direct;
+
 pub mod spirv_context;
+#[cfg(feature = "spirv-cross")]
+pub mod spirv_cross;
+pub mod spirv_debug;
+pub mod spirv_filter;
+#[cfg(feature = "spirv-cross")]
 pub mod spirv_glsl;
 pub mod spirv_helpers;
+#[cfg(feature = "spirv-cross")]
+pub mod spirv_hlsl;
+#[cfg(feature = "spirv-cross")]
+pub mod spirv_msl;
+pub mod spirv_reflect;
+pub mod spirv_temporal;
+pub mod spirv_texture;
 pub mod spirv_visitor;
+pub mod spirv_wgsl;
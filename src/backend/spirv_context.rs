@@ -3,7 +3,13 @@
 use rspirv::dr::Builder;
 use rspirv::dr::Operand;
 use rspirv::spirv::{self, Word};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use super::spirv_debug::DebugInfo;
+use super::spirv_texture::ExternalTexture;
+use crate::error::CompileError;
+use crate::globals::CUSTOM_SLOTS;
+use crate::ir::Global;
+use crate::output::ColorSpace;
 
 pub struct SpirvContext {
     pub builder: Builder,
@@ -11,6 +17,30 @@ pub struct SpirvContext {
     pub builtins: Builtins,
     pub glsl_ext: Word,
     pub variables: HashMap<String, Word>,
+    pub debug: Option<DebugInfo>,
+    pub aspect: Aspect,
+    // Working space of the sketch; external textures are decoded into it
+    pub color_space: ColorSpace,
+    // Variables per external source slot, see spirv_texture
+    pub externals: BTreeMap<u32, ExternalTexture>,
+    // Texture and sampler variables per intermediate pass target
+    pub passes: BTreeMap<u32, (Word, Word)>,
+    // Texture and sampler variables per pass history ring
+    pub histories: BTreeMap<u32, (Word, Word)>,
+}
+
+// How window pixels map to the `st` coordinate space on non-square windows
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Aspect {
+    // 0..1 on both axes, as in Hydra: shapes stretch and rotations shear
+    #[default]
+    Stretch,
+    // `st` as in Stretch, but rotate/scale/kaleid/shape measure distances from the centre
+    // with x scaled by width / height, so circles stay round
+    Corrected,
+    // Letterboxed square space: the shorter axis spans 0..1 and the longer one extends
+    // equally past both ends
+    Square,
 }
 
 pub struct TypeCache {
@@ -18,6 +48,7 @@ pub struct TypeCache {
     pub f32_ty: Word,
     pub bool_ty: Word,
     pub vec2_ty: Word,
+    pub vec3_ty: Word,
     pub vec4_ty: Word,
 }
 
@@ -28,6 +59,10 @@ pub struct Builtins {
     pub globals_ptr: Word,   // variable pointer
 }
 
+impl Default for SpirvContext {
+    fn default() -> Self { Self::new() }
+}
+
 impl SpirvContext {
     pub fn new() -> Self {
         let mut b = Builder::new();
@@ -39,6 +74,7 @@ impl SpirvContext {
         let f32_ty = b.type_float(32);
         let bool_ty = b.type_bool();
         let vec2_ty = b.type_vector(f32_ty, 2);
+        let vec3_ty = b.type_vector(f32_ty, 3);
         let vec4_ty = b.type_vector(f32_ty, 4);
 
         // Create Input/Output storage class pointers
@@ -58,16 +94,32 @@ impl SpirvContext {
         // GLSL extended instruction set
         let glsl_ext = b.ext_inst_import("GLSL.std.450");
 
-        // Uniform block: struct Globals { vec4 data; } layout(binding=0,set=0)
-        let vec4_struct = b.type_struct(vec![vec4_ty]);
-        // Decorate block & member offset
-        b.decorate(vec4_struct, spirv::Decoration::Block, []);
-        b.member_decorate(vec4_struct, 0, spirv::Decoration::Offset, [Operand::LiteralBit32(0)]);
-        let globals_ptr_ty = b.type_pointer(None, spirv::StorageClass::Uniform, vec4_struct);
+        // Uniform block: struct Globals, std140 at binding=0, set=0 (layout mirrored by crate::globals::Globals)
+        let u32_ty = b.type_int(32, 0);
+        let custom_len = b.constant_bit32(u32_ty, (CUSTOM_SLOTS / 4) as u32);
+        let custom_ty = b.type_array(vec4_ty, custom_len);
+        b.decorate(custom_ty, spirv::Decoration::ArrayStride, [Operand::LiteralBit32(16)]);
+        let members: [(&str, Word, u32); 10] = [
+            ("time", f32_ty, 0), ("frame", f32_ty, 4), ("resolution", vec2_ty, 8), ("mouse", vec2_ty, 16),
+            ("mouseButtons", f32_ty, 24), ("dt", f32_ty, 28), ("fps", f32_ty, 32), ("bpm", f32_ty, 36),
+            ("beat", f32_ty, 40), ("custom", custom_ty, 48),
+        ];
+        let globals_struct = b.type_struct(members.iter().map(|m| m.1));
+        b.decorate(globals_struct, spirv::Decoration::Block, []);
+        for (i, (name, _, offset)) in members.iter().enumerate() {
+            b.member_decorate(globals_struct, i as u32, spirv::Decoration::Offset, [Operand::LiteralBit32(*offset)]);
+            b.member_name(globals_struct, i as u32, *name);
+        }
+        let globals_ptr_ty = b.type_pointer(None, spirv::StorageClass::Uniform, globals_struct);
         let globals_var = b.variable(globals_ptr_ty, None, spirv::StorageClass::Uniform, None);
         b.decorate(globals_var, spirv::Decoration::Binding, [Operand::LiteralBit32(0)]);
         b.decorate(globals_var, spirv::Decoration::DescriptorSet, [Operand::LiteralBit32(0)]);
 
+        // Interface names, kept by spirv-cross in the generated source
+        b.name(globals_struct, "Globals");
+        b.name(globals_var, "globals");
+        b.name(frag_color, "fragColor");
+
         Self {
             builder: b,
             types: TypeCache {
@@ -75,42 +127,168 @@ impl SpirvContext {
                 f32_ty,
                 bool_ty,
                 vec2_ty,
+                vec3_ty,
                 vec4_ty,
             },
             builtins: Builtins {
                 frag_coord,
                 frag_color,
-                globals_block: vec4_struct,
+                globals_block: globals_struct,
                 globals_ptr: globals_var,
             },
             glsl_ext,
             variables: HashMap::new(),
+            debug: None,
+            aspect: Aspect::default(),
+            color_space: ColorSpace::default(),
+            externals: BTreeMap::new(),
+            passes: BTreeMap::new(),
+            histories: BTreeMap::new(),
         }
     }
 
-    pub fn compute_uv(&mut self) -> Word {
-        // Load globals vec4: (time, width, height, pad)
-        let globals_val = self.builder.load(self.builtins.globals_block, None, self.builtins.globals_ptr, None, vec![]).unwrap();
-        let data_vec = self.builder.composite_extract(self.types.vec4_ty, None, globals_val, vec![0]).unwrap();
-        let width = self.builder.composite_extract(self.types.f32_ty, None, data_vec, vec![1]).unwrap();
-        let height = self.builder.composite_extract(self.types.f32_ty, None, data_vec, vec![2]).unwrap();
+    // Working coordinate set by the emitter before calling a source emitter
+    pub fn current_st(&self) -> Result<Word, CompileError> {
+        self.variables.get("_st").copied()
+            .ok_or_else(|| CompileError::InvalidProgram("source emitted without a coordinate".into()))
+    }
+
+    // Loads a Globals member (or one component of it) through an access chain, e.g. [3, 0] for mouse.x.
+    // Whole-block loads break spirv-cross UBO flattening (GLSL ES).
+    fn load_globals_member(&mut self, ty: Word, indices: &[u32]) -> Result<Word, CompileError> {
+        let u32_ty = self.builder.type_int(32, 0);
+        let indices: Vec<Word> = indices.iter().map(|i| self.builder.constant_bit32(u32_ty, *i)).collect();
+        let ptr_ty = self.builder.type_pointer(None, spirv::StorageClass::Uniform, ty);
+        let ptr = self.builder.access_chain(ptr_ty, None, self.builtins.globals_ptr, indices)?;
+        Ok(self.builder.load(ty, None, ptr, None, vec![])?)
+    }
+
+    // Globals::frame as an unsigned integer, for indexing history rings. The block keeps it a float
+    // (GLSL ES flattening needs one basic type); the host wraps it at FRAME_WRAP, so it is exact.
+    pub fn load_frame(&mut self) -> Result<Word, CompileError> {
+        let u32_ty = self.builder.type_int(32, 0);
+        let frame = self.load_globals_member(self.types.f32_ty, &[1])?;
+        Ok(self.builder.convert_f_to_u(u32_ty, None, frame)?)
+    }
 
+    pub fn load_global(&mut self, global: Global) -> Result<Word, CompileError> {
+        let indices: &[u32] = match global {
+            Global::Time => &[0],
+            Global::Frame => &[1],
+            Global::ResolutionX => &[2, 0],
+            Global::ResolutionY => &[2, 1],
+            Global::MouseX => &[3, 0],
+            Global::MouseY => &[3, 1],
+            Global::MouseButtons => &[4],
+            Global::Dt => &[5],
+            Global::Fps => &[6],
+            Global::Bpm => &[7],
+            Global::Beat => &[8],
+            Global::Custom(i) if (i as usize) < CUSTOM_SLOTS => return self.load_globals_member(self.types.f32_ty, &[9, i / 4, i % 4]),
+            Global::Custom(i) => return Err(CompileError::InvalidProgram(format!("custom global {} out of range", i))),
+        };
+        self.load_globals_member(self.types.f32_ty, indices)
+    }
+
+    // `st` of this fragment, or of a point `jitter` pixels from its centre (supersampling)
+    pub fn compute_uv(&mut self, jitter: Option<(f32, f32)>) -> Result<Word, CompileError> {
+        let resolution = self.load_globals_member(self.types.vec2_ty, &[2])?;
         let frag_coord_val = self.builder.load(
             self.types.vec4_ty,
             None,
             self.builtins.frag_coord,
             None,
             vec![],
-        ).unwrap();
+        )?;
         let xy = self.builder.vector_shuffle(
             self.types.vec2_ty,
             None,
             frag_coord_val,
             frag_coord_val,
             vec![0, 1],
-        ).unwrap();
-        // Divide by resolution (width,height)
-        let width_height = self.builder.composite_construct(self.types.vec2_ty, None, vec![width, height]).unwrap();
-        self.builder.f_div(self.types.vec2_ty, None, xy, width_height).unwrap()
+        )?;
+        let xy = match jitter {
+            Some((x, y)) => {
+                let offset = self.emit_vec_constant(self.types.vec2_ty, &[x, y]);
+                self.builder.f_add(self.types.vec2_ty, None, xy, offset)?
+            }
+            None => xy,
+        };
+        if self.aspect != Aspect::Square {
+            // Divide by resolution (width,height)
+            return Ok(self.builder.f_div(self.types.vec2_ty, None, xy, resolution)?);
+        }
+        // (xy - resolution/2) / min(width, height) + 0.5
+        let half = self.emit_f32_constant(0.5);
+        let half_res = self.builder.vector_times_scalar(self.types.vec2_ty, None, resolution, half)?;
+        let centered = self.builder.f_sub(self.types.vec2_ty, None, xy, half_res)?;
+        let (w, h) = self.extract_vec2_components(resolution)?;
+        let side = self.emit_glsl_fmin(w, h)?;
+        let side = self.emit_vec2(side, side)?;
+        let scaled = self.builder.f_div(self.types.vec2_ty, None, centered, side)?;
+        let half2 = self.emit_vec2(half, half)?;
+        Ok(self.builder.f_add(self.types.vec2_ty, None, scaled, half2)?)
+    }
+
+    // Size of one pixel in `st` units, for effects that sample neighbours
+    pub fn pixel_size(&mut self) -> Result<Word, CompileError> {
+        let resolution = self.load_globals_member(self.types.vec2_ty, &[2])?;
+        let one = self.emit_f32_constant(1.0);
+        let ones = self.emit_vec2(one, one)?;
+        if self.aspect != Aspect::Square {
+            return Ok(self.builder.f_div(self.types.vec2_ty, None, ones, resolution)?);
+        }
+        let (w, h) = self.extract_vec2_components(resolution)?;
+        let side = self.emit_glsl_fmin(w, h)?;
+        let side = self.emit_vec2(side, side)?;
+        Ok(self.builder.f_div(self.types.vec2_ty, None, ones, side)?)
+    }
+
+    // Inverse of compute_uv: where `st` lies on a render target covering the window (0..1 on both axes)
+    pub fn st_to_target(&mut self, st: Word) -> Result<Word, CompileError> {
+        if self.aspect != Aspect::Square { return Ok(st); }
+        // ((st - 0.5) * min(width, height) + resolution / 2) / resolution
+        let resolution = self.load_globals_member(self.types.vec2_ty, &[2])?;
+        let half = self.emit_f32_constant(0.5);
+        let half2 = self.emit_vec2(half, half)?;
+        let centered = self.builder.f_sub(self.types.vec2_ty, None, st, half2)?;
+        let (w, h) = self.extract_vec2_components(resolution)?;
+        let side = self.emit_glsl_fmin(w, h)?;
+        let pixels = self.builder.vector_times_scalar(self.types.vec2_ty, None, centered, side)?;
+        let half_res = self.builder.vector_times_scalar(self.types.vec2_ty, None, resolution, half)?;
+        let xy = self.builder.f_add(self.types.vec2_ty, None, pixels, half_res)?;
+        Ok(self.builder.f_div(self.types.vec2_ty, None, xy, resolution)?)
+    }
+
+    // Offset of `coord` from the centre of the window, in units that are equal on both axes
+    // under Aspect::Corrected. Undone by uncenter_coord.
+    pub fn center_coord(&mut self, coord: Word) -> Result<(Word, Word), CompileError> {
+        let (x, y) = self.extract_vec2_components(coord)?;
+        let half = self.emit_f32_constant(0.5);
+        let mut x_c = self.builder.f_sub(self.types.f32_ty, None, x, half)?;
+        let y_c = self.builder.f_sub(self.types.f32_ty, None, y, half)?;
+        if self.aspect == Aspect::Corrected {
+            let ratio = self.load_aspect_ratio()?;
+            x_c = self.builder.f_mul(self.types.f32_ty, None, x_c, ratio)?;
+        }
+        Ok((x_c, y_c))
+    }
+
+    pub fn uncenter_coord(&mut self, x_c: Word, y_c: Word) -> Result<Word, CompileError> {
+        let mut x_c = x_c;
+        if self.aspect == Aspect::Corrected {
+            let ratio = self.load_aspect_ratio()?;
+            x_c = self.builder.f_div(self.types.f32_ty, None, x_c, ratio)?;
+        }
+        let half = self.emit_f32_constant(0.5);
+        let x = self.builder.f_add(self.types.f32_ty, None, x_c, half)?;
+        let y = self.builder.f_add(self.types.f32_ty, None, y_c, half)?;
+        self.emit_vec2(x, y)
+    }
+
+    fn load_aspect_ratio(&mut self) -> Result<Word, CompileError> {
+        let w = self.load_global(Global::ResolutionX)?;
+        let h = self.load_global(Global::ResolutionY)?;
+        Ok(self.builder.f_div(self.types.f32_ty, None, w, h)?)
     }
 }
@@ -0,0 +1,61 @@
+// Shared spirv-cross setup for the GLSL, HLSL and MSL backends
+
+use spirv_cross2::compile::glsl::GlslVersion;
+use spirv_cross2::compile::hlsl::HlslShaderModel;
+use spirv_cross2::compile::msl::MslVersion;
+use spirv_cross2::reflect::{DecorationValue, ResourceType};
+use spirv_cross2::spirv;
+use spirv_cross2::targets::Target;
+use spirv_cross2::{Compiler, Module, SpirvCrossError};
+
+#[derive(Debug, Clone, Copy)]
+pub struct CrossOptions {
+    // Resources are rebound to `set * set_stride + binding` and their descriptor set cleared
+    pub set_stride: u32,
+    pub glsl_version: GlslVersion,
+    pub hlsl_shader_model: HlslShaderModel,
+    pub msl_version: MslVersion,
+}
+
+impl Default for CrossOptions {
+    fn default() -> Self {
+        Self {
+            set_stride: 16,
+            glsl_version: GlslVersion::Glsl460,
+            hlsl_shader_model: HlslShaderModel::ShaderModel5_0,
+            msl_version: MslVersion::new(2, 0, 0),
+        }
+    }
+}
+
+const REMAPPED: [ResourceType; 4] = [
+    ResourceType::UniformBuffer,
+    ResourceType::SampledImage,
+    ResourceType::SeparateImage,
+    ResourceType::SeparateSamplers,
+];
+
+// Parse the module and apply the binding remap shared by every target
+pub fn load<T: Target>(words: &[u32], options: &CrossOptions) -> Result<Compiler<T>, SpirvCrossError> {
+    let mut compiler = Compiler::<T>::new(Module::from_words(words))?;
+    let resources = compiler.shader_resources()?;
+
+    for ty in REMAPPED {
+        for resource in resources.resources_for_type(ty)? {
+            let Some(DecorationValue::Literal(set)) =
+                compiler.decoration(resource.id, spirv::Decoration::DescriptorSet)? else {
+                continue;
+            };
+            let Some(DecorationValue::Literal(binding)) =
+                compiler.decoration(resource.id, spirv::Decoration::Binding)? else {
+                continue;
+            };
+
+            compiler.set_decoration(resource.id, spirv::Decoration::DescriptorSet,
+                                    DecorationValue::unset())?;
+            compiler.set_decoration(resource.id, spirv::Decoration::Binding,
+                                    Some(set * options.set_stride + binding))?;
+        }
+    }
+    Ok(compiler)
+}
@@ -0,0 +1,64 @@
+// SPIR-V debug info: OpString/OpSource for the sketch, OpLine/OpName per IR node
+
+use std::collections::HashSet;
+use rspirv::spirv::{SourceLanguage, Word};
+use swc_common::Span;
+use super::spirv_context::SpirvContext;
+
+// OpSource/OpSourceContinued literals are bounded by the 16-bit instruction word count
+const MAX_SOURCE_CHUNK: usize = 60_000;
+
+pub struct DebugInfo {
+    pub file: Word,
+    line_starts: Vec<usize>,
+    named: HashSet<Word>,
+}
+
+impl DebugInfo {
+    // Spans come from the single-file SourceMap built by hydra_ecma, whose first BytePos is 1
+    fn line_col(&self, span: Span) -> (u32, u32) {
+        let offset = (span.lo.0 as usize).saturating_sub(1);
+        let line = self.line_starts.partition_point(|&start| start <= offset).max(1);
+        let col = offset - self.line_starts[line - 1];
+        (line as u32, col as u32 + 1)
+    }
+}
+
+impl SpirvContext {
+    pub fn emit_debug_source(&mut self, file_name: &str, source: &str) {
+        let file = self.builder.string(file_name);
+        let mut chunks = Vec::new();
+        let mut rest = source;
+        while rest.len() > MAX_SOURCE_CHUNK {
+            let mut cut = MAX_SOURCE_CHUNK;
+            while !rest.is_char_boundary(cut) { cut -= 1; }
+            chunks.push(&rest[..cut]);
+            rest = &rest[cut..];
+        }
+        chunks.push(rest);
+        self.builder.source(SourceLanguage::Unknown, 0, Some(file), Some(chunks[0]));
+        for chunk in &chunks[1..] { self.builder.source_continued(*chunk); }
+
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        self.debug = Some(DebugInfo { file, line_starts, named: HashSet::new() });
+    }
+
+    // OpLine for the instructions that follow; no-op without debug info or for synthesized nodes
+    pub fn emit_span_line(&mut self, span: Span) {
+        let Some(debug) = &self.debug else { return };
+        if span.is_dummy() { return; }
+        let file = debug.file;
+        let (line, col) = debug.line_col(span);
+        self.builder.line(file, line, col);
+    }
+
+    // OpName a node result; results passed through unchanged (e.g. src(o0)) keep their first name
+    pub fn emit_debug_name(&mut self, id: Word, name: &str) {
+        let Some(debug) = &mut self.debug else { return };
+        if debug.named.insert(id) {
+            self.builder.name(id, name);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+// Neighbourhood filters (IRKind::Filter): tap coordinates around `st`, and how the child's colours
+// at those taps combine. The taps normally read an intermediate pass (see ir::split); supersample
+// taps evaluate the child itself.
+
+use rspirv::spirv::Word;
+use super::spirv_context::SpirvContext;
+use crate::error::CompileError;
+use crate::ir::{Arg, FilterType};
+
+// Blur taps per side; a blur(radius) spaces them radius / BLUR_TAPS pixels apart
+pub const BLUR_TAPS: i32 = 4;
+
+// Sample count of a Supersample node; the builder makes it a constant in 1..=MAX_SUPERSAMPLE
+pub fn supersample_count(args: &[Arg]) -> u32 {
+    args.first().and_then(Arg::as_const).unwrap_or(4.0) as u32
+}
+
+// `n` sub-pixel offsets in -0.5..0.5 pixels from the R2 sequence, which spreads any count evenly.
+// The first is the pixel centre.
+pub fn supersample_offsets(n: u32) -> Vec<(f32, f32)> {
+    // 1 / g and 1 / g^2 for the plastic number g
+    const A: (f32, f32) = (0.754_877_7, 0.569_840_3);
+    (0..n).map(|i| ((0.5 + A.0 * i as f32).fract() - 0.5, (0.5 + A.1 * i as f32).fract() - 0.5)).collect()
+}
+
+// Normalised Gaussian weights for taps -BLUR_TAPS..=BLUR_TAPS, sigma half the tap count
+pub fn blur_weights() -> Vec<f32> {
+    let sigma = BLUR_TAPS as f32 / 2.0;
+    let w: Vec<f32> = (-BLUR_TAPS..=BLUR_TAPS).map(|k| (-(k * k) as f32 / (2.0 * sigma * sigma)).exp()).collect();
+    let sum: f32 = w.iter().sum();
+    w.iter().map(|x| x / sum).collect()
+}
+
+// Sobel neighbours: pixel offset (dx, dy) and weights in the x and y kernels
+pub const SOBEL: [(f32, f32, f32, f32); 8] = [
+    (-1.0, -1.0, -1.0, -1.0), (0.0, -1.0, 0.0, -2.0), (1.0, -1.0, 1.0, -1.0),
+    (-1.0, 0.0, -2.0, 0.0), (1.0, 0.0, 2.0, 0.0),
+    (-1.0, 1.0, -1.0, 1.0), (0.0, 1.0, 0.0, 2.0), (1.0, 1.0, 1.0, 1.0),
+];
+
+impl SpirvContext {
+    // Coordinates the child is evaluated at, in the order combine_taps expects
+    pub fn filter_taps(&mut self, ty: FilterType, args: &[Arg], coord: Word) -> Result<Vec<Word>, CompileError> {
+        let mut offsets = Vec::new();
+        match ty {
+            FilterType::BlurX | FilterType::BlurY => {
+                let pixel = self.pixel_size()?;
+                let (px, py) = self.extract_vec2_components(pixel)?;
+                let zero = self.emit_f32_constant(0.0);
+                let radius = self.get_arg_or_default(args, 0, 4.0)?;
+                let axis = if matches!(ty, FilterType::BlurX) { px } else { py };
+                let spacing = self.emit_f32_constant(1.0 / BLUR_TAPS as f32);
+                let step = self.builder.f_mul(self.types.f32_ty, None, axis, radius)?;
+                let step = self.builder.f_mul(self.types.f32_ty, None, step, spacing)?;
+                for k in -BLUR_TAPS..=BLUR_TAPS {
+                    let k = self.emit_f32_constant(k as f32);
+                    let d = self.builder.f_mul(self.types.f32_ty, None, step, k)?;
+                    offsets.push(if matches!(ty, FilterType::BlurX) { self.emit_vec2(d, zero)? } else { self.emit_vec2(zero, d)? });
+                }
+            }
+            FilterType::Edges => {
+                let pixel = self.pixel_size()?;
+                let (px, py) = self.extract_vec2_components(pixel)?;
+                for (dx, dy, _, _) in SOBEL {
+                    let (dx, dy) = (self.emit_f32_constant(dx), self.emit_f32_constant(dy));
+                    let x = self.builder.f_mul(self.types.f32_ty, None, px, dx)?;
+                    let y = self.builder.f_mul(self.types.f32_ty, None, py, dy)?;
+                    offsets.push(self.emit_vec2(x, y)?);
+                }
+            }
+            // Jitter along the screen-space derivatives of `coord`, so the taps stay inside this
+            // pixel whatever transforms sit above the node
+            FilterType::Supersample => {
+                let vec2_ty = self.types.vec2_ty;
+                let ddx = self.builder.d_pdx(vec2_ty, None, coord)?;
+                let ddy = self.builder.d_pdy(vec2_ty, None, coord)?;
+                for (jx, jy) in supersample_offsets(supersample_count(args)) {
+                    let (jx, jy) = (self.emit_f32_constant(jx), self.emit_f32_constant(jy));
+                    let x = self.builder.vector_times_scalar(vec2_ty, None, ddx, jx)?;
+                    let y = self.builder.vector_times_scalar(vec2_ty, None, ddy, jy)?;
+                    offsets.push(self.builder.f_add(vec2_ty, None, x, y)?);
+                }
+            }
+        }
+        let mut taps = Vec::new();
+        for offset in offsets {
+            taps.push(self.builder.f_add(self.types.vec2_ty, None, coord, offset)?);
+        }
+        Ok(taps)
+    }
+
+    pub fn combine_taps(&mut self, ty: FilterType, colors: &[Word]) -> Result<Word, CompileError> {
+        match ty {
+            // (c0 + c1 + ...) / n
+            FilterType::Supersample => {
+                let mut sum = *colors.first().ok_or_else(|| CompileError::InvalidProgram("supersample without taps".into()))?;
+                for &c in &colors[1..] { sum = self.builder.f_add(self.types.vec4_ty, None, sum, c)?; }
+                let scale = self.emit_f32_constant(1.0 / colors.len() as f32);
+                Ok(self.builder.vector_times_scalar(self.types.vec4_ty, None, sum, scale)?)
+            }
+            FilterType::BlurX | FilterType::BlurY => {
+                let mut sum = None;
+                for (&c, w) in colors.iter().zip(blur_weights()) {
+                    let w = self.emit_f32_constant(w);
+                    let term = self.builder.vector_times_scalar(self.types.vec4_ty, None, c, w)?;
+                    sum = Some(match sum {
+                        Some(s) => self.builder.f_add(self.types.vec4_ty, None, s, term)?,
+                        None => term,
+                    });
+                }
+                sum.ok_or_else(|| CompileError::InvalidProgram("blur without taps".into()))
+            }
+            // vec4(vec3(length(vec2(gx, gy))), 1)
+            FilterType::Edges => {
+                let zero = self.emit_f32_constant(0.0);
+                let (mut gx, mut gy) = (zero, zero);
+                for (&c, (_, _, wx, wy)) in colors.iter().zip(SOBEL) {
+                    let l = self.emit_luma(c)?;
+                    if wx != 0.0 {
+                        let w = self.emit_f32_constant(wx);
+                        let t = self.builder.f_mul(self.types.f32_ty, None, l, w)?;
+                        gx = self.builder.f_add(self.types.f32_ty, None, gx, t)?;
+                    }
+                    if wy != 0.0 {
+                        let w = self.emit_f32_constant(wy);
+                        let t = self.builder.f_mul(self.types.f32_ty, None, l, w)?;
+                        gy = self.builder.f_add(self.types.f32_ty, None, gy, t)?;
+                    }
+                }
+                let g = self.emit_vec2(gx, gy)?;
+                let magnitude = self.emit_length2(g)?;
+                let one = self.emit_f32_constant(1.0);
+                self.construct_vec4(magnitude, magnitude, magnitude, one)
+            }
+        }
+    }
+}
@@ -1,40 +1,52 @@
 use spirv_cross2::compile::{CompilableTarget, CompiledArtifact};
-use spirv_cross2::{Compiler, Module, SpirvCrossError};
+use spirv_cross2::{spirv, Compiler, SpirvCrossError};
 use spirv_cross2::compile::glsl::GlslVersion;
 use spirv_cross2::reflect::{DecorationValue, ResourceType};
-use spirv_cross2::spirv;
 use spirv_cross2::targets::Glsl;
+use super::spirv_cross::{load, CrossOptions};
+
+// Plain GLSL has no separate textures and samplers: each texture/sampler pair becomes one
+// `sampler2D` named after the texture (e.g. `s0`), at the texture's binding
+fn combine_image_samplers(compiler: &mut Compiler<Glsl>) -> Result<(), SpirvCrossError> {
+    let proof = compiler.create_dummy_sampler_for_combined_images()?;
+    compiler.build_combined_image_samplers(proof)?;
+    let combined: Vec<_> = compiler.combined_image_samplers()?.collect();
+    for c in combined {
+        if let Some(name) = compiler.name(c.image_id)?.map(|n| n.to_string()) {
+            compiler.set_name(c.combined_id, name.as_str())?;
+        }
+        if let Some(DecorationValue::Literal(binding)) = compiler.decoration(c.image_id, spirv::Decoration::Binding)? {
+            compiler.set_decoration(c.combined_id, spirv::Decoration::Binding, Some(binding))?;
+        }
+    }
+    Ok(())
+}
 
-pub fn compile_to_glsl(words: &[u32]) -> Result<CompiledArtifact<Glsl>, SpirvCrossError> {
-    let module = Module::from_words(words);
+pub fn compile_to_glsl(words: &[u32], cross: &CrossOptions) -> Result<CompiledArtifact<Glsl>, SpirvCrossError> {
+    let mut compiler = load::<Glsl>(words, cross)?;
+    combine_image_samplers(&mut compiler)?;
 
-    let mut compiler = Compiler::<Glsl>::new(module)?;
+    let mut options = Glsl::options();
+    options.version = cross.glsl_version;
 
-    let resources = compiler.shader_resources()?;
+    compiler.compile(&options)
+}
 
-    for resource in resources.resources_for_type(ResourceType::SampledImage)? {
-        let Some(DecorationValue::Literal(set)) =
-            compiler.decoration(resource.id, spirv::Decoration::DescriptorSet)? else {
-            continue;
-        };
-        let Some(DecorationValue::Literal(binding)) =
-            compiler.decoration(resource.id, spirv::Decoration::Binding)? else {
-            continue;
-        };
-
-        println!("Image {} at set = {}, binding = {}", resource.name, set, binding);
-
-        // Modify the decoration to prepare it for GLSL.
-        compiler.set_decoration(resource.id, spirv::Decoration::DescriptorSet,
-                                DecorationValue::unset())?;
-
-        // Some arbitrary remapping if we want.
-        compiler.set_decoration(resource.id, spirv::Decoration::Binding,
-                                Some(set * 16 + binding))?;
+// GLSL ES 3.00 for WebGL2. Every uniform block is flattened into a plain `uniform highp vec4 <Block>[n]`
+// array (n = block size / 16), so a player uploads the block bytes with a single uniform4fv call.
+pub fn compile_to_glsl_es(words: &[u32], cross: &CrossOptions) -> Result<CompiledArtifact<Glsl>, SpirvCrossError> {
+    let mut compiler = load::<Glsl>(words, cross)?;
+
+    let resources = compiler.shader_resources()?;
+    for resource in resources.resources_for_type(ResourceType::UniformBuffer)? {
+        compiler.flatten_buffer_block(resource.id)?;
     }
+    combine_image_samplers(&mut compiler)?;
 
     let mut options = Glsl::options();
-    options.version = GlslVersion::Glsl460;
+    options.version = GlslVersion::Glsl300Es;
+    options.es_default_float_precision_highp = true;
+    options.es_default_int_precision_highp = true;
 
     compiler.compile(&options)
 }
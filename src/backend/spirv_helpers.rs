@@ -3,228 +3,354 @@
 use rspirv::dr::Operand;
 use rspirv::spirv::Word;
 use super::spirv_context::SpirvContext;
+use crate::error::CompileError;
+use crate::ir::Global;
+
+// Rows of the linear Rec.709 -> Rec.2020 matrix (ITU-R BT.2087)
+pub const REC709_TO_REC2020: [[f32; 3]; 3] = [
+    [0.627404, 0.329283, 0.043313],
+    [0.069097, 0.919540, 0.011362],
+    [0.016391, 0.088013, 0.895595],
+];
+
+// SMPTE ST 2084 constants
+pub const PQ_M1: f32 = 2610.0 / 16384.0;
+pub const PQ_M2: f32 = 2523.0 / 4096.0 * 128.0;
+pub const PQ_C1: f32 = 3424.0 / 4096.0;
+pub const PQ_C2: f32 = 2413.0 / 4096.0 * 32.0;
+pub const PQ_C3: f32 = 2392.0 / 4096.0 * 32.0;
 
 impl SpirvContext {
     pub fn emit_f32_constant(&mut self, value: f32) -> Word {
         self.builder.constant_bit32(self.types.f32_ty, value.to_bits())
     }
 
-    pub fn emit_glsl_sin(&mut self, x: Word) -> Word {
-        self.builder.ext_inst(
+    // Float vector constant; `ty` must have values.len() components
+    pub fn emit_vec_constant(&mut self, ty: Word, values: &[f32]) -> Word {
+        let components: Vec<Word> = values.iter().map(|v| self.emit_f32_constant(*v)).collect();
+        self.builder.constant_composite(ty, components)
+    }
+
+    // Any GLSL.std.450 instruction, for vector operands (the emit_glsl_* helpers are scalar only)
+    pub fn emit_glsl_op(&mut self, ty: Word, inst: u32, operands: &[Word]) -> Result<Word, CompileError> {
+        Ok(self.builder.ext_inst(ty, None, self.glsl_ext, inst, operands.iter().map(|w| Operand::IdRef(*w)).collect::<Vec<_>>())?)
+    }
+
+    pub fn emit_glsl_sin(&mut self, x: Word) -> Result<Word, CompileError> {
+        Ok(self.builder.ext_inst(
             self.types.f32_ty,
             None,
             self.glsl_ext,
             13, // Sin opcode in GLSL.std.450
             vec![Operand::IdRef(x)],
-        ).unwrap()
+        )?)
     }
 
-    pub fn emit_glsl_cos(&mut self, x: Word) -> Word {
-        self.builder.ext_inst(
+    pub fn emit_glsl_cos(&mut self, x: Word) -> Result<Word, CompileError> {
+        Ok(self.builder.ext_inst(
             self.types.f32_ty,
             None,
             self.glsl_ext,
             14, // Cos opcode in GLSL.std.450
             vec![Operand::IdRef(x)],
-        ).unwrap()
+        )?)
     }
 
-    pub fn emit_glsl_floor(&mut self, x: Word) -> Word {
-        // Floor opcode 8
+    pub fn emit_glsl_floor(&mut self, x: Word) -> Result<Word, CompileError> {
+        Ok(// Floor opcode 8
         self.builder.ext_inst(
             self.types.f32_ty,
             None,
             self.glsl_ext,
             8,
             vec![Operand::IdRef(x)],
-        ).unwrap()
+        )?)
     }
 
-    pub fn emit_glsl_sqrt(&mut self, x: Word) -> Word {
-        // Sqrt opcode 32
+    pub fn emit_glsl_sqrt(&mut self, x: Word) -> Result<Word, CompileError> {
+        Ok(// Sqrt opcode 31
         self.builder.ext_inst(
             self.types.f32_ty,
             None,
             self.glsl_ext,
-            32,
+            31,
             vec![Operand::IdRef(x)],
-        ).unwrap()
+        )?)
+    }
+
+    // FMin opcode 37, FMax opcode 40 (39/42 are the signed-integer SMin/SMax)
+    pub fn emit_glsl_fmin(&mut self, x: Word, y: Word) -> Result<Word, CompileError> {
+        Ok(self.builder.ext_inst(self.types.f32_ty, None, self.glsl_ext, 37, vec![Operand::IdRef(x), Operand::IdRef(y)])?)
+    }
+
+    pub fn emit_glsl_fmax(&mut self, x: Word, y: Word) -> Result<Word, CompileError> {
+        Ok(self.builder.ext_inst(self.types.f32_ty, None, self.glsl_ext, 40, vec![Operand::IdRef(x), Operand::IdRef(y)])?)
     }
 
-    pub fn emit_step(&mut self, edge: Word, x: Word) -> Word {
+    pub fn emit_step(&mut self, edge: Word, x: Word) -> Result<Word, CompileError> {
         // step(edge, x) -> x < edge ? 0.0 : 1.0 (GLSL defines as x < edge returns 0; else 1)
-        let cmp = self.builder.f_ord_less_than(self.types.bool_ty, None, x, edge).unwrap();
+        let cmp = self.builder.f_ord_less_than(self.types.bool_ty, None, x, edge)?;
         let zero = self.emit_f32_constant(0.0);
         let one = self.emit_f32_constant(1.0);
-        self.builder.select(self.types.f32_ty, None, cmp, zero, one).unwrap()
+        Ok(self.builder.select(self.types.f32_ty, None, cmp, zero, one)?)
     }
 
-    pub fn mix(&mut self, a: Word, b: Word, t: Word) -> Word {
+    pub fn mix(&mut self, a: Word, b: Word, t: Word) -> Result<Word, CompileError> {
         // mix(a, b, t) = a * (1-t) + b * t
         let one = self.emit_f32_constant(1.0);
-        let one_minus_t = self.builder.f_sub(self.types.f32_ty, None, one, t).unwrap();
-        let left = self.builder.f_mul(self.types.f32_ty, None, a, one_minus_t).unwrap();
-        let right = self.builder.f_mul(self.types.f32_ty, None, b, t).unwrap();
-        self.builder.f_add(self.types.f32_ty, None, left, right).unwrap()
+        let one_minus_t = self.builder.f_sub(self.types.f32_ty, None, one, t)?;
+        let left = self.builder.f_mul(self.types.f32_ty, None, a, one_minus_t)?;
+        let right = self.builder.f_mul(self.types.f32_ty, None, b, t)?;
+        Ok(self.builder.f_add(self.types.f32_ty, None, left, right)?)
     }
 
-    pub fn extract_component(&mut self, vec: Word, index: u32) -> Word {
-        self.builder.composite_extract(self.types.f32_ty, None, vec, vec![index]).unwrap()
+    pub fn extract_component(&mut self, vec: Word, index: u32) -> Result<Word, CompileError> {
+        Ok(self.builder.composite_extract(self.types.f32_ty, None, vec, vec![index])?)
     }
 
-    pub fn construct_vec4(&mut self, r: Word, g: Word, b: Word, a: Word) -> Word {
-        self.builder.composite_construct(self.types.vec4_ty, None, vec![r, g, b, a]).unwrap()
+    pub fn construct_vec4(&mut self, r: Word, g: Word, b: Word, a: Word) -> Result<Word, CompileError> {
+        Ok(self.builder.composite_construct(self.types.vec4_ty, None, vec![r, g, b, a])?)
     }
 
-    pub fn load_time(&mut self) -> Word {
-        // Globals struct: member 0 is vec4 (time,width,height,pad)
-        let globals_val = self.builder.load(self.builtins.globals_block, None, self.builtins.globals_ptr, None, vec![]).unwrap();
-        let data_vec = self.builder.composite_extract(self.types.vec4_ty, None, globals_val, vec![0]).unwrap();
-        self.builder.composite_extract(self.types.f32_ty, None, data_vec, vec![0]).unwrap()
+    pub fn load_time(&mut self) -> Result<Word, CompileError> {
+        self.load_global(Global::Time)
     }
 
-    pub fn clamp_vec4(&mut self, v: Word) -> Word {
-        use rspirv::dr::Operand;
+    pub fn clamp_vec4(&mut self, v: Word) -> Result<Word, CompileError> {
         // Clamp each component 0..1 using FMax then FMin
         let zero = self.emit_f32_constant(0.0);
         let one = self.emit_f32_constant(1.0);
-        let r = self.extract_component(v,0); let g = self.extract_component(v,1); let b = self.extract_component(v,2); let a = self.extract_component(v,3);
+        let r = self.extract_component(v,0)?; let g = self.extract_component(v,1)?; let b = self.extract_component(v,2)?; let a = self.extract_component(v,3)?;
         let clamp_comp = |ctx: &mut SpirvContext, c: Word| {
-            let maxv = ctx.builder.ext_inst(ctx.types.f32_ty, None, ctx.glsl_ext, 42, vec![Operand::IdRef(c), Operand::IdRef(zero)]).unwrap();
-            ctx.builder.ext_inst(ctx.types.f32_ty, None, ctx.glsl_ext, 39, vec![Operand::IdRef(maxv), Operand::IdRef(one)]).unwrap()
+            let maxv = ctx.emit_glsl_fmax(c, zero)?;
+            ctx.emit_glsl_fmin(maxv, one)
         };
-        let r2 = clamp_comp(self,r); let g2 = clamp_comp(self,g); let b2 = clamp_comp(self,b);
+        let r2 = clamp_comp(self,r)?; let g2 = clamp_comp(self,g)?; let b2 = clamp_comp(self,b)?;
         self.construct_vec4(r2,g2,b2,a)
     }
 
-    pub fn emit_vec2(&mut self, x: Word, y: Word) -> Word {
-        self.builder.composite_construct(self.types.vec2_ty, None, vec![x, y]).unwrap()
+    // The sRGB transfer function on rgb, alpha untouched:
+    // c <= 0.04045 ? c / 12.92 : pow((c + 0.055) / 1.055, 2.4)
+    pub fn srgb_to_linear(&mut self, color: Word) -> Result<Word, CompileError> {
+        let vec3_ty = self.types.vec3_ty;
+        let c = self.builder.vector_shuffle(vec3_ty, None, color, color, [0, 1, 2])?;
+        let lo = self.emit_vec_constant(vec3_ty, &[1.0 / 12.92; 3]);
+        let lo = self.builder.f_mul(vec3_ty, None, c, lo)?;
+        let offset = self.emit_vec_constant(vec3_ty, &[0.055; 3]);
+        let scale = self.emit_vec_constant(vec3_ty, &[1.0 / 1.055; 3]);
+        let hi = self.builder.f_add(vec3_ty, None, c, offset)?;
+        let hi = self.builder.f_mul(vec3_ty, None, hi, scale)?;
+        let hi = self.srgb_pow(hi, 2.4)?;
+        let edge = self.emit_vec_constant(vec3_ty, &[0.04045; 3]);
+        self.srgb_select(color, lo, hi, edge, c)
+    }
+
+    // Inverse of srgb_to_linear: c <= 0.0031308 ? c * 12.92 : 1.055 * pow(c, 1 / 2.4) - 0.055
+    pub fn linear_to_srgb(&mut self, color: Word) -> Result<Word, CompileError> {
+        let vec3_ty = self.types.vec3_ty;
+        let c = self.builder.vector_shuffle(vec3_ty, None, color, color, [0, 1, 2])?;
+        let lo = self.emit_vec_constant(vec3_ty, &[12.92; 3]);
+        let lo = self.builder.f_mul(vec3_ty, None, c, lo)?;
+        let hi = self.srgb_pow(c, 1.0 / 2.4)?;
+        let scale = self.emit_vec_constant(vec3_ty, &[1.055; 3]);
+        let offset = self.emit_vec_constant(vec3_ty, &[0.055; 3]);
+        let hi = self.builder.f_mul(vec3_ty, None, hi, scale)?;
+        let hi = self.builder.f_sub(vec3_ty, None, hi, offset)?;
+        let edge = self.emit_vec_constant(vec3_ty, &[0.0031308; 3]);
+        self.srgb_select(color, lo, hi, edge, c)
+    }
+
+    // pow(max(x, 0), e), so the unused branch never reads NaN
+    fn srgb_pow(&mut self, x: Word, e: f32) -> Result<Word, CompileError> {
+        let vec3_ty = self.types.vec3_ty;
+        let zero = self.emit_vec_constant(vec3_ty, &[0.0; 3]);
+        let e = self.emit_vec_constant(vec3_ty, &[e; 3]);
+        let x = self.emit_glsl_op(vec3_ty, 40, &[x, zero])?; // FMax
+        self.emit_glsl_op(vec3_ty, 26, &[x, e]) // Pow
+    }
+
+    // vec4(mix(lo, hi, step(edge, c)), color.a)
+    fn srgb_select(&mut self, color: Word, lo: Word, hi: Word, edge: Word, c: Word) -> Result<Word, CompileError> {
+        let vec3_ty = self.types.vec3_ty;
+        let t = self.emit_glsl_op(vec3_ty, 48, &[edge, c])?; // Step
+        let rgb = self.emit_glsl_op(vec3_ty, 46, &[lo, hi, t])?; // FMix
+        Ok(self.builder.vector_shuffle(self.types.vec4_ty, None, rgb, color, [0, 1, 2, 6])?)
+    }
+
+    // Linear light to scRGB: vec4(max(rgb, 0) * scale, a), scale being paper white / 80 nits
+    pub fn scrgb_encode(&mut self, color: Word, scale: f32) -> Result<Word, CompileError> {
+        let vec3_ty = self.types.vec3_ty;
+        let c = self.builder.vector_shuffle(vec3_ty, None, color, color, [0, 1, 2])?;
+        let zero = self.emit_vec_constant(vec3_ty, &[0.0; 3]);
+        let c = self.emit_glsl_op(vec3_ty, 40, &[c, zero])?; // FMax
+        let scale = self.emit_vec_constant(vec3_ty, &[scale; 3]);
+        let c = self.builder.f_mul(vec3_ty, None, c, scale)?;
+        Ok(self.builder.vector_shuffle(self.types.vec4_ty, None, c, color, [0, 1, 2, 6])?)
+    }
+
+    // Linear Rec.709 light to SMPTE ST 2084 (PQ) over Rec.2020, alpha untouched. `scale` is paper
+    // white / 10000 nits; y = min(rec2020(max(rgb, 0)) * scale, 1), then
+    // ((c1 + c2 * y^m1) / (1 + c3 * y^m1))^m2
+    pub fn pq_encode(&mut self, color: Word, scale: f32) -> Result<Word, CompileError> {
+        let (f32_ty, vec3_ty) = (self.types.f32_ty, self.types.vec3_ty);
+        let c = self.builder.vector_shuffle(vec3_ty, None, color, color, [0, 1, 2])?;
+        let zero = self.emit_vec_constant(vec3_ty, &[0.0; 3]);
+        let c = self.emit_glsl_op(vec3_ty, 40, &[c, zero])?; // FMax
+        let mut rows = [0; 3];
+        for (row, coeffs) in rows.iter_mut().zip(REC709_TO_REC2020) {
+            let coeffs = self.emit_vec_constant(vec3_ty, &coeffs);
+            *row = self.builder.dot(f32_ty, None, c, coeffs)?;
+        }
+        let y = self.builder.composite_construct(vec3_ty, None, rows)?;
+        let scale = self.emit_vec_constant(vec3_ty, &[scale; 3]);
+        let y = self.builder.f_mul(vec3_ty, None, y, scale)?;
+        let one = self.emit_vec_constant(vec3_ty, &[1.0; 3]);
+        let y = self.emit_glsl_op(vec3_ty, 37, &[y, one])?; // FMin
+        let m1 = self.emit_vec_constant(vec3_ty, &[PQ_M1; 3]);
+        let p = self.emit_glsl_op(vec3_ty, 26, &[y, m1])?; // Pow
+        let c2 = self.emit_vec_constant(vec3_ty, &[PQ_C2; 3]);
+        let c3 = self.emit_vec_constant(vec3_ty, &[PQ_C3; 3]);
+        let c1 = self.emit_vec_constant(vec3_ty, &[PQ_C1; 3]);
+        let num = self.builder.f_mul(vec3_ty, None, p, c2)?;
+        let num = self.builder.f_add(vec3_ty, None, num, c1)?;
+        let den = self.builder.f_mul(vec3_ty, None, p, c3)?;
+        let den = self.builder.f_add(vec3_ty, None, den, one)?;
+        let q = self.builder.f_div(vec3_ty, None, num, den)?;
+        let m2 = self.emit_vec_constant(vec3_ty, &[PQ_M2; 3]);
+        let e = self.emit_glsl_op(vec3_ty, 26, &[q, m2])?; // Pow
+        Ok(self.builder.vector_shuffle(self.types.vec4_ty, None, e, color, [0, 1, 2, 6])?)
+    }
+
+    // vec4(rgb * a, a) with a clamped to 0..1
+    pub fn premultiply(&mut self, v: Word) -> Result<Word, CompileError> {
+        let zero = self.emit_f32_constant(0.0);
+        let one = self.emit_f32_constant(1.0);
+        let a = self.extract_component(v, 3)?;
+        let a = self.emit_glsl_fmax(a, zero)?;
+        let a = self.emit_glsl_fmin(a, one)?;
+        let rgb = self.builder.vector_shuffle(self.types.vec3_ty, None, v, v, [0, 1, 2])?;
+        let rgb = self.builder.vector_times_scalar(self.types.vec3_ty, None, rgb, a)?;
+        Ok(self.builder.composite_construct(self.types.vec4_ty, None, [rgb, a])?)
+    }
+
+    pub fn emit_vec2(&mut self, x: Word, y: Word) -> Result<Word, CompileError> {
+        Ok(self.builder.composite_construct(self.types.vec2_ty, None, vec![x, y])?)
     }
 
-    pub fn extract_vec2_components(&mut self, v: Word) -> (Word, Word) {
-        let x = self.builder.composite_extract(self.types.f32_ty, None, v, vec![0]).unwrap();
-        let y = self.builder.composite_extract(self.types.f32_ty, None, v, vec![1]).unwrap();
-        (x, y)
+    pub fn extract_vec2_components(&mut self, v: Word) -> Result<(Word, Word), CompileError> {
+        let x = self.builder.composite_extract(self.types.f32_ty, None, v, vec![0])?;
+        let y = self.builder.composite_extract(self.types.f32_ty, None, v, vec![1])?;
+        Ok((x, y))
     }
 
-    pub fn emit_length2(&mut self, v: Word) -> Word {
-        let (x, y) = self.extract_vec2_components(v);
-        let x2 = self.builder.f_mul(self.types.f32_ty, None, x, x).unwrap();
-        let y2 = self.builder.f_mul(self.types.f32_ty, None, y, y).unwrap();
-        let sum = self.builder.f_add(self.types.f32_ty, None, x2, y2).unwrap();
+    pub fn emit_length2(&mut self, v: Word) -> Result<Word, CompileError> {
+        let (x, y) = self.extract_vec2_components(v)?;
+        let x2 = self.builder.f_mul(self.types.f32_ty, None, x, x)?;
+        let y2 = self.builder.f_mul(self.types.f32_ty, None, y, y)?;
+        let sum = self.builder.f_add(self.types.f32_ty, None, x2, y2)?;
         self.emit_glsl_sqrt(sum)
     }
 
-    pub fn clamp01(&mut self, x: Word) -> Word {
+    pub fn clamp01(&mut self, x: Word) -> Result<Word, CompileError> {
         // clamp(x, 0, 1) using min(max(x,0),1)
         let zero = self.emit_f32_constant(0.0);
         let one = self.emit_f32_constant(1.0);
-        // FMax opcode 42, FMin opcode 39
-        let maxv = self.builder.ext_inst(self.types.f32_ty, None, self.glsl_ext, 42, vec![Operand::IdRef(x), Operand::IdRef(zero)]).unwrap();
-        self.builder.ext_inst(self.types.f32_ty, None, self.glsl_ext, 39, vec![Operand::IdRef(maxv), Operand::IdRef(one)]).unwrap()
+        let maxv = self.emit_glsl_fmax(x, zero)?;
+        self.emit_glsl_fmin(maxv, one)
     }
 
-    pub fn emit_luma(&mut self, color: Word) -> Word {
-        let r = self.extract_component(color, 0);
-        let g = self.extract_component(color, 1);
-        let b = self.extract_component(color, 2);
+    pub fn emit_luma(&mut self, color: Word) -> Result<Word, CompileError> {
+        let r = self.extract_component(color, 0)?;
+        let g = self.extract_component(color, 1)?;
+        let b = self.extract_component(color, 2)?;
         let wr = self.emit_f32_constant(0.299);
         let wg = self.emit_f32_constant(0.587);
         let wb = self.emit_f32_constant(0.114);
-        let rg = self.builder.f_mul(self.types.f32_ty, None, r, wr).unwrap();
-        let gg = self.builder.f_mul(self.types.f32_ty, None, g, wg).unwrap();
-        let bg = self.builder.f_mul(self.types.f32_ty, None, b, wb).unwrap();
-        let sum = self.builder.f_add(self.types.f32_ty, None, rg, gg).unwrap();
-        self.builder.f_add(self.types.f32_ty, None, sum, bg).unwrap()
-    }
-
-    pub fn apply_rgb<F: Fn(&mut SpirvContext, Word) -> Word>(&mut self, color: Word, f: F) -> Word {
-        let r = self.extract_component(color, 0);
-        let g = self.extract_component(color, 1);
-        let b = self.extract_component(color, 2);
-        let a = self.extract_component(color, 3);
-        let r2 = f(self, r);
-        let g2 = f(self, g);
-        let b2 = f(self, b);
+        let rg = self.builder.f_mul(self.types.f32_ty, None, r, wr)?;
+        let gg = self.builder.f_mul(self.types.f32_ty, None, g, wg)?;
+        let bg = self.builder.f_mul(self.types.f32_ty, None, b, wb)?;
+        let sum = self.builder.f_add(self.types.f32_ty, None, rg, gg)?;
+        Ok(self.builder.f_add(self.types.f32_ty, None, sum, bg)?)
+    }
+
+    pub fn apply_rgb<F: Fn(&mut SpirvContext, Word) -> Result<Word, CompileError>>(&mut self, color: Word, f: F) -> Result<Word, CompileError> {
+        let r = self.extract_component(color, 0)?;
+        let g = self.extract_component(color, 1)?;
+        let b = self.extract_component(color, 2)?;
+        let a = self.extract_component(color, 3)?;
+        let r2 = f(self, r)?;
+        let g2 = f(self, g)?;
+        let b2 = f(self, b)?;
         self.construct_vec4(r2, g2, b2, a)
     }
 
-    pub fn emit_quantize(&mut self, x: Word, levels: Word) -> Word {
+    pub fn emit_quantize(&mut self, x: Word, levels: Word) -> Result<Word, CompileError> {
         // floor(x * levels)/levels
-        let mul = self.builder.f_mul(self.types.f32_ty, None, x, levels).unwrap();
-        let floored = self.emit_glsl_floor(mul);
-        self.builder.f_div(self.types.f32_ty, None, floored, levels).unwrap()
+        let mul = self.builder.f_mul(self.types.f32_ty, None, x, levels)?;
+        let floored = self.emit_glsl_floor(mul)?;
+        Ok(self.builder.f_div(self.types.f32_ty, None, floored, levels)?)
     }
 
-    pub fn smoothstep(&mut self, edge1: Word, edge2: Word, x: Word) -> Word {
+    pub fn smoothstep(&mut self, edge1: Word, edge2: Word, x: Word) -> Result<Word, CompileError> {
         // GLSL smoothstep(edge1, edge2, x)
         // t = clamp((x - edge1)/(edge2 - edge1), 0,1); return t*t*(3-2*t)
-        let diff1 = self.builder.f_sub(self.types.f32_ty, None, x, edge1).unwrap();
-        let diff_edge = self.builder.f_sub(self.types.f32_ty, None, edge2, edge1).unwrap();
-        let t_raw = self.builder.f_div(self.types.f32_ty, None, diff1, diff_edge).unwrap();
-        let t = self.clamp01(t_raw);
+        let diff1 = self.builder.f_sub(self.types.f32_ty, None, x, edge1)?;
+        let diff_edge = self.builder.f_sub(self.types.f32_ty, None, edge2, edge1)?;
+        let t_raw = self.builder.f_div(self.types.f32_ty, None, diff1, diff_edge)?;
+        let t = self.clamp01(t_raw)?;
         let two = self.emit_f32_constant(2.0);
         let three = self.emit_f32_constant(3.0);
-        let t2 = self.builder.f_mul(self.types.f32_ty, None, t, t).unwrap();
-        let two_t = self.builder.f_mul(self.types.f32_ty, None, two, t).unwrap();
-        let three_minus_2t = self.builder.f_sub(self.types.f32_ty, None, three, two_t).unwrap();
-        self.builder.f_mul(self.types.f32_ty, None, t2, three_minus_2t).unwrap()
+        let t2 = self.builder.f_mul(self.types.f32_ty, None, t, t)?;
+        let two_t = self.builder.f_mul(self.types.f32_ty, None, two, t)?;
+        let three_minus_2t = self.builder.f_sub(self.types.f32_ty, None, three, two_t)?;
+        Ok(self.builder.f_mul(self.types.f32_ty, None, t2, three_minus_2t)?)
     }
 
-    pub fn emit_glsl_abs(&mut self, x: Word) -> Word {
-        // FAbs opcode 4
+    pub fn emit_glsl_abs(&mut self, x: Word) -> Result<Word, CompileError> {
+        Ok(// FAbs opcode 4
         self.builder.ext_inst(
             self.types.f32_ty,
             None,
             self.glsl_ext,
             4,
             vec![Operand::IdRef(x)],
-        ).unwrap()
+        )?)
     }
 
-    pub fn emit_fract(&mut self, x: Word) -> Word {
+    pub fn emit_fract(&mut self, x: Word) -> Result<Word, CompileError> {
         // fract(x) = x - floor(x)
-        let flo = self.emit_glsl_floor(x);
-        self.builder.f_sub(self.types.f32_ty, None, x, flo).unwrap()
+        let flo = self.emit_glsl_floor(x)?;
+        Ok(self.builder.f_sub(self.types.f32_ty, None, x, flo)?)
     }
 
-    pub fn emit_glsl_atan2(&mut self, y: Word, x: Word) -> Word {
+    pub fn emit_glsl_atan2(&mut self, y: Word, x: Word) -> Result<Word, CompileError> {
         use rspirv::dr::Operand;
-        // GLSL.std.450 Atan2 opcode 25
+        Ok(// GLSL.std.450 Atan2 opcode 25
         self.builder.ext_inst(
             self.types.f32_ty,
             None,
             self.glsl_ext,
             25,
             vec![Operand::IdRef(y), Operand::IdRef(x)],
-        ).unwrap()
+        )?)
     }
 
-    pub fn emit_mod_scalar(&mut self, x: Word, y: Word) -> Word {
+    pub fn emit_mod_scalar(&mut self, x: Word, y: Word) -> Result<Word, CompileError> {
         // x - y * floor(x / y)
-        let div = self.builder.f_div(self.types.f32_ty, None, x, y).unwrap();
-        let flo = self.emit_glsl_floor(div);
-        let mul = self.builder.f_mul(self.types.f32_ty, None, flo, y).unwrap();
-        self.builder.f_sub(self.types.f32_ty, None, x, mul).unwrap()
+        let div = self.builder.f_div(self.types.f32_ty, None, x, y)?;
+        let flo = self.emit_glsl_floor(div)?;
+        let mul = self.builder.f_mul(self.types.f32_ty, None, flo, y)?;
+        Ok(self.builder.f_sub(self.types.f32_ty, None, x, mul)?)
     }
 
-    pub fn emit_glsl_pow(&mut self, x: Word, y: Word) -> Word {
-        // Pow opcode 26 (based on existing opcode sequence used)
+    pub fn emit_glsl_pow(&mut self, x: Word, y: Word) -> Result<Word, CompileError> {
+        Ok(// Pow opcode 26 (based on existing opcode sequence used)
         self.builder.ext_inst(
             self.types.f32_ty,
             None,
             self.glsl_ext,
             26,
             vec![Operand::IdRef(x), Operand::IdRef(y)],
-        ).unwrap()
-    }
-
-    pub fn safe_pow(&mut self, x: Word, y: Word) -> Word {
-        use rspirv::dr::Operand;
-        let zero = self.emit_f32_constant(0.0);
-        let one = self.emit_f32_constant(1.0);
-        let maxv = self.builder.ext_inst(self.types.f32_ty, None, self.glsl_ext, 42, vec![Operand::IdRef(x), Operand::IdRef(zero)]).unwrap(); // FMax
-        let clamped = self.builder.ext_inst(self.types.f32_ty, None, self.glsl_ext, 39, vec![Operand::IdRef(maxv), Operand::IdRef(one)]).unwrap(); // FMin
-        self.emit_glsl_pow(clamped, y)
+        )?)
     }
 }
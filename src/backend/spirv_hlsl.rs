@@ -0,0 +1,13 @@
+use spirv_cross2::compile::{CompilableTarget, CompiledArtifact};
+use spirv_cross2::SpirvCrossError;
+use spirv_cross2::targets::Hlsl;
+use super::spirv_cross::{load, CrossOptions};
+
+pub fn compile_to_hlsl(words: &[u32], cross: &CrossOptions) -> Result<CompiledArtifact<Hlsl>, SpirvCrossError> {
+    let compiler = load::<Hlsl>(words, cross)?;
+
+    let mut options = Hlsl::options();
+    options.shader_model = cross.hlsl_shader_model;
+
+    compiler.compile(&options)
+}
@@ -0,0 +1,13 @@
+use spirv_cross2::compile::{CompilableTarget, CompiledArtifact};
+use spirv_cross2::SpirvCrossError;
+use spirv_cross2::targets::Msl;
+use super::spirv_cross::{load, CrossOptions};
+
+pub fn compile_to_msl(words: &[u32], cross: &CrossOptions) -> Result<CompiledArtifact<Msl>, SpirvCrossError> {
+    let compiler = load::<Msl>(words, cross)?;
+
+    let mut options = Msl::options();
+    options.version = cross.msl_version;
+
+    compiler.compile(&options)
+}
@@ -0,0 +1,282 @@
+// Shader interfaces: reflected from SPIR-V via spirv-cross (`reflect`, with the spirv-cross
+// feature), or built from the IR and the fixed binding layout (`interface_from_ir`)
+
+use std::collections::BTreeSet;
+use std::mem::{offset_of, size_of};
+#[cfg(feature = "spirv-cross")]
+use spirv_cross2::reflect::{ArrayDimension, DecorationValue, ImageClass, Resource, ResourceType, ScalarKind, TypeInner};
+#[cfg(feature = "spirv-cross")]
+use spirv_cross2::{Compiler, Module, SpirvCrossError};
+#[cfg(feature = "spirv-cross")]
+use spirv_cross2::spirv;
+#[cfg(feature = "spirv-cross")]
+use spirv_cross2::targets;
+use crate::external;
+use crate::globals::{Globals, CUSTOM_SLOTS};
+use crate::ir::{walk, Arg, IRBuilder, IRKind, NodeId, SourceType, Visitor};
+use crate::passes;
+
+// Everything a host needs to build bind group layouts and render targets for a compiled shader.
+// Set/binding are the SPIR-V decorations, before any CrossOptions remap.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShaderInterface {
+    pub uniform_buffers: Vec<UniformBuffer>,
+    pub textures: Vec<TextureBinding>,
+    pub samplers: Vec<SamplerBinding>,
+    pub outputs: Vec<FragmentOutput>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniformBuffer {
+    pub name: String,
+    pub set: u32,
+    pub binding: u32,
+    pub size: usize,
+    pub members: Vec<UniformMember>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniformMember {
+    pub name: String,
+    pub offset: u32,
+    pub size: usize,
+    pub ty: ValueType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarType { Float, Int, UInt, Bool }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    Scalar(ScalarType),
+    Vector(ScalarType, u32),
+    Matrix { columns: u32, rows: u32 },
+    // Element type is not tracked, only the layout
+    Array { count: u32, stride: u32 },
+    Struct,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureBinding {
+    pub name: String,
+    pub set: u32,
+    pub binding: u32,
+    pub dimension: TextureDimension,
+    // An array of layers, e.g. a pass history (texture2DArray)
+    pub arrayed: bool,
+    // Combined image + sampler (GLSL sampler2D); wgpu needs separate textures and samplers
+    pub combined: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureDimension { D1, D2, D3, Cube, Other }
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SamplerBinding {
+    pub name: String,
+    pub set: u32,
+    pub binding: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FragmentOutput {
+    pub name: String,
+    pub location: u32,
+    pub ty: ValueType,
+}
+
+impl ShaderInterface {
+    pub fn uniform_buffer(&self, name: &str) -> Option<&UniformBuffer> {
+        self.uniform_buffers.iter().find(|b| b.name == name)
+    }
+}
+
+impl UniformBuffer {
+    pub fn member(&self, name: &str) -> Option<&UniformMember> {
+        self.members.iter().find(|m| m.name == name)
+    }
+}
+
+#[cfg(feature = "spirv-cross")]
+fn literal(d: Option<DecorationValue>) -> u32 {
+    d.and_then(|v| v.as_literal()).unwrap_or(0)
+}
+
+#[cfg(feature = "spirv-cross")]
+fn scalar_type(kind: ScalarKind) -> ScalarType {
+    match kind {
+        ScalarKind::Int => ScalarType::Int,
+        ScalarKind::Uint => ScalarType::UInt,
+        ScalarKind::Float => ScalarType::Float,
+        ScalarKind::Bool => ScalarType::Bool,
+    }
+}
+
+#[cfg(feature = "spirv-cross")]
+fn value_type(inner: &TypeInner) -> ValueType {
+    match inner {
+        TypeInner::Scalar(s) => ValueType::Scalar(scalar_type(s.kind)),
+        TypeInner::Vector { width, scalar } => ValueType::Vector(scalar_type(scalar.kind), *width),
+        TypeInner::Matrix { columns, rows, .. } => ValueType::Matrix { columns: *columns, rows: *rows },
+        TypeInner::Array { dimensions, stride, .. } => {
+            // Spec-constant sized arrays report 0
+            let count = dimensions.iter()
+                .map(|d| match d { ArrayDimension::Literal(n) => *n, _ => 0 })
+                .product();
+            ValueType::Array { count, stride: stride.unwrap_or(0) }
+        }
+        TypeInner::Struct(_) => ValueType::Struct,
+        _ => ValueType::Other,
+    }
+}
+
+#[cfg(feature = "spirv-cross")]
+fn set_binding(compiler: &Compiler<targets::None>, resource: &Resource) -> Result<(u32, u32), SpirvCrossError> {
+    Ok((
+        literal(compiler.decoration(resource.id, spirv::Decoration::DescriptorSet)?),
+        literal(compiler.decoration(resource.id, spirv::Decoration::Binding)?),
+    ))
+}
+
+#[cfg(feature = "spirv-cross")]
+pub fn reflect(words: &[u32]) -> Result<ShaderInterface, SpirvCrossError> {
+    let compiler = Compiler::<targets::None>::new(Module::from_words(words))?;
+    let resources = compiler.shader_resources()?;
+
+    let mut interface = ShaderInterface::default();
+    for resource in resources.resources_for_type(ResourceType::UniformBuffer)? {
+        let (set, binding) = set_binding(&compiler, &resource)?;
+        let ty = compiler.type_description(resource.base_type_id)?;
+        let mut members = Vec::new();
+        if let TypeInner::Struct(block) = &ty.inner {
+            for member in &block.members {
+                members.push(UniformMember {
+                    name: member.name.as_ref().map(|n| n.to_string()).unwrap_or_default(),
+                    offset: member.offset,
+                    size: member.size,
+                    ty: value_type(&compiler.type_description(member.id)?.inner),
+                });
+            }
+        }
+        interface.uniform_buffers.push(UniformBuffer {
+            name: resource.name.to_string(),
+            set,
+            binding,
+            size: ty.size_hint.declared(),
+            members,
+        });
+    }
+
+    for (ty, combined) in [(ResourceType::SampledImage, true), (ResourceType::SeparateImage, false)] {
+        for resource in resources.resources_for_type(ty)? {
+            let (set, binding) = set_binding(&compiler, &resource)?;
+            let (dimension, arrayed) = match compiler.type_description(resource.base_type_id)?.inner {
+                TypeInner::Image(image) => (
+                    match image.dimension {
+                        spirv::Dim::Dim1D => TextureDimension::D1,
+                        spirv::Dim::Dim2D => TextureDimension::D2,
+                        spirv::Dim::Dim3D => TextureDimension::D3,
+                        spirv::Dim::DimCube => TextureDimension::Cube,
+                        _ => TextureDimension::Other,
+                    },
+                    matches!(image.class, ImageClass::Sampled { arrayed: true, .. } | ImageClass::Texture { arrayed: true, .. }),
+                ),
+                _ => (TextureDimension::Other, false),
+            };
+            interface.textures.push(TextureBinding { name: resource.name.to_string(), set, binding, dimension, arrayed, combined });
+        }
+    }
+
+    for resource in resources.resources_for_type(ResourceType::SeparateSamplers)? {
+        let (set, binding) = set_binding(&compiler, &resource)?;
+        interface.samplers.push(SamplerBinding { name: resource.name.to_string(), set, binding });
+    }
+
+    for resource in resources.resources_for_type(ResourceType::StageOutput)? {
+        interface.outputs.push(FragmentOutput {
+            name: resource.name.to_string(),
+            location: literal(compiler.decoration(resource.id, spirv::Decoration::Location)?),
+            ty: value_type(&compiler.type_description(resource.base_type_id)?.inner),
+        });
+    }
+    Ok(interface)
+}
+
+// Every texture and sampler the graph under `root` reads, by slot, pass and history ring
+#[derive(Default)]
+struct Bindings {
+    externals: BTreeSet<u32>,
+    passes: BTreeSet<u32>,
+    histories: BTreeSet<u32>,
+}
+
+impl Visitor for Bindings {
+    fn leave(&mut self, ir: &IRBuilder, id: NodeId) {
+        match &ir.node(id).kind {
+            IRKind::Source { ty: SourceType::External, args } => { self.externals.insert(index(args)); }
+            IRKind::Source { ty: SourceType::Pass, args } => { self.passes.insert(index(args)); }
+            IRKind::Temporal { history: Some(pass), .. } => { self.histories.insert(*pass); }
+            _ => {}
+        }
+    }
+}
+
+// Slot or pass index, the first argument of `src(s0)` and pass sources
+fn index(args: &[Arg]) -> u32 {
+    args.first().and_then(Arg::as_const).unwrap_or(0.0) as u32
+}
+
+// The std140 block every shader declares, laid out as crate::globals::Globals
+fn globals_block() -> UniformBuffer {
+    let float = ValueType::Scalar(ScalarType::Float);
+    let vec2 = ValueType::Vector(ScalarType::Float, 2);
+    let custom = ValueType::Array { count: (CUSTOM_SLOTS / 4) as u32, stride: 16 };
+    let members = [
+        ("time", offset_of!(Globals, time), 4, float),
+        ("frame", offset_of!(Globals, frame), 4, float),
+        ("resolution", offset_of!(Globals, resolution), 8, vec2),
+        ("mouse", offset_of!(Globals, mouse), 8, vec2),
+        ("mouseButtons", offset_of!(Globals, mouse_buttons), 4, float),
+        ("dt", offset_of!(Globals, dt), 4, float),
+        ("fps", offset_of!(Globals, fps), 4, float),
+        ("bpm", offset_of!(Globals, bpm), 4, float),
+        ("beat", offset_of!(Globals, beat), 4, float),
+        ("custom", offset_of!(Globals, custom), CUSTOM_SLOTS * 4, custom),
+    ];
+    UniformBuffer {
+        name: "Globals".into(),
+        set: 0,
+        binding: 0,
+        size: size_of::<Globals>(),
+        members: members.into_iter()
+            .map(|(name, offset, size, ty)| UniformMember { name: name.into(), offset: offset as u32, size, ty })
+            .collect(),
+    }
+}
+
+// The interface of a shader drawing the graph under `root`, as `reflect` reports it for the
+// SPIR-V (up to the order of the textures), but without emitting or parsing any: the Globals block,
+// `fragColor`, and the bindings from crate::external and crate::passes for what the graph reads
+pub fn interface_from_ir(ir: &IRBuilder, root: NodeId) -> ShaderInterface {
+    let mut bindings = Bindings::default();
+    walk(&mut bindings, ir, root);
+
+    let mut interface = ShaderInterface { uniform_buffers: vec![globals_block()], ..Default::default() };
+    let mut bind = |texture: String, texture_binding: u32, sampler: String, sampler_binding: u32, arrayed: bool| {
+        interface.textures.push(TextureBinding { name: texture, set: 0, binding: texture_binding, dimension: TextureDimension::D2, arrayed, combined: false });
+        interface.samplers.push(SamplerBinding { name: sampler, set: 0, binding: sampler_binding });
+    };
+    for &slot in &bindings.externals {
+        bind(external::texture_name(slot), external::texture_binding(slot), external::sampler_name(slot), external::sampler_binding(slot), false);
+    }
+    for &index in &bindings.passes {
+        bind(passes::texture_name(index), passes::texture_binding(index), passes::sampler_name(index), passes::sampler_binding(index), false);
+    }
+    for &index in &bindings.histories {
+        bind(passes::history_texture_name(index), passes::history_texture_binding(index),
+             passes::history_sampler_name(index), passes::history_sampler_binding(index), true);
+    }
+    interface.outputs.push(FragmentOutput { name: "fragColor".into(), location: 0, ty: ValueType::Vector(ScalarType::Float, 4) });
+    interface
+}
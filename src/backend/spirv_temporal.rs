@@ -0,0 +1,45 @@
+// Temporal effects (IRKind::Temporal): the child's colour this frame combined with past frames of
+// a pass, read from its history ring (see crate::passes and ir::split).
+
+use rspirv::spirv::Word;
+use super::spirv_context::SpirvContext;
+use crate::error::CompileError;
+use crate::ir::{Arg, TemporalType};
+
+impl SpirvContext {
+    // `current` is the child at `st`; `index` and `ring` the pass and ring size it reads history from
+    pub fn emit_temporal(&mut self, ty: TemporalType, args: &[Arg], index: u32, ring: u32, current: Word, st: Word) -> Result<Word, CompileError> {
+        let count = |i: usize| args.get(i).and_then(Arg::as_const).unwrap_or(4.0) as u32;
+        match ty {
+            // max(c, decay * last)
+            TemporalType::Trails => {
+                let decay = self.get_arg_or_default(args, 0, 0.9)?;
+                let last = self.emit_history(index, 1, ring, st)?;
+                let faded = self.builder.vector_times_scalar(self.types.vec4_ty, None, last, decay)?;
+                self.emit_glsl_op(self.types.vec4_ty, 40, &[current, faded]) // FMax
+            }
+            // max over k of copy k * (1 - k / frames)
+            TemporalType::Echo => {
+                let (frames, spacing) = (count(0), count(1));
+                let mut color = current;
+                for k in 1..frames {
+                    let copy = self.emit_history(index, k * spacing, ring, st)?;
+                    let fade = self.emit_f32_constant(1.0 - k as f32 / frames as f32);
+                    let faded = self.builder.vector_times_scalar(self.types.vec4_ty, None, copy, fade)?;
+                    color = self.emit_glsl_op(self.types.vec4_ty, 40, &[color, faded])?; // FMax
+                }
+                Ok(color)
+            }
+            TemporalType::MotionBlur => {
+                let samples = count(0);
+                let mut sum = current;
+                for k in 1..samples {
+                    let past = self.emit_history(index, k, ring, st)?;
+                    sum = self.builder.f_add(self.types.vec4_ty, None, sum, past)?;
+                }
+                let scale = self.emit_f32_constant(1.0 / samples.max(1) as f32);
+                Ok(self.builder.vector_times_scalar(self.types.vec4_ty, None, sum, scale)?)
+            }
+        }
+    }
+}
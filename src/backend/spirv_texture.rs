@@ -0,0 +1,170 @@
+// Sampled textures: external sources (`src(s0)`, see crate::external), intermediate pass targets
+// and their history rings (see crate::passes), each a separate texture and sampler variable at set 0
+
+use rspirv::dr::Operand;
+use rspirv::spirv::{self, Word};
+use super::spirv_context::SpirvContext;
+use crate::error::CompileError;
+use crate::external::{self, Wrap};
+use crate::ir::Arg;
+use crate::output::ColorSpace;
+use crate::passes;
+
+pub struct ExternalTexture {
+    pub texture: Word,
+    pub sampler: Word,
+    pub wrap: [Wrap; 2],
+}
+
+impl SpirvContext {
+    // History rings are 2D arrays, one layer per frame
+    fn image_ty(&mut self, arrayed: bool) -> Word {
+        self.builder.type_image(self.types.f32_ty, spirv::Dim::Dim2D, 0, arrayed as u32, 0, 1, spirv::ImageFormat::Unknown, None)
+    }
+
+    // Module-scope variables, so this has to run before any function is begun
+    fn declare_texture(&mut self, bindings: (u32, u32), names: (String, String), arrayed: bool) -> (Word, Word) {
+        let image_ty = self.image_ty(arrayed);
+        let sampler_ty = self.builder.type_sampler();
+        let b = &mut self.builder;
+        let image_ptr = b.type_pointer(None, spirv::StorageClass::UniformConstant, image_ty);
+        let sampler_ptr = b.type_pointer(None, spirv::StorageClass::UniformConstant, sampler_ty);
+        let texture = b.variable(image_ptr, None, spirv::StorageClass::UniformConstant, None);
+        let sampler = b.variable(sampler_ptr, None, spirv::StorageClass::UniformConstant, None);
+        for (var, binding, name) in [(texture, bindings.0, names.0), (sampler, bindings.1, names.1)] {
+            b.decorate(var, spirv::Decoration::DescriptorSet, [Operand::LiteralBit32(0)]);
+            b.decorate(var, spirv::Decoration::Binding, [Operand::LiteralBit32(binding)]);
+            b.name(var, name);
+        }
+        (texture, sampler)
+    }
+
+    pub fn declare_external(&mut self, slot: u32, wrap: [Wrap; 2]) {
+        if self.externals.contains_key(&slot) { return; }
+        let (texture, sampler) = self.declare_texture(
+            (external::texture_binding(slot), external::sampler_binding(slot)),
+            (external::texture_name(slot), external::sampler_name(slot)),
+            false,
+        );
+        self.externals.insert(slot, ExternalTexture { texture, sampler, wrap });
+    }
+
+    pub fn declare_pass(&mut self, index: u32) {
+        if self.passes.contains_key(&index) { return; }
+        let vars = self.declare_texture(
+            (passes::texture_binding(index), passes::sampler_binding(index)),
+            (passes::texture_name(index), passes::sampler_name(index)),
+            false,
+        );
+        self.passes.insert(index, vars);
+    }
+
+    pub fn declare_history(&mut self, index: u32) {
+        if self.histories.contains_key(&index) { return; }
+        let vars = self.declare_texture(
+            (passes::history_texture_binding(index), passes::history_sampler_binding(index)),
+            (passes::history_texture_name(index), passes::history_sampler_name(index)),
+            true,
+        );
+        self.histories.insert(index, vars);
+    }
+
+    // Texture and sampler variables of every declared slot, pass and history, for the entry point interface
+    pub fn texture_interface(&self) -> Vec<Word> {
+        self.externals.values().flat_map(|e| [e.texture, e.sampler])
+            .chain(self.passes.values().chain(self.histories.values()).flat_map(|&(t, s)| [t, s]))
+            .collect()
+    }
+
+    // `coord` is a vec3 (st, layer) for arrayed textures
+    fn sample(&mut self, arrayed: bool, texture: Word, sampler: Word, coord: Word) -> Result<Word, CompileError> {
+        let image_ty = self.image_ty(arrayed);
+        let sampler_ty = self.builder.type_sampler();
+        let sampled_ty = self.builder.type_sampled_image(image_ty);
+        let image = self.builder.load(image_ty, None, texture, None, [])?;
+        let sampler = self.builder.load(sampler_ty, None, sampler, None, [])?;
+        let sampled = self.builder.sampled_image(sampled_ty, None, image, sampler)?;
+        Ok(self.builder.image_sample_implicit_lod(self.types.vec4_ty, None, sampled, coord, None, [])?)
+    }
+
+    // src(s0): the slot's texture at st, addressed per axis (Repeat is Hydra's `texture2D(tex, fract(_st))`)
+    pub fn emit_external(&mut self, args: &[Arg]) -> Result<Word, CompileError> {
+        let slot = args.first().and_then(Arg::as_const).unwrap_or(0.0) as u32;
+        let (texture, sampler, wrap) = self.externals.get(&slot)
+            .map(|e| (e.texture, e.sampler, e.wrap))
+            .ok_or_else(|| CompileError::InvalidProgram(format!("external source s{} sampled but not declared", slot)))?;
+        let st = self.current_st()?;
+        let (x, y) = self.extract_vec2_components(st)?;
+        let (ax, mask_x) = self.emit_address(x, wrap[0])?;
+        let (ay, mask_y) = self.emit_address(y, wrap[1])?;
+        let coord = self.emit_vec2(ax, ay)?;
+        let color = self.sample(false, texture, sampler, coord)?;
+        // Images hold sRGB values
+        let color = match self.color_space {
+            ColorSpace::Srgb => color,
+            ColorSpace::Linear => self.srgb_to_linear(color)?,
+        };
+        let mask = match (mask_x, mask_y) {
+            (Some(mx), Some(my)) => Some(self.builder.f_mul(self.types.f32_ty, None, mx, my)?),
+            (m, None) | (None, m) => m,
+        };
+        match mask {
+            Some(mask) => Ok(self.builder.vector_times_scalar(self.types.vec4_ty, None, color, mask)?),
+            None => Ok(color),
+        }
+    }
+
+    // A pass target at st; the host's clamp-to-edge sampler handles coordinates off the target
+    pub fn emit_pass(&mut self, args: &[Arg]) -> Result<Word, CompileError> {
+        let index = args.first().and_then(Arg::as_const).unwrap_or(0.0) as u32;
+        let &(texture, sampler) = self.passes.get(&index)
+            .ok_or_else(|| CompileError::InvalidProgram(format!("pass {} sampled but not declared", index)))?;
+        let st = self.current_st()?;
+        let uv = self.st_to_target(st)?;
+        self.sample(false, texture, sampler, uv)
+    }
+
+    // Pass `index` as it was `frames_ago` frames back, from layer `(frame - frames_ago) % ring`.
+    // Integer math on the wrapped frame (see globals::FRAME_WRAP); `ring - frames_ago % ring` is
+    // added instead of subtracting so early frames do not wrap around u32.
+    pub fn emit_history(&mut self, index: u32, frames_ago: u32, ring: u32, st: Word) -> Result<Word, CompileError> {
+        let &(texture, sampler) = self.histories.get(&index)
+            .ok_or_else(|| CompileError::InvalidProgram(format!("history of pass {} sampled but not declared", index)))?;
+        let u32_ty = self.builder.type_int(32, 0);
+        let frame = self.load_frame()?;
+        let ring_const = self.builder.constant_bit32(u32_ty, ring);
+        let back = self.builder.constant_bit32(u32_ty, ring - frames_ago % ring);
+        let current = self.builder.u_mod(u32_ty, None, frame, ring_const)?;
+        let shifted = self.builder.i_add(u32_ty, None, current, back)?;
+        let layer_index = self.builder.u_mod(u32_ty, None, shifted, ring_const)?;
+        let layer = self.builder.convert_u_to_f(self.types.f32_ty, None, layer_index)?;
+        let uv = self.st_to_target(st)?;
+        let (u, v) = self.extract_vec2_components(uv)?;
+        let coord = self.builder.composite_construct(self.types.vec3_ty, None, [u, v, layer])?;
+        self.sample(true, texture, sampler, coord)
+    }
+
+    // Addressed coordinate on one axis, plus a 0/1 inside mask for Border
+    fn emit_address(&mut self, x: Word, wrap: Wrap) -> Result<(Word, Option<Word>), CompileError> {
+        Ok(match wrap {
+            Wrap::Repeat => (self.emit_fract(x)?, None),
+            Wrap::Clamp => (self.clamp01(x)?, None),
+            // 1 - |mod(x, 2) - 1|
+            Wrap::Mirror => {
+                let (one, two) = (self.emit_f32_constant(1.0), self.emit_f32_constant(2.0));
+                let m = self.emit_mod_scalar(x, two)?;
+                let d = self.builder.f_sub(self.types.f32_ty, None, m, one)?;
+                let a = self.emit_glsl_abs(d)?;
+                (self.builder.f_sub(self.types.f32_ty, None, one, a)?, None)
+            }
+            // step(0, x) * step(x, 1)
+            Wrap::Border => {
+                let (zero, one) = (self.emit_f32_constant(0.0), self.emit_f32_constant(1.0));
+                let above = self.emit_step(zero, x)?;
+                let below = self.emit_step(x, one)?;
+                let mask = self.builder.f_mul(self.types.f32_ty, None, above, below)?;
+                (self.clamp01(x)?, Some(mask))
+            }
+        })
+    }
+}
@@ -3,23 +3,131 @@
 use rspirv::binary::Assemble;
 use rspirv::spirv::{self, Word};
 use swc_ecma_ast::*;
-use swc_common::{DUMMY_SP, SyntaxContext};
-use swc_atoms::Atom;
+use std::collections::HashMap;
 use crate::ir::*;
-use crate::backend::spirv_context::SpirvContext;
+use crate::backend::spirv_context::{Aspect, SpirvContext};
+use crate::backend::spirv_filter::supersample_offsets;
+use crate::error::CompileError;
+use crate::output::{AlphaMode, ColorSpace, OutputTransform};
+use crate::post_process::{PostProcess, ToneMap};
 
 pub struct SpirvEmitter {
     context: SpirvContext,
     current_function: Option<Word>,
+    // When set, every IR node is emitted as its own named SPIR-V function called from main
+    node_functions: bool,
+    functions: HashMap<NodeId, Word>,
+    post_process: PostProcess,
+    alpha: AlphaMode,
+    output_space: ColorSpace,
+    output_transform: OutputTransform,
+    supersample: u32,
+    // Intermediate pass (see ir::split): the colour is stored without post-processing or clamp
+    intermediate: bool,
+}
+
+// Collects reachable nodes in post-order (children before parents)
+struct PostOrder(Vec<NodeId>);
+
+impl Visitor for PostOrder {
+    fn leave(&mut self, _ir: &IRBuilder, id: NodeId) { self.0.push(id); }
+}
+
+impl Default for SpirvEmitter {
+    fn default() -> Self { Self::new() }
 }
 
 impl SpirvEmitter {
-    pub fn new() -> Self { Self { context: SpirvContext::new(), current_function: None } }
+    pub fn new() -> Self { Self { context: SpirvContext::new(), current_function: None, node_functions: false, functions: HashMap::new(), post_process: PostProcess::default(), alpha: AlphaMode::default(), output_space: ColorSpace::default(), output_transform: OutputTransform::default(), supersample: 1, intermediate: false } }
+
+    // Embed the sketch as OpSource and emit OpLine/OpName for every IR node
+    pub fn with_source(mut self, file_name: &str, source: &str) -> Self {
+        self.context.emit_debug_source(file_name, source);
+        self
+    }
+
+    // Emit each IR node as a named function (e.g. `vec4 osc_0(vec2 st)`) for readable cross-compiled output
+    pub fn with_node_functions(mut self) -> Self {
+        self.node_functions = true;
+        self
+    }
+
+    pub fn with_aspect(mut self, aspect: Aspect) -> Self {
+        self.context.aspect = aspect;
+        self
+    }
+
+    pub fn with_post_process(mut self, post_process: PostProcess) -> Self {
+        self.post_process = post_process;
+        self
+    }
+
+    pub fn with_alpha(mut self, alpha: AlphaMode) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    // Space the sketch works in, see CompileOptions::color_space
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.context.color_space = color_space;
+        self
+    }
+
+    // Space the output target expects; the main shader converts to it after the clamp
+    pub fn with_output_space(mut self, output_space: ColorSpace) -> Self {
+        self.output_space = output_space;
+        self
+    }
+
+    // SDR or HDR output, see CompileOptions::output_transform
+    pub fn with_output_transform(mut self, output_transform: OutputTransform) -> Self {
+        self.output_transform = output_transform;
+        self
+    }
+
+    // Samples per pixel for the whole graph, see CompileOptions::supersample
+    pub fn with_supersample(mut self, samples: u32) -> Self {
+        self.supersample = samples;
+        self
+    }
+
+    // For the passes of CompiledShader::passes, which draw into float targets
+    pub fn with_intermediate_output(mut self) -> Self {
+        self.intermediate = true;
+        self
+    }
 
-    pub fn emit_pipeline(mut self, script: &Script) -> Vec<u32> {
+    pub fn emit_pipeline(self, script: &Script) -> Result<Vec<u32>, CompileError> {
         // Build IR first
         let mut ir_builder = IRBuilder::new();
         let root = ir_builder.build_script(script);
+        if let Some(e) = ir_builder.errors.drain(..).next() {
+            return Err(e);
+        }
+        self.emit_ir(&ir_builder, root)
+    }
+
+    // Emit an already built (and possibly rewritten, see ir::visit) IR graph
+    pub fn emit_ir(mut self, ir_builder: &IRBuilder, root: Option<NodeId>) -> Result<Vec<u32>, CompileError> {
+        let root = root.ok_or(CompileError::EmptyProgram)?;
+        let mut order = PostOrder(Vec::new());
+        walk(&mut order, ir_builder, root);
+        // Texture variables are module-scope, so they are declared before any function
+        for id in &order.0 {
+            let index = |args: &[Arg]| args.first().and_then(Arg::as_const).unwrap_or(0.0) as u32;
+            match &lookup(ir_builder, *id)?.kind {
+                IRKind::Source { ty: SourceType::External, args } => self.context.declare_external(index(args), ir_builder.external_wrap(index(args))),
+                IRKind::Source { ty: SourceType::Pass, args } => self.context.declare_pass(index(args)),
+                IRKind::Temporal { history: Some(pass), .. } => self.context.declare_history(*pass),
+                _ => {}
+            }
+        }
+        // Node functions have to be complete before main is begun
+        if self.node_functions {
+            for id in order.0 {
+                if let Some(f) = self.emit_node_function(ir_builder, id)? { self.functions.insert(id, f); }
+            }
+        }
 
         // Create main function
         let fn_ty = self.context.builder.type_function(self.context.types.void_ty, vec![]);
@@ -28,51 +136,99 @@ impl SpirvEmitter {
             None,
             spirv::FunctionControl::NONE,
             fn_ty,
-        ).unwrap();
+        )?;
         self.current_function = Some(main_fn);
-        let _entry_block = self.context.builder.begin_block(None).unwrap();
+        let _entry_block = self.context.builder.begin_block(None)?;
 
-        // Base UV
-        let uv = self.context.compute_uv();
-        self.context.variables.insert("_base_uv".to_string(), uv);
-
-        // Emit IR root
-        if let Some(r) = root { if let Some(color) = self.emit_ir_node(&ir_builder, r, uv) {
-            // Always apply auto exposure then ACES tone mapping
-            let exposed = self.apply_auto_exposure(color);
-            let adjusted = self.tone_map_aces(exposed);
-            let clamped = self.context.clamp_vec4(adjusted);
-            let _ = self.context.builder.store(self.context.builtins.frag_color, clamped, None, vec![]);
-        } }
+        let color = if self.supersample > 1 {
+            // Nothing after the root reads its output, so the samples start below it
+            let node = match lookup(ir_builder, root)?.kind { IRKind::Output { child, .. } => child, _ => root };
+            let mut colors = Vec::new();
+            for jitter in supersample_offsets(self.supersample) {
+                let uv = self.context.compute_uv(Some(jitter))?;
+                if self.node_functions { self.context.builder.name(uv, "st"); }
+                colors.push(self.emit_root(ir_builder, node, uv)?);
+            }
+            self.context.combine_taps(FilterType::Supersample, &colors)?
+        } else {
+            // Base UV
+            let uv = self.context.compute_uv(None)?;
+            self.context.variables.insert("_base_uv".to_string(), uv);
+            if self.node_functions { self.context.builder.name(uv, "st"); }
+            self.emit_root(ir_builder, root, uv)?
+        };
+        let color = if self.intermediate {
+            color
+        } else {
+            let adjusted = self.apply_post_process(color)?;
+            let encoded = match self.output_transform {
+                OutputTransform::Sdr => {
+                    let clamped = self.context.clamp_vec4(adjusted)?;
+                    match (self.context.color_space, self.output_space) {
+                        (ColorSpace::Srgb, ColorSpace::Linear) => self.context.srgb_to_linear(clamped)?,
+                        (ColorSpace::Linear, ColorSpace::Srgb) => self.context.linear_to_srgb(clamped)?,
+                        _ => clamped,
+                    }
+                }
+                // HDR encodings start from linear light and keep values above 1.0
+                OutputTransform::ScRgb { paper_white } => {
+                    let linear = self.linear_light(adjusted)?;
+                    self.context.scrgb_encode(linear, paper_white / 80.0)?
+                }
+                OutputTransform::Pq { paper_white } => {
+                    let linear = self.linear_light(adjusted)?;
+                    self.context.pq_encode(linear, paper_white / 10000.0)?
+                }
+            };
+            match self.alpha {
+                AlphaMode::Straight => encoded,
+                AlphaMode::Premultiplied => self.context.premultiply(encoded)?,
+            }
+        };
+        self.context.builder.store(self.context.builtins.frag_color, color, None, vec![])?;
 
-        self.context.builder.ret().unwrap();
-        self.context.builder.end_function().unwrap();
+        self.context.builder.ret()?;
+        self.context.builder.end_function()?;
 
         // Entry point and execution mode
-        self.context.builder.entry_point(
-            spirv::ExecutionModel::Fragment,
-            main_fn,
-            "main",
-            vec![
-                self.context.builtins.frag_coord,
-                self.context.builtins.frag_color,
-                self.context.builtins.globals_ptr,
-            ],
-        );
+        let mut interface = vec![
+            self.context.builtins.frag_coord,
+            self.context.builtins.frag_color,
+            self.context.builtins.globals_ptr,
+        ];
+        interface.extend(self.context.texture_interface());
+        self.context.builder.entry_point(spirv::ExecutionModel::Fragment, main_fn, "main", interface);
         self.context.builder.execution_mode(main_fn, spirv::ExecutionMode::OriginUpperLeft, vec![]);
-        self.context.builder.module().assemble()
+        Ok(self.context.builder.module().assemble())
+    }
+
+    fn emit_root(&mut self, ir: &IRBuilder, id: NodeId, coord: Word) -> Result<Word, CompileError> {
+        if self.node_functions { self.emit_ir_node_call(ir, id, coord) } else { self.emit_ir_node(ir, id, coord) }
     }
 
-    fn emit_ir_node(&mut self, ir: &IRBuilder, id: NodeId, coord: Word) -> Option<Word> {
-        match &ir.nodes[id.0].kind {
-            IRKind::Source { ty, args } => self.emit_source(ty, args, coord),
+    fn emit_ir_node(&mut self, ir: &IRBuilder, id: NodeId, coord: Word) -> Result<Word, CompileError> {
+        let node = lookup(ir, id)?;
+        let debug_name = format!("{}_{}", node.kind.name(), id.0);
+        // OpLine is re-emitted after child subtrees so it covers this node's own instructions
+        match &node.kind {
+            IRKind::Source { ty, args } => {
+                self.context.emit_span_line(node.span);
+                let color = self.emit_source(ty, args, coord)?;
+                self.context.emit_debug_name(color, &debug_name);
+                Ok(color)
+            }
             IRKind::Spatial { ty, args, child } => {
-                let new_coord = self.apply_spatial_transform(*ty, args, coord);
+                self.context.emit_span_line(node.span);
+                let new_coord = self.apply_spatial_transform(*ty, args, coord)?;
+                self.context.emit_debug_name(new_coord, &debug_name);
                 self.emit_ir_node(ir, *child, new_coord)
             }
             IRKind::UnaryColor { ty, args, child } => {
                 let base = self.emit_ir_node(ir, *child, coord)?;
-                self.emit_unary_color(*ty, args, base)
+                self.context.emit_span_line(node.span);
+                let color = self.emit_unary_color(*ty, args, base)?;
+                self.context.emit_debug_name(color, &debug_name);
+                Ok(color)
             }
             IRKind::Binary { ty, args, left, right } => {
                 // Specialized handling for coordinate-modulating binaries
@@ -80,336 +236,598 @@ impl SpirvEmitter {
                     BinaryType::Modulate | BinaryType::ModulateScale => {
                         // Evaluate right (modulator) first at current coord
                         let mod_color = self.emit_ir_node(ir, *right, coord)?;
-                        // Amount (first arg if present)
-                        let amount = if let Some(v) = args.get(0) { self.context.emit_f32_constant(*v) } else { self.context.emit_f32_constant(0.5) };
+                        self.context.emit_span_line(node.span);
+                        // Amount (first arg if present), Hydra's default for modulate
+                        let amount = self.context.get_arg_or_default(args, 0, modulate_default(*ty))?;
                         // Derive new coord
                         let new_coord = if matches!(ty, BinaryType::ModulateScale) {
-                            self.scale_coord_from_color(coord, mod_color, amount)
+                            self.scale_coord_from_color(coord, mod_color, amount)?
                         } else {
-                            self.displace_coord_from_color(coord, mod_color, amount)
+                            self.displace_coord_from_color(coord, mod_color, amount)?
                         };
+                        self.context.emit_debug_name(new_coord, &debug_name);
                         // Re-sample left subtree with transformed coordinates
                         let recomputed = self.emit_ir_node(ir, *left, new_coord)?;
                         // For ModulateHue we still want hue shift based on modulator; but that's not in this branch.
-                        Some(recomputed)
+                        Ok(recomputed)
                     }
                     _ => {
                         // Default binary path: evaluate both at same coordinate
                         let a = self.emit_ir_node(ir, *left, coord)?;
                         let b = self.emit_ir_node(ir, *right, coord)?;
-                        self.emit_standard_binary(*ty, args, a, b)
+                        self.context.emit_span_line(node.span);
+                        let color = self.emit_standard_binary(*ty, args, a, b)?;
+                        self.context.emit_debug_name(color, &debug_name);
+                        Ok(color)
                     }
                 }
             }
+            IRKind::Filter { ty, args, child } => {
+                let taps = self.context.filter_taps(*ty, args, coord)?;
+                let mut colors = Vec::new();
+                for tap in taps { colors.push(self.emit_ir_node(ir, *child, tap)?); }
+                self.context.emit_span_line(node.span);
+                let color = self.context.combine_taps(*ty, &colors)?;
+                self.context.emit_debug_name(color, &debug_name);
+                Ok(color)
+            }
+            IRKind::Temporal { ty, args, child, history } => {
+                let current = self.emit_ir_node(ir, *child, coord)?;
+                self.context.emit_span_line(node.span);
+                let color = self.emit_temporal(ir, *ty, args, *history, current, coord)?;
+                self.context.emit_debug_name(color, &debug_name);
+                Ok(color)
+            }
             IRKind::Output { child, index } => {
                 let c = self.emit_ir_node(ir, *child, coord)?;
                 // store color in variable o{index}
                 let key = format!("o{}", index);
                 self.context.variables.insert(key, c);
-                Some(c)
+                Ok(c)
+            }
+        }
+    }
+
+    // Builds `ret name(params...)` around `body`, which receives the parameter ids
+    fn emit_function<F>(&mut self, name: &str, ret_ty: Word, params: &[(Word, &str)], body: F) -> Result<Word, CompileError>
+        where F: FnOnce(&mut Self, &[Word]) -> Result<Word, CompileError>
+    {
+        let param_tys: Vec<Word> = params.iter().map(|(ty, _)| *ty).collect();
+        let fn_ty = self.context.builder.type_function(ret_ty, param_tys);
+        let func = self.context.builder.begin_function(ret_ty, None, spirv::FunctionControl::NONE, fn_ty)?;
+        self.context.builder.name(func, name);
+        let mut param_ids = Vec::new();
+        for (ty, param_name) in params {
+            let p = self.context.builder.function_parameter(*ty)?;
+            self.context.builder.name(p, *param_name);
+            param_ids.push(p);
+        }
+        self.context.builder.begin_block(None)?;
+        let result = body(self, &param_ids);
+        match result {
+            Ok(v) => self.context.builder.ret_value(v)?,
+            Err(_) => self.context.builder.unreachable()?,
+        }
+        self.context.builder.end_function()?;
+        result.map(|_| func)
+    }
+
+    // Function for a single node; None for nodes that stay inline in main (src, filters, temporal, out)
+    fn emit_node_function(&mut self, ir: &IRBuilder, id: NodeId) -> Result<Option<Word>, CompileError> {
+        let node = lookup(ir, id)?;
+        let name = format!("{}_{}", node.kind.name(), id.0);
+        let vec2_ty = self.context.types.vec2_ty;
+        let vec4_ty = self.context.types.vec4_ty;
+        let span = node.span;
+        match &node.kind {
+            IRKind::Source { ty: SourceType::Src, .. } | IRKind::Filter { .. } | IRKind::Temporal { .. } | IRKind::Output { .. } => Ok(None),
+            IRKind::Source { ty, args } => self.emit_function(&name, vec4_ty, &[(vec2_ty, "st")], |e, p| {
+                e.context.emit_span_line(span);
+                e.emit_source(ty, args, p[0])
+            }).map(Some),
+            IRKind::Spatial { ty, args, .. } => self.emit_function(&name, vec2_ty, &[(vec2_ty, "st")], |e, p| {
+                e.context.emit_span_line(span);
+                e.apply_spatial_transform(*ty, args, p[0])
+            }).map(Some),
+            IRKind::UnaryColor { ty, args, .. } => self.emit_function(&name, vec4_ty, &[(vec4_ty, "c")], |e, p| {
+                e.context.emit_span_line(span);
+                e.emit_unary_color(*ty, args, p[0])
+            }).map(Some),
+            IRKind::Binary { ty: ty @ (BinaryType::Modulate | BinaryType::ModulateScale), args, .. } => {
+                self.emit_function(&name, vec2_ty, &[(vec2_ty, "st"), (vec4_ty, "c")], |e, p| {
+                    e.context.emit_span_line(span);
+                    let amount = e.context.get_arg_or_default(args, 0, modulate_default(*ty))?;
+                    if matches!(ty, BinaryType::ModulateScale) {
+                        e.scale_coord_from_color(p[0], p[1], amount)
+                    } else {
+                        e.displace_coord_from_color(p[0], p[1], amount)
+                    }
+                }).map(Some)
+            }
+            IRKind::Binary { ty, args, .. } => self.emit_function(&name, vec4_ty, &[(vec4_ty, "a"), (vec4_ty, "b")], |e, p| {
+                e.context.emit_span_line(span);
+                e.emit_standard_binary(*ty, args, p[0], p[1])
+            }).map(Some),
+        }
+    }
+
+    // Node-function counterpart of emit_ir_node: composes calls to the functions built above
+    fn emit_ir_node_call(&mut self, ir: &IRBuilder, id: NodeId, coord: Word) -> Result<Word, CompileError> {
+        let node = lookup(ir, id)?;
+        let vec2_ty = self.context.types.vec2_ty;
+        let vec4_ty = self.context.types.vec4_ty;
+        let func = self.functions.get(&id).copied();
+        let missing = || CompileError::InvalidProgram(format!("no function emitted for node {}", id.0));
+        match &node.kind {
+            IRKind::Source { ty, args } => match func {
+                Some(f) => {
+                    self.context.emit_span_line(node.span);
+                    Ok(self.context.builder.function_call(vec4_ty, None, f, [coord])?)
+                }
+                None => self.emit_source(ty, args, coord),
+            },
+            IRKind::Spatial { child, .. } => {
+                self.context.emit_span_line(node.span);
+                let new_coord = self.context.builder.function_call(vec2_ty, None, func.ok_or_else(missing)?, [coord])?;
+                self.emit_ir_node_call(ir, *child, new_coord)
+            }
+            IRKind::UnaryColor { child, .. } => {
+                let base = self.emit_ir_node_call(ir, *child, coord)?;
+                self.context.emit_span_line(node.span);
+                Ok(self.context.builder.function_call(vec4_ty, None, func.ok_or_else(missing)?, [base])?)
+            }
+            IRKind::Binary { ty: BinaryType::Modulate | BinaryType::ModulateScale, left, right, .. } => {
+                let mod_color = self.emit_ir_node_call(ir, *right, coord)?;
+                self.context.emit_span_line(node.span);
+                let new_coord = self.context.builder.function_call(vec2_ty, None, func.ok_or_else(missing)?, [coord, mod_color])?;
+                self.emit_ir_node_call(ir, *left, new_coord)
+            }
+            IRKind::Binary { left, right, .. } => {
+                let a = self.emit_ir_node_call(ir, *left, coord)?;
+                let b = self.emit_ir_node_call(ir, *right, coord)?;
+                self.context.emit_span_line(node.span);
+                Ok(self.context.builder.function_call(vec4_ty, None, func.ok_or_else(missing)?, [a, b])?)
+            }
+            IRKind::Filter { ty, args, child } => {
+                let taps = self.context.filter_taps(*ty, args, coord)?;
+                let mut colors = Vec::new();
+                for tap in taps { colors.push(self.emit_ir_node_call(ir, *child, tap)?); }
+                self.context.emit_span_line(node.span);
+                self.context.combine_taps(*ty, &colors)
+            }
+            IRKind::Temporal { ty, args, child, history } => {
+                let current = self.emit_ir_node_call(ir, *child, coord)?;
+                self.context.emit_span_line(node.span);
+                self.emit_temporal(ir, *ty, args, *history, current, coord)
+            }
+            IRKind::Output { child, index } => {
+                let c = self.emit_ir_node_call(ir, *child, coord)?;
+                self.context.variables.insert(format!("o{}", index), c);
+                Ok(c)
             }
         }
     }
 
-    fn emit_source(&mut self, ty: &SourceType, args: &Vec<f32>, coord: Word) -> Option<Word> {
+    // The history pass is assigned by ir::split, which every compile runs
+    fn emit_temporal(&mut self, ir: &IRBuilder, ty: TemporalType, args: &[Arg], history: Option<u32>, current: Word, coord: Word) -> Result<Word, CompileError> {
+        let pass = history.ok_or_else(|| CompileError::InvalidProgram(format!("`{}` without a history pass", ty.name())))?;
+        self.context.emit_temporal(ty, args, pass, ir.history_frames(pass), current, coord)
+    }
+
+    fn emit_source(&mut self, ty: &SourceType, args: &[Arg], coord: Word) -> Result<Word, CompileError> {
         // Set working coordinate variable for legacy source emitters
         self.context.variables.insert("_st".to_string(), coord);
         match ty {
-            SourceType::Osc => self.call_args_wrapper(args, |ctx, call_args| ctx.emit_osc(call_args)),
-            SourceType::Noise => self.call_args_wrapper(args, |ctx, call_args| ctx.emit_noise(call_args)),
-            SourceType::Solid => self.call_args_wrapper(args, |ctx, call_args| ctx.emit_solid(call_args)),
-            SourceType::Gradient => self.call_args_wrapper(args, |ctx, call_args| ctx.emit_gradient(call_args)),
-            SourceType::Shape => self.call_args_wrapper(args, |ctx, call_args| ctx.emit_shape(call_args)),
-            SourceType::Voronoi => self.call_args_wrapper(args, |ctx, call_args| ctx.emit_voronoi(call_args)),
+            SourceType::Osc => self.context.emit_osc(args),
+            SourceType::Noise => self.context.emit_noise(args),
+            SourceType::Solid => self.context.emit_solid(args),
+            SourceType::Gradient => self.context.emit_gradient(args),
+            SourceType::Shape => self.context.emit_shape(args),
+            SourceType::Voronoi => self.context.emit_voronoi(args),
+            SourceType::External => self.context.emit_external(args),
+            SourceType::Pass => self.context.emit_pass(args),
             SourceType::Src => {
                 // src(index=0)
-                let idx = args.get(0).cloned().unwrap_or(0.0) as u32;
+                let idx = args.first().and_then(Arg::as_const).unwrap_or(0.0) as u32;
                 let key = format!("o{}", idx);
-                if let Some(val) = self.context.variables.get(&key) { Some(*val) } else { self.call_args_wrapper(args, |ctx, call_args| ctx.emit_solid(call_args)) }
+                if let Some(val) = self.context.variables.get(&key) { Ok(*val) } else { self.context.emit_solid(args) }
             }
         }
     }
 
-    // Helper: fabricate a temporary CallExpr with numeric literal args so we can reuse existing emit_* functions without rewriting them.
-    fn call_args_wrapper<F>(&mut self, args: &Vec<f32>, f: F) -> Option<Word>
-        where F: Fn(&mut SpirvContext, &CallExpr) -> Option<Word>
-    {
-        use swc_ecma_ast::{CallExpr, Callee, Expr, ExprOrSpread, Lit, Number};
-        let expr_args: Vec<ExprOrSpread> = args.iter().map(|v| {
-            ExprOrSpread { spread: None, expr: Box::new(Expr::Lit(Lit::Num(Number { span: DUMMY_SP, value: *v as f64, raw: None })))}
-        }).collect();
-        let ident = Ident::new_no_ctxt(Atom::from("_tmp"), DUMMY_SP);
-        let fake_call = CallExpr { span: DUMMY_SP, ctxt: SyntaxContext::empty(), callee: Callee::Expr(Box::new(Expr::Ident(ident))), args: expr_args, type_args: None };
-        f(&mut self.context, &fake_call)
-    }
-
-    fn apply_spatial_transform(&mut self, ty: SpatialType, args: &Vec<f32>, coord: Word) -> Word {
+    fn apply_spatial_transform(&mut self, ty: SpatialType, args: &[Arg], coord: Word) -> Result<Word, CompileError> {
+        let ctx = &mut self.context;
         match ty {
             SpatialType::Scale => {
-                let sx = args.get(0).cloned().unwrap_or(1.0);
-                let sy = if args.len() > 1 { args[1] } else { sx };
+                let sx = ctx.get_arg_or_default(args, 0, 1.0)?;
+                let sy = if args.len() > 1 { ctx.get_arg_or_default(args, 1, 1.0)? } else { sx };
                 self.scale_coord(coord, sx, sy)
             }
             SpatialType::Kaleid => {
-                let sides = args.get(0).cloned().unwrap_or(4.0);
+                let sides = ctx.get_arg_or_default(args, 0, 4.0)?;
                 self.kaleid_coord(coord, sides)
             }
             SpatialType::Rotate => {
                 // rotate(angle=0, speed=0)
-                let angle = args.get(0).cloned().unwrap_or(0.0);
-                let speed = args.get(1).cloned().unwrap_or(0.0);
+                let angle = ctx.get_arg_or_default(args, 0, 0.0)?;
+                let speed = ctx.get_arg_or_default(args, 1, 0.0)?;
                 self.rotate_coord(coord, angle, speed)
             }
             SpatialType::ScrollX => {
-                let amt = args.get(0).cloned().unwrap_or(0.0);
-                let speed = args.get(1).cloned().unwrap_or(0.0);
+                let amt = ctx.get_arg_or_default(args, 0, 0.0)?;
+                let speed = ctx.get_arg_or_default(args, 1, 0.0)?;
                 self.scroll_coord(coord, Some(amt), None, Some(speed), None)
             }
             SpatialType::ScrollY => {
-                let amt = args.get(0).cloned().unwrap_or(0.0);
-                let speed = args.get(1).cloned().unwrap_or(0.0);
+                let amt = ctx.get_arg_or_default(args, 0, 0.0)?;
+                let speed = ctx.get_arg_or_default(args, 1, 0.0)?;
                 self.scroll_coord(coord, None, Some(amt), None, Some(speed))
             }
             SpatialType::Scroll => {
-                let ax = args.get(0).cloned().unwrap_or(0.0);
-                let ay = args.get(1).cloned().unwrap_or(0.0);
-                let sx = args.get(2).cloned().unwrap_or(0.0);
-                let sy = args.get(3).cloned().unwrap_or(0.0);
+                let ax = ctx.get_arg_or_default(args, 0, 0.0)?;
+                let ay = ctx.get_arg_or_default(args, 1, 0.0)?;
+                let sx = ctx.get_arg_or_default(args, 2, 0.0)?;
+                let sy = ctx.get_arg_or_default(args, 3, 0.0)?;
                 self.scroll_coord(coord, Some(ax), Some(ay), Some(sx), Some(sy))
             }
             SpatialType::Repeat => {
-                let rx = args.get(0).cloned().unwrap_or(3.0);
-                let ry = args.get(1).cloned().unwrap_or(rx);
+                let rx = ctx.get_arg_or_default(args, 0, 3.0)?;
+                let ry = if args.len() > 1 { ctx.get_arg_or_default(args, 1, 3.0)? } else { rx };
                 self.repeat_coord(coord, rx, ry)
             }
             SpatialType::RepeatX => {
-                let rx = args.get(0).cloned().unwrap_or(3.0);
-                self.repeat_coord(coord, rx, 1.0)
+                let rx = ctx.get_arg_or_default(args, 0, 3.0)?;
+                let one = ctx.emit_f32_constant(1.0);
+                self.repeat_coord(coord, rx, one)
             }
             SpatialType::RepeatY => {
-                let ry = args.get(0).cloned().unwrap_or(3.0);
-                self.repeat_coord(coord, 1.0, ry)
+                let ry = ctx.get_arg_or_default(args, 0, 3.0)?;
+                let one = ctx.emit_f32_constant(1.0);
+                self.repeat_coord(coord, one, ry)
             }
             SpatialType::Pixelate => {
-                let sx = args.get(0).cloned().unwrap_or(10.0);
-                let sy = if args.len() > 1 { args[1] } else { sx };
+                let sx = ctx.get_arg_or_default(args, 0, 10.0)?;
+                let sy = if args.len() > 1 { ctx.get_arg_or_default(args, 1, 10.0)? } else { sx };
                 self.pixelate_coord(coord, sx, sy)
             }
+            SpatialType::Polar => self.polar_coord(coord),
+            SpatialType::Cartesian => self.cartesian_coord(coord),
+            SpatialType::Mirror => {
+                // mirror(axis=0): 0 folds x, 1 folds y, 2 both
+                let axis = ctx.get_arg_or_default(args, 0, 0.0)?;
+                self.mirror_coord(coord, axis)
+            }
+            SpatialType::Tile => {
+                let tx = ctx.get_arg_or_default(args, 0, 3.0)?;
+                let ty = if args.len() > 1 { ctx.get_arg_or_default(args, 1, 3.0)? } else { tx };
+                let offset = ctx.get_arg_or_default(args, 2, 0.5)?;
+                self.tile_coord(coord, tx, ty, offset)
+            }
+            SpatialType::Skew => {
+                let x = ctx.get_arg_or_default(args, 0, 0.0)?;
+                let y = ctx.get_arg_or_default(args, 1, 0.0)?;
+                let (zero, one) = (ctx.emit_f32_constant(0.0), ctx.emit_f32_constant(1.0));
+                self.affine_coord(coord, [one, x, y, one], [zero, zero])
+            }
+            SpatialType::Transform => {
+                // transform(a=1, b=0, c=0, d=1, tx=0, ty=0)
+                let mut m = [0; 6];
+                for (i, (v, d)) in m.iter_mut().zip([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]).enumerate() {
+                    *v = self.context.get_arg_or_default(args, i, d)?;
+                }
+                self.affine_coord(coord, [m[0], m[1], m[2], m[3]], [m[4], m[5]])
+            }
         }
     }
 
-    fn rotate_coord(&mut self, coord: Word, angle: f32, speed: f32) -> Word {
-        let time = self.context.load_time();
-        let speed_c = self.context.emit_f32_constant(speed);
-        let time_term = self.context.builder.f_mul(self.context.types.f32_ty, None, time, speed_c).unwrap();
-        let base_angle = self.context.emit_f32_constant(angle);
-        let total = self.context.builder.f_add(self.context.types.f32_ty, None, base_angle, time_term).unwrap();
-        // center
-        let (x,y) = self.context.extract_vec2_components(coord);
-        let half = self.context.emit_f32_constant(0.5);
-        let x_c = self.context.builder.f_sub(self.context.types.f32_ty, None, x, half).unwrap();
-        let y_c = self.context.builder.f_sub(self.context.types.f32_ty, None, y, half).unwrap();
-        let cos_a = self.context.emit_glsl_cos(total);
-        let sin_a = self.context.emit_glsl_sin(total);
-        // rot: (x', y') = (x*cos - y*sin, x*sin + y*cos)
-        let x_cos = self.context.builder.f_mul(self.context.types.f32_ty, None, x_c, cos_a).unwrap();
-        let y_sin = self.context.builder.f_mul(self.context.types.f32_ty, None, y_c, sin_a).unwrap();
-        let x_prime = self.context.builder.f_sub(self.context.types.f32_ty, None, x_cos, y_sin).unwrap();
-        let x_sin = self.context.builder.f_mul(self.context.types.f32_ty, None, x_c, sin_a).unwrap();
-        let y_cos = self.context.builder.f_mul(self.context.types.f32_ty, None, y_c, cos_a).unwrap();
-        let y_prime = self.context.builder.f_add(self.context.types.f32_ty, None, x_sin, y_cos).unwrap();
-        let x_add = self.context.builder.f_add(self.context.types.f32_ty, None, x_prime, half).unwrap();
-        let y_add = self.context.builder.f_add(self.context.types.f32_ty, None, y_prime, half).unwrap();
-        self.context.emit_vec2(x_add, y_add)
-    }
-
-    fn scroll_coord(&mut self, coord: Word, ax: Option<f32>, ay: Option<f32>, sx: Option<f32>, sy: Option<f32>) -> Word {
-        let (x,y) = self.context.extract_vec2_components(coord);
-        let time = self.context.load_time();
-        let shift_axis = |val: Word, amt: Option<f32>, spd: Option<f32>, ctx: &mut SpirvContext, time_val: Word| -> Word {
+    fn rotate_coord(&mut self, coord: Word, angle: Word, speed: Word) -> Result<Word, CompileError> {
+        let time = self.context.load_time()?;
+        let time_term = self.context.builder.f_mul(self.context.types.f32_ty, None, time, speed)?;
+        let total = self.context.builder.f_add(self.context.types.f32_ty, None, angle, time_term)?;
+        let (x_c, y_c) = self.context.center_coord(coord)?;
+        let cos_a = self.context.emit_glsl_cos(total)?;
+        let sin_a = self.context.emit_glsl_sin(total)?;
+        // Hydra's mat2(cos, -sin, sin, cos) * xy: (x', y') = (x*cos + y*sin, y*cos - x*sin)
+        let x_cos = self.context.builder.f_mul(self.context.types.f32_ty, None, x_c, cos_a)?;
+        let y_sin = self.context.builder.f_mul(self.context.types.f32_ty, None, y_c, sin_a)?;
+        let x_prime = self.context.builder.f_add(self.context.types.f32_ty, None, x_cos, y_sin)?;
+        let x_sin = self.context.builder.f_mul(self.context.types.f32_ty, None, x_c, sin_a)?;
+        let y_cos = self.context.builder.f_mul(self.context.types.f32_ty, None, y_c, cos_a)?;
+        let y_prime = self.context.builder.f_sub(self.context.types.f32_ty, None, y_cos, x_sin)?;
+        self.context.uncenter_coord(x_prime, y_prime)
+    }
+
+    fn scroll_coord(&mut self, coord: Word, ax: Option<Word>, ay: Option<Word>, sx: Option<Word>, sy: Option<Word>) -> Result<Word, CompileError> {
+        let (x,y) = self.context.extract_vec2_components(coord)?;
+        let time = self.context.load_time()?;
+        let shift_axis = |val: Word, amt: Option<Word>, spd: Option<Word>, ctx: &mut SpirvContext, time_val: Word| -> Result<Word, CompileError> {
             let mut out = val;
-            if let Some(a) = amt { let a_c = ctx.emit_f32_constant(a); out = ctx.builder.f_add(ctx.types.f32_ty, None, out, a_c).unwrap(); }
-            if let Some(s) = spd { let sc = ctx.emit_f32_constant(s); let delta = ctx.builder.f_mul(ctx.types.f32_ty, None, time_val, sc).unwrap(); out = ctx.builder.f_add(ctx.types.f32_ty, None, out, delta).unwrap(); }
-            let flo = ctx.emit_glsl_floor(out);
-            ctx.builder.f_sub(ctx.types.f32_ty, None, out, flo).unwrap()
+            if let Some(a) = amt { out = ctx.builder.f_add(ctx.types.f32_ty, None, out, a)?; }
+            if let Some(s) = spd { let delta = ctx.builder.f_mul(ctx.types.f32_ty, None, time_val, s)?; out = ctx.builder.f_add(ctx.types.f32_ty, None, out, delta)?; }
+            let flo = ctx.emit_glsl_floor(out)?;
+            Ok(ctx.builder.f_sub(ctx.types.f32_ty, None, out, flo)?)
         };
-        let nx = shift_axis(x, ax, sx, &mut self.context, time);
-        let ny = shift_axis(y, ay, sy, &mut self.context, time);
+        let nx = shift_axis(x, ax, sx, &mut self.context, time)?;
+        let ny = shift_axis(y, ay, sy, &mut self.context, time)?;
         self.context.emit_vec2(nx, ny)
     }
 
-    fn emit_unary_color(&mut self, ty: UnaryColorType, args: &Vec<f32>, input: Word) -> Option<Word> {
+    fn emit_unary_color(&mut self, ty: UnaryColorType, args: &[Arg], input: Word) -> Result<Word, CompileError> {
+        let ctx = &mut self.context;
         match ty {
-            UnaryColorType::Invert => self.call_unary_color_with_args(input, args, |ctx, color, call| ctx.emit_invert(color, call)),
-            UnaryColorType::Color => self.call_unary_color_with_args(input, args, |ctx, color, call| ctx.emit_color(color, call)),
-            UnaryColorType::Brightness => self.call_unary_color_with_args(input, args, |ctx, color, call| ctx.emit_brightness(color, call)),
-            UnaryColorType::Contrast => self.call_unary_color_with_args(input, args, |ctx, color, call| ctx.emit_contrast(color, call)),
-            UnaryColorType::Saturate => self.call_unary_color_with_args(input, args, |ctx, color, call| ctx.emit_saturate(color, call)),
-            UnaryColorType::Posterize => self.call_unary_color_with_args(input, args, |ctx, color, call| ctx.emit_posterize(color, call)),
-            UnaryColorType::Thresh => self.call_unary_color_with_args(input, args, |ctx, color, call| ctx.emit_thresh(color, call)),
-            UnaryColorType::Hue => self.call_unary_color_with_args(input, args, |ctx, color, call| ctx.emit_hue(color, call)),
-            UnaryColorType::Colorama => self.call_unary_color_with_args(input, args, |ctx, color, call| ctx.emit_colorama(color, call)),
-            UnaryColorType::Luma => self.call_unary_color_with_args(input, args, |ctx, color, call| ctx.emit_luma_effect(color, call)),
-            UnaryColorType::Shift => self.call_unary_color_with_args(input, args, |ctx, color, call| ctx.emit_shift(color, call)),
+            UnaryColorType::Invert => ctx.emit_invert(input, args),
+            UnaryColorType::Color => ctx.emit_color(input, args),
+            UnaryColorType::Brightness => ctx.emit_brightness(input, args),
+            UnaryColorType::Contrast => ctx.emit_contrast(input, args),
+            UnaryColorType::Saturate => ctx.emit_saturate(input, args),
+            UnaryColorType::Posterize => ctx.emit_posterize(input, args),
+            UnaryColorType::Thresh => ctx.emit_thresh(input, args),
+            UnaryColorType::Hue => ctx.emit_hue(input, args),
+            UnaryColorType::Colorama => ctx.emit_colorama(input, args),
+            UnaryColorType::Luma => ctx.emit_luma_effect(input, args),
+            UnaryColorType::Shift => ctx.emit_shift(input, args),
+            UnaryColorType::BrightPass => ctx.emit_bright_pass(input, args),
         }
     }
 
-    fn emit_standard_binary(&mut self, ty: BinaryType, args: &Vec<f32>, a: Word, b: Word) -> Option<Word> {
-        let amount_const = if let Some(v) = args.get(0) { self.context.emit_f32_constant(*v) } else { self.context.emit_f32_constant(1.0) };
-        Some(match ty {
-            BinaryType::Add => self.context.binary_add(a, b, amount_const),
-            BinaryType::Sub => self.context.binary_sub(a, b, amount_const),
-            BinaryType::Mult => self.context.binary_mult(a, b, amount_const),
-            BinaryType::Blend => self.context.binary_blend(a, b, amount_const),
-            BinaryType::Diff => self.context.binary_diff(a, b),
-            BinaryType::Layer => self.context.binary_layer(a, b),
-            BinaryType::Mask => self.context.binary_mask(a, b),
-            BinaryType::Modulate | BinaryType::ModulateScale => self.context.binary_modulate(a, b, amount_const),
+    fn emit_standard_binary(&mut self, ty: BinaryType, args: &[Arg], a: Word, b: Word) -> Result<Word, CompileError> {
+        let amount_const = self.context.get_arg_or_default(args, 0, 1.0)?;
+        Ok(match ty {
+            BinaryType::Add => self.context.binary_add(a, b, amount_const)?,
+            BinaryType::Sub => self.context.binary_sub(a, b, amount_const)?,
+            BinaryType::Mult => self.context.binary_mult(a, b, amount_const)?,
+            BinaryType::Blend => self.context.binary_blend(a, b, amount_const)?,
+            BinaryType::Diff => self.context.binary_diff(a, b)?,
+            BinaryType::Layer => self.context.binary_layer(a, b)?,
+            BinaryType::Mask => self.context.binary_mask(a, b)?,
+            BinaryType::Modulate | BinaryType::ModulateScale => self.context.binary_modulate(a, b, amount_const)?,
+            BinaryType::Screen => self.context.binary_screen(a, b, amount_const)?,
+            BinaryType::Overlay => self.context.binary_overlay(a, b, amount_const)?,
+            BinaryType::SoftLight => self.context.binary_soft_light(a, b, amount_const)?,
+            BinaryType::HardLight => self.context.binary_hard_light(a, b, amount_const)?,
+            BinaryType::ColorDodge => self.context.binary_color_dodge(a, b, amount_const)?,
+            BinaryType::ColorBurn => self.context.binary_color_burn(a, b, amount_const)?,
+            BinaryType::Darken => self.context.binary_darken(a, b, amount_const)?,
+            BinaryType::Lighten => self.context.binary_lighten(a, b, amount_const)?,
+            BinaryType::Exclusion => self.context.binary_exclusion(a, b, amount_const)?,
+            BinaryType::Hue => self.context.binary_hue(a, b, amount_const)?,
+            BinaryType::Saturation => self.context.binary_saturation(a, b, amount_const)?,
+            BinaryType::Color => self.context.binary_color(a, b, amount_const)?,
+            BinaryType::Luminosity => self.context.binary_luminosity(a, b, amount_const)?,
         })
     }
 
-    fn displace_coord_from_color(&mut self, coord: Word, color: Word, amount: Word) -> Word {
-        let (x,y) = self.context.extract_vec2_components(coord);
-        let r = self.context.extract_component(color, 0);
-        let g = self.context.extract_component(color, 1);
-        let half = self.context.emit_f32_constant(0.5);
-        let r_off = self.context.builder.f_sub(self.context.types.f32_ty, None, r, half).unwrap();
-        let g_off = self.context.builder.f_sub(self.context.types.f32_ty, None, g, half).unwrap();
-        let r_scaled = self.context.builder.f_mul(self.context.types.f32_ty, None, r_off, amount).unwrap();
-        let g_scaled = self.context.builder.f_mul(self.context.types.f32_ty, None, g_off, amount).unwrap();
-        let x_new_add = self.context.builder.f_add(self.context.types.f32_ty, None, x, r_scaled).unwrap();
-        let y_new_add = self.context.builder.f_add(self.context.types.f32_ty, None, y, g_scaled).unwrap();
-        // Clamp to 0..1 (avoid out-of-range sampling for now)
-        let x_clamp = self.context.clamp01(x_new_add);
-        let y_clamp = self.context.clamp01(y_new_add);
-        self.context.emit_vec2(x_clamp, y_clamp)
-    }
-
-    fn scale_coord_from_color(&mut self, coord: Word, color: Word, amount: Word) -> Word {
+    fn displace_coord_from_color(&mut self, coord: Word, color: Word, amount: Word) -> Result<Word, CompileError> {
+        let (x,y) = self.context.extract_vec2_components(coord)?;
+        let r = self.context.extract_component(color, 0)?;
+        let g = self.context.extract_component(color, 1)?;
+        // Hydra's st + c.xy * amount: the displacement is never negative
+        let r_scaled = self.context.builder.f_mul(self.context.types.f32_ty, None, r, amount)?;
+        let g_scaled = self.context.builder.f_mul(self.context.types.f32_ty, None, g, amount)?;
+        let x_new_add = self.context.builder.f_add(self.context.types.f32_ty, None, x, r_scaled)?;
+        let y_new_add = self.context.builder.f_add(self.context.types.f32_ty, None, y, g_scaled)?;
+        // Left unclamped; external sources address out-of-range coordinates by their wrap mode
+        self.context.emit_vec2(x_new_add, y_new_add)
+    }
+
+    fn scale_coord_from_color(&mut self, coord: Word, color: Word, amount: Word) -> Result<Word, CompileError> {
         // factor = 1 + luma(color)*amount
-        let l = self.context.emit_luma(color);
-        let l_scaled = self.context.builder.f_mul(self.context.types.f32_ty, None, l, amount).unwrap();
+        let l = self.context.emit_luma(color)?;
+        let l_scaled = self.context.builder.f_mul(self.context.types.f32_ty, None, l, amount)?;
         let one = self.context.emit_f32_constant(1.0);
-        let factor = self.context.builder.f_add(self.context.types.f32_ty, None, one, l_scaled).unwrap();
+        let factor = self.context.builder.f_add(self.context.types.f32_ty, None, one, l_scaled)?;
         // Reuse scale_coord by extracting scalar constants from factor? Need a dynamic scale (same for x/y).
         // scale_coord expects f32 values; we create inverse by dividing (centered) by factor.
-        let (x,y) = self.context.extract_vec2_components(coord);
-        let half = self.context.emit_f32_constant(0.5);
-        let x_c = self.context.builder.f_sub(self.context.types.f32_ty, None, x, half).unwrap();
-        let y_c = self.context.builder.f_sub(self.context.types.f32_ty, None, y, half).unwrap();
+        let (x_c, y_c) = self.context.center_coord(coord)?;
         // inv_factor = 1/factor
-        let inv_factor = self.context.builder.f_div(self.context.types.f32_ty, None, one, factor).unwrap();
-        let x_s = self.context.builder.f_mul(self.context.types.f32_ty, None, x_c, inv_factor).unwrap();
-        let y_s = self.context.builder.f_mul(self.context.types.f32_ty, None, y_c, inv_factor).unwrap();
-        let x_new = self.context.builder.f_add(self.context.types.f32_ty, None, x_s, half).unwrap();
-        let y_new = self.context.builder.f_add(self.context.types.f32_ty, None, y_s, half).unwrap();
-        self.context.emit_vec2(x_new, y_new)
+        let inv_factor = self.context.builder.f_div(self.context.types.f32_ty, None, one, factor)?;
+        let x_s = self.context.builder.f_mul(self.context.types.f32_ty, None, x_c, inv_factor)?;
+        let y_s = self.context.builder.f_mul(self.context.types.f32_ty, None, y_c, inv_factor)?;
+        self.context.uncenter_coord(x_s, y_s)
     }
 
-    fn call_unary_color_with_args<F>(&mut self, input: Word, args: &Vec<f32>, f: F) -> Option<Word>
-        where F: Fn(&mut SpirvContext, Word, &CallExpr) -> Option<Word>
-    {
-        use swc_ecma_ast::{CallExpr, Callee, Expr, ExprOrSpread, Lit, Number};
-        let expr_args: Vec<ExprOrSpread> = args.iter().map(|v| {
-            ExprOrSpread { spread: None, expr: Box::new(Expr::Lit(Lit::Num(Number { span: DUMMY_SP, value: *v as f64, raw: None })))}
-        }).collect();
-        let ident = Ident::new_no_ctxt(Atom::from("_tmp"), DUMMY_SP);
-        let fake_call = CallExpr { span: DUMMY_SP, ctxt: SyntaxContext::empty(), callee: Callee::Expr(Box::new(Expr::Ident(ident))), args: expr_args, type_args: None };
-        f(&mut self.context, input, &fake_call)
+    fn scale_coord(&mut self, coord: Word, sx: Word, sy: Word) -> Result<Word, CompileError> {
+        let (x_c, y_c) = self.context.center_coord(coord)?;
+        let eps = self.context.emit_f32_constant(1e-6);
+        let sx_safe = self.context.emit_glsl_fmax(sx, eps)?;
+        let sy_safe = self.context.emit_glsl_fmax(sy, eps)?;
+        let x_s = self.context.builder.f_div(self.context.types.f32_ty, None, x_c, sx_safe)?;
+        let y_s = self.context.builder.f_div(self.context.types.f32_ty, None, y_c, sy_safe)?;
+        self.context.uncenter_coord(x_s, y_s)
     }
 
-    fn scale_coord(&mut self, coord: Word, sx: f32, sy: f32) -> Word {
-        let (x,y) = self.context.extract_vec2_components(coord);
-        let half = self.context.emit_f32_constant(0.5);
-        let x_c = self.context.builder.f_sub(self.context.types.f32_ty, None, x, half).unwrap();
-        let y_c = self.context.builder.f_sub(self.context.types.f32_ty, None, y, half).unwrap();
-        let inv_x = self.context.emit_f32_constant(1.0 / sx.max(1e-6));
-        let inv_y = self.context.emit_f32_constant(1.0 / sy.max(1e-6));
-        let x_s = self.context.builder.f_mul(self.context.types.f32_ty, None, x_c, inv_x).unwrap();
-        let y_s = self.context.builder.f_mul(self.context.types.f32_ty, None, y_c, inv_y).unwrap();
-        let x_new = self.context.builder.f_add(self.context.types.f32_ty, None, x_s, half).unwrap();
-        let y_new = self.context.builder.f_add(self.context.types.f32_ty, None, y_s, half).unwrap();
-        self.context.emit_vec2(x_new, y_new)
-    }
-
-    fn kaleid_coord(&mut self, coord: Word, sides: f32) -> Word {
-        let (x,y) = self.context.extract_vec2_components(coord);
-        let half = self.context.emit_f32_constant(0.5);
-        let x_c = self.context.builder.f_sub(self.context.types.f32_ty, None, x, half).unwrap();
-        let y_c = self.context.builder.f_sub(self.context.types.f32_ty, None, y, half).unwrap();
-        let v2 = self.context.emit_vec2(x_c, y_c);
-        let r = self.context.emit_length2(v2);
-        let angle = self.context.emit_glsl_atan2(y_c, x_c);
-        let sides_clamped = sides.max(1.0);
-        let sides_const = self.context.emit_f32_constant(sides_clamped);
-        let two_pi = self.context.emit_f32_constant(6.28318530718_f32);
-        let sector = self.context.builder.f_div(self.context.types.f32_ty, None, two_pi, sides_const).unwrap();
+    fn kaleid_coord(&mut self, coord: Word, sides: Word) -> Result<Word, CompileError> {
+        let (x_c, y_c) = self.context.center_coord(coord)?;
+        let v2 = self.context.emit_vec2(x_c, y_c)?;
+        let r = self.context.emit_length2(v2)?;
+        let angle = self.context.emit_glsl_atan2(y_c, x_c)?;
+        let one = self.context.emit_f32_constant(1.0);
+        let sides_const = self.context.emit_glsl_fmax(sides, one)?;
+        // Under Stretch this is Hydra's kaleid verbatim: 2 * 3.1416, atan folded straight into
+        // the sector, and the result left centred on the origin
+        let stretch = self.context.aspect == Aspect::Stretch;
+        #[allow(clippy::approx_constant, clippy::excessive_precision)]
+        let two_pi = self.context.emit_f32_constant(if stretch { 6.2832 } else { 6.28318530718_f32 });
+        let sector = self.context.builder.f_div(self.context.types.f32_ty, None, two_pi, sides_const)?;
         let half_sec = self.context.emit_f32_constant(0.5);
-        let sector_half = self.context.builder.f_mul(self.context.types.f32_ty, None, sector, half_sec).unwrap();
-        let angle_pos = self.context.emit_mod_scalar(angle, two_pi);
-        let angle_sector = self.context.emit_mod_scalar(angle_pos, sector);
-        let diff = self.context.builder.f_sub(self.context.types.f32_ty, None, angle_sector, sector_half).unwrap();
-        let fold = self.context.emit_glsl_abs(diff);
-        let cos_a = self.context.emit_glsl_cos(fold);
-        let sin_a = self.context.emit_glsl_sin(fold);
-        let x_new = self.context.builder.f_mul(self.context.types.f32_ty, None, cos_a, r).unwrap();
-        let y_new = self.context.builder.f_mul(self.context.types.f32_ty, None, sin_a, r).unwrap();
-        let x_add = self.context.builder.f_add(self.context.types.f32_ty, None, x_new, half).unwrap();
-        let y_add = self.context.builder.f_add(self.context.types.f32_ty, None, y_new, half).unwrap();
-        self.context.emit_vec2(x_add, y_add)
-    }
-
-    fn repeat_coord(&mut self, coord: Word, rx: f32, ry: f32) -> Word {
-        let (x,y) = self.context.extract_vec2_components(coord);
-        let rx_c = self.context.emit_f32_constant(rx.max(0.0001));
-        let ry_c = self.context.emit_f32_constant(ry.max(0.0001));
-        let x_s = self.context.builder.f_mul(self.context.types.f32_ty, None, x, rx_c).unwrap();
-        let y_s = self.context.builder.f_mul(self.context.types.f32_ty, None, y, ry_c).unwrap();
-        let x_f = self.context.emit_fract(x_s);
-        let y_f = self.context.emit_fract(y_s);
+        let sector_half = self.context.builder.f_mul(self.context.types.f32_ty, None, sector, half_sec)?;
+        let angle_pos = if stretch { angle } else { self.context.emit_mod_scalar(angle, two_pi)? };
+        let angle_sector = self.context.emit_mod_scalar(angle_pos, sector)?;
+        let diff = self.context.builder.f_sub(self.context.types.f32_ty, None, angle_sector, sector_half)?;
+        let fold = self.context.emit_glsl_abs(diff)?;
+        let cos_a = self.context.emit_glsl_cos(fold)?;
+        let sin_a = self.context.emit_glsl_sin(fold)?;
+        let x_new = self.context.builder.f_mul(self.context.types.f32_ty, None, cos_a, r)?;
+        let y_new = self.context.builder.f_mul(self.context.types.f32_ty, None, sin_a, r)?;
+        if stretch { return self.context.emit_vec2(x_new, y_new); }
+        self.context.uncenter_coord(x_new, y_new)
+    }
+
+    fn repeat_coord(&mut self, coord: Word, rx: Word, ry: Word) -> Result<Word, CompileError> {
+        let (x,y) = self.context.extract_vec2_components(coord)?;
+        let min_repeat = self.context.emit_f32_constant(0.0001);
+        let rx_c = self.context.emit_glsl_fmax(rx, min_repeat)?;
+        let ry_c = self.context.emit_glsl_fmax(ry, min_repeat)?;
+        let x_s = self.context.builder.f_mul(self.context.types.f32_ty, None, x, rx_c)?;
+        let y_s = self.context.builder.f_mul(self.context.types.f32_ty, None, y, ry_c)?;
+        let x_f = self.context.emit_fract(x_s)?;
+        let y_f = self.context.emit_fract(y_s)?;
         self.context.emit_vec2(x_f, y_f)
     }
 
-    fn pixelate_coord(&mut self, coord: Word, sx: f32, sy: f32) -> Word {
-        let (x,y) = self.context.extract_vec2_components(coord);
-        let sx_c = self.context.emit_f32_constant(sx.max(1.0));
-        let sy_c = self.context.emit_f32_constant(sy.max(1.0));
-        let x_mul = self.context.builder.f_mul(self.context.types.f32_ty, None, x, sx_c).unwrap();
-        let y_mul = self.context.builder.f_mul(self.context.types.f32_ty, None, y, sy_c).unwrap();
-        let x_fl = self.context.emit_glsl_floor(x_mul);
-        let y_fl = self.context.emit_glsl_floor(y_mul);
-        let x_div = self.context.builder.f_div(self.context.types.f32_ty, None, x_fl, sx_c).unwrap();
-        let y_div = self.context.builder.f_div(self.context.types.f32_ty, None, y_fl, sy_c).unwrap();
+    fn pixelate_coord(&mut self, coord: Word, sx: Word, sy: Word) -> Result<Word, CompileError> {
+        let (x,y) = self.context.extract_vec2_components(coord)?;
+        let one = self.context.emit_f32_constant(1.0);
+        let sx_c = self.context.emit_glsl_fmax(sx, one)?;
+        let sy_c = self.context.emit_glsl_fmax(sy, one)?;
+        let x_mul = self.context.builder.f_mul(self.context.types.f32_ty, None, x, sx_c)?;
+        let y_mul = self.context.builder.f_mul(self.context.types.f32_ty, None, y, sy_c)?;
+        let x_fl = self.context.emit_glsl_floor(x_mul)?;
+        let y_fl = self.context.emit_glsl_floor(y_mul)?;
+        let x_div = self.context.builder.f_div(self.context.types.f32_ty, None, x_fl, sx_c)?;
+        let y_div = self.context.builder.f_div(self.context.types.f32_ty, None, y_fl, sy_c)?;
         let half = self.context.emit_f32_constant(0.5);
-        let x_ofs = self.context.builder.f_div(self.context.types.f32_ty, None, half, sx_c).unwrap();
-        let y_ofs = self.context.builder.f_div(self.context.types.f32_ty, None, half, sy_c).unwrap();
-        let x_final = self.context.builder.f_add(self.context.types.f32_ty, None, x_div, x_ofs).unwrap();
-        let y_final = self.context.builder.f_add(self.context.types.f32_ty, None, y_div, y_ofs).unwrap();
+        let x_ofs = self.context.builder.f_div(self.context.types.f32_ty, None, half, sx_c)?;
+        let y_ofs = self.context.builder.f_div(self.context.types.f32_ty, None, half, sy_c)?;
+        let x_final = self.context.builder.f_add(self.context.types.f32_ty, None, x_div, x_ofs)?;
+        let y_final = self.context.builder.f_add(self.context.types.f32_ty, None, y_div, y_ofs)?;
         self.context.emit_vec2(x_final, y_final)
     }
 
-    fn apply_auto_exposure(&mut self, color: Word) -> Word {
+    // (angle / TAU + 0.5, 2 * radius) around the centre, so the edge midpoints are at radius 1
+    fn polar_coord(&mut self, coord: Word) -> Result<Word, CompileError> {
+        let f32_ty = self.context.types.f32_ty;
+        let (x_c, y_c) = self.context.center_coord(coord)?;
+        let v2 = self.context.emit_vec2(x_c, y_c)?;
+        let r = self.context.emit_length2(v2)?;
+        let angle = self.context.emit_glsl_atan2(y_c, x_c)?;
+        let inv_tau = self.context.emit_f32_constant(1.0 / std::f32::consts::TAU);
+        let half = self.context.emit_f32_constant(0.5);
+        let two = self.context.emit_f32_constant(2.0);
+        let u = self.context.builder.f_mul(f32_ty, None, angle, inv_tau)?;
+        let u = self.context.builder.f_add(f32_ty, None, u, half)?;
+        let v = self.context.builder.f_mul(f32_ty, None, r, two)?;
+        self.context.emit_vec2(u, v)
+    }
+
+    // Inverse of polar_coord
+    fn cartesian_coord(&mut self, coord: Word) -> Result<Word, CompileError> {
+        let f32_ty = self.context.types.f32_ty;
+        let (u, v) = self.context.extract_vec2_components(coord)?;
+        let half = self.context.emit_f32_constant(0.5);
+        let tau = self.context.emit_f32_constant(std::f32::consts::TAU);
+        let angle = self.context.builder.f_sub(f32_ty, None, u, half)?;
+        let angle = self.context.builder.f_mul(f32_ty, None, angle, tau)?;
+        let r = self.context.builder.f_mul(f32_ty, None, v, half)?;
+        let cos_a = self.context.emit_glsl_cos(angle)?;
+        let sin_a = self.context.emit_glsl_sin(angle)?;
+        let x_c = self.context.builder.f_mul(f32_ty, None, cos_a, r)?;
+        let y_c = self.context.builder.f_mul(f32_ty, None, sin_a, r)?;
+        self.context.uncenter_coord(x_c, y_c)
+    }
+
+    // Folds each selected axis about 0.5: 0.5 - abs(x - 0.5)
+    fn mirror_coord(&mut self, coord: Word, axis: Word) -> Result<Word, CompileError> {
+        let f32_ty = self.context.types.f32_ty;
+        let (x, y) = self.context.extract_vec2_components(coord)?;
+        let half = self.context.emit_f32_constant(0.5);
+        let one = self.context.emit_f32_constant(1.0);
+        // x for axis 0 and 2, y for 1 and 2
+        let from_y = self.context.builder.f_sub(f32_ty, None, axis, one)?;
+        let from_y = self.context.emit_glsl_abs(from_y)?;
+        let fold_x = self.context.emit_step(half, from_y)?;
+        let fold_y = self.context.emit_step(half, axis)?;
+        let mut folded = [0; 2];
+        for (out, (c, t)) in folded.iter_mut().zip([(x, fold_x), (y, fold_y)]) {
+            let d = self.context.builder.f_sub(f32_ty, None, c, half)?;
+            let d = self.context.emit_glsl_abs(d)?;
+            let f = self.context.builder.f_sub(f32_ty, None, half, d)?;
+            *out = self.context.mix(c, f, t)?;
+        }
+        self.context.emit_vec2(folded[0], folded[1])
+    }
+
+    // repeat_coord with row k shifted by k * offset tiles, for brick patterns
+    fn tile_coord(&mut self, coord: Word, tx: Word, ty: Word, offset: Word) -> Result<Word, CompileError> {
+        let f32_ty = self.context.types.f32_ty;
+        let (x, y) = self.context.extract_vec2_components(coord)?;
+        let min_repeat = self.context.emit_f32_constant(0.0001);
+        let tx = self.context.emit_glsl_fmax(tx, min_repeat)?;
+        let ty = self.context.emit_glsl_fmax(ty, min_repeat)?;
+        let x_s = self.context.builder.f_mul(f32_ty, None, x, tx)?;
+        let y_s = self.context.builder.f_mul(f32_ty, None, y, ty)?;
+        let row = self.context.emit_glsl_floor(y_s)?;
+        let shift = self.context.builder.f_mul(f32_ty, None, offset, row)?;
+        let x_s = self.context.builder.f_add(f32_ty, None, x_s, shift)?;
+        let x_f = self.context.emit_fract(x_s)?;
+        let y_f = self.context.emit_fract(y_s)?;
+        self.context.emit_vec2(x_f, y_f)
+    }
+
+    // Centred p -> (a b; c d) p + t, as rotate_coord and scale_coord work around the centre
+    fn affine_coord(&mut self, coord: Word, m: [Word; 4], t: [Word; 2]) -> Result<Word, CompileError> {
+        let f32_ty = self.context.types.f32_ty;
+        let (x_c, y_c) = self.context.center_coord(coord)?;
+        let mut out = [0; 2];
+        for (o, (row, t)) in out.iter_mut().zip([([m[0], m[1]], t[0]), ([m[2], m[3]], t[1])]) {
+            let ax = self.context.builder.f_mul(f32_ty, None, row[0], x_c)?;
+            let by = self.context.builder.f_mul(f32_ty, None, row[1], y_c)?;
+            let sum = self.context.builder.f_add(f32_ty, None, ax, by)?;
+            *o = self.context.builder.f_add(f32_ty, None, sum, t)?;
+        }
+        self.context.uncenter_coord(out[0], out[1])
+    }
+
+    fn apply_post_process(&mut self, mut color: Word) -> Result<Word, CompileError> {
+        let pp = self.post_process.clone();
+        if let Some(max_gain) = pp.auto_exposure { color = self.apply_auto_exposure(color, max_gain)?; }
+        // Tone mapping squeezes highlights into SDR; HDR outputs keep them
+        let tone_map = if self.output_transform.is_hdr() { ToneMap::None } else { pp.tone_map };
+        color = match tone_map {
+            ToneMap::None => color,
+            ToneMap::Aces => self.tone_map_aces(color)?,
+            ToneMap::Reinhard => self.tone_map_reinhard(color)?,
+        };
+        if pp.gamma != 1.0 {
+            let inv = self.context.emit_f32_constant(1.0 / pp.gamma);
+            let zero = self.context.emit_f32_constant(0.0);
+            color = self.context.apply_rgb(color, |ctx, c| {
+                let c = ctx.emit_glsl_fmax(c, zero)?;
+                ctx.emit_glsl_pow(c, inv)
+            })?;
+        }
+        if pp.lut.len() >= 2 { color = self.apply_lut(color, &pp.lut)?; }
+        Ok(color)
+    }
+
+    fn linear_light(&mut self, color: Word) -> Result<Word, CompileError> {
+        match self.context.color_space {
+            ColorSpace::Srgb => self.context.srgb_to_linear(color),
+            ColorSpace::Linear => Ok(color),
+        }
+    }
+
+    fn apply_auto_exposure(&mut self, color: Word, max_gain: f32) -> Result<Word, CompileError> {
         // simple luma-based gain: factor = min(max_gain, 1/(luma+eps))
-        let l = self.context.emit_luma(color);
+        let l = self.context.emit_luma(color)?;
         let eps = self.context.emit_f32_constant(0.02); // avoid huge blow-ups for very dark
-        let denom = self.context.builder.f_add(self.context.types.f32_ty, None, l, eps).unwrap();
+        let denom = self.context.builder.f_add(self.context.types.f32_ty, None, l, eps)?;
         let one = self.context.emit_f32_constant(1.0);
-        let raw_gain = self.context.builder.f_div(self.context.types.f32_ty, None, one, denom).unwrap();
-        let max_gain_c = self.context.emit_f32_constant(6.0);
-        use rspirv::dr::Operand;
+        let raw_gain = self.context.builder.f_div(self.context.types.f32_ty, None, one, denom)?;
+        let max_gain_c = self.context.emit_f32_constant(max_gain);
         // gain = min(raw_gain, max_gain)
-        let gain = self.context.builder.ext_inst(self.context.types.f32_ty, None, self.context.glsl_ext, 39, vec![Operand::IdRef(raw_gain), Operand::IdRef(max_gain_c)]).unwrap();
-        let r = self.context.extract_component(color,0);
-        let g = self.context.extract_component(color,1);
-        let b = self.context.extract_component(color,2);
-        let a = self.context.extract_component(color,3);
-        let r2 = self.context.builder.f_mul(self.context.types.f32_ty, None, r, gain).unwrap();
-        let g2 = self.context.builder.f_mul(self.context.types.f32_ty, None, g, gain).unwrap();
-        let b2 = self.context.builder.f_mul(self.context.types.f32_ty, None, b, gain).unwrap();
+        let gain = self.context.emit_glsl_fmin(raw_gain, max_gain_c)?;
+        let r = self.context.extract_component(color,0)?;
+        let g = self.context.extract_component(color,1)?;
+        let b = self.context.extract_component(color,2)?;
+        let a = self.context.extract_component(color,3)?;
+        let r2 = self.context.builder.f_mul(self.context.types.f32_ty, None, r, gain)?;
+        let g2 = self.context.builder.f_mul(self.context.types.f32_ty, None, g, gain)?;
+        let b2 = self.context.builder.f_mul(self.context.types.f32_ty, None, b, gain)?;
         self.context.construct_vec4(r2,g2,b2,a)
     }
 
-    fn tone_map_aces(&mut self, color: Word) -> Word {
+    fn tone_map_aces(&mut self, color: Word) -> Result<Word, CompileError> {
         // ACES filmic approximation per channel:
         // (x*(a*x + b)) / (x*(c*x + d) + e) with constants
         let a_c = self.context.emit_f32_constant(2.51);
@@ -417,23 +835,70 @@ impl SpirvEmitter {
         let c_c = self.context.emit_f32_constant(2.43);
         let d_c = self.context.emit_f32_constant(0.59);
         let e_c = self.context.emit_f32_constant(0.14);
-        let r = self.context.extract_component(color,0);
-        let g = self.context.extract_component(color,1);
-        let b = self.context.extract_component(color,2);
-        let a = self.context.extract_component(color,3);
-        let map = |ctx: &mut SpirvContext, ch: Word, a_c: Word, b_c: Word, c_c: Word, d_c: Word, e_c: Word| -> Word {
-            let a_x = ctx.builder.f_mul(ctx.types.f32_ty, None, a_c, ch).unwrap();
-            let a_x_plus_b = ctx.builder.f_add(ctx.types.f32_ty, None, a_x, b_c).unwrap();
-            let ch_mul = ctx.builder.f_mul(ctx.types.f32_ty, None, ch, a_x_plus_b).unwrap();
-            let c_x = ctx.builder.f_mul(ctx.types.f32_ty, None, c_c, ch).unwrap();
-            let c_x_plus_d = ctx.builder.f_add(ctx.types.f32_ty, None, c_x, d_c).unwrap();
-            let ch_mul2 = ctx.builder.f_mul(ctx.types.f32_ty, None, ch, c_x_plus_d).unwrap();
-            let denom = ctx.builder.f_add(ctx.types.f32_ty, None, ch_mul2, e_c).unwrap();
-            ctx.builder.f_div(ctx.types.f32_ty, None, ch_mul, denom).unwrap()
+        let r = self.context.extract_component(color,0)?;
+        let g = self.context.extract_component(color,1)?;
+        let b = self.context.extract_component(color,2)?;
+        let a = self.context.extract_component(color,3)?;
+        let map = |ctx: &mut SpirvContext, ch: Word, a_c: Word, b_c: Word, c_c: Word, d_c: Word, e_c: Word| -> Result<Word, CompileError> {
+            let a_x = ctx.builder.f_mul(ctx.types.f32_ty, None, a_c, ch)?;
+            let a_x_plus_b = ctx.builder.f_add(ctx.types.f32_ty, None, a_x, b_c)?;
+            let ch_mul = ctx.builder.f_mul(ctx.types.f32_ty, None, ch, a_x_plus_b)?;
+            let c_x = ctx.builder.f_mul(ctx.types.f32_ty, None, c_c, ch)?;
+            let c_x_plus_d = ctx.builder.f_add(ctx.types.f32_ty, None, c_x, d_c)?;
+            let ch_mul2 = ctx.builder.f_mul(ctx.types.f32_ty, None, ch, c_x_plus_d)?;
+            let denom = ctx.builder.f_add(ctx.types.f32_ty, None, ch_mul2, e_c)?;
+            Ok(ctx.builder.f_div(ctx.types.f32_ty, None, ch_mul, denom)?)
         };
-        let r2 = map(&mut self.context, r, a_c,b_c,c_c,d_c,e_c);
-        let g2 = map(&mut self.context, g, a_c,b_c,c_c,d_c,e_c);
-        let b2 = map(&mut self.context, b, a_c,b_c,c_c,d_c,e_c);
+        let r2 = map(&mut self.context, r, a_c,b_c,c_c,d_c,e_c)?;
+        let g2 = map(&mut self.context, g, a_c,b_c,c_c,d_c,e_c)?;
+        let b2 = map(&mut self.context, b, a_c,b_c,c_c,d_c,e_c)?;
         self.context.construct_vec4(r2,g2,b2,a)
     }
+
+    fn tone_map_reinhard(&mut self, color: Word) -> Result<Word, CompileError> {
+        // x / (1 + x), negatives clipped first so the denominator stays >= 1
+        let zero = self.context.emit_f32_constant(0.0);
+        let one = self.context.emit_f32_constant(1.0);
+        self.context.apply_rgb(color, |ctx, c| {
+            let x = ctx.emit_glsl_fmax(c, zero)?;
+            let denom = ctx.builder.f_add(ctx.types.f32_ty, None, x, one)?;
+            Ok(ctx.builder.f_div(ctx.types.f32_ty, None, x, denom)?)
+        })
+    }
+
+    fn apply_lut(&mut self, color: Word, lut: &[[f32; 3]]) -> Result<Word, CompileError> {
+        // Piecewise linear as a sum of ramps, no array indexing:
+        // x = clamp(c, 0, 1) * (n - 1); out = lut[0] + sum_k (lut[k] - lut[k-1]) * clamp(x - (k - 1), 0, 1)
+        let f32_ty = self.context.types.f32_ty;
+        let scale = self.context.emit_f32_constant((lut.len() - 1) as f32);
+        let mut channels = [0; 3];
+        for (i, channel) in channels.iter_mut().enumerate() {
+            let c = self.context.extract_component(color, i as u32)?;
+            let c = self.context.clamp01(c)?;
+            let x = self.context.builder.f_mul(f32_ty, None, c, scale)?;
+            let mut out = self.context.emit_f32_constant(lut[0][i]);
+            for k in 1..lut.len() {
+                let delta = lut[k][i] - lut[k - 1][i];
+                if delta == 0.0 { continue; }
+                let offset = self.context.emit_f32_constant((k - 1) as f32);
+                let t = self.context.builder.f_sub(f32_ty, None, x, offset)?;
+                let t = self.context.clamp01(t)?;
+                let delta = self.context.emit_f32_constant(delta);
+                let step = self.context.builder.f_mul(f32_ty, None, t, delta)?;
+                out = self.context.builder.f_add(f32_ty, None, out, step)?;
+            }
+            *channel = out;
+        }
+        let a = self.context.extract_component(color, 3)?;
+        self.context.construct_vec4(channels[0], channels[1], channels[2], a)
+    }
+}
+
+fn lookup(ir: &IRBuilder, id: NodeId) -> Result<&IRNode, CompileError> {
+    ir.nodes.get(id.0).ok_or_else(|| CompileError::InvalidProgram(format!("dangling node id {}", id.0)))
+}
+
+// Hydra's modulate displaces by 0.1 unless told otherwise; modulateScale keeps 0.5
+fn modulate_default(ty: BinaryType) -> f32 {
+    if matches!(ty, BinaryType::ModulateScale) { 0.5 } else { 0.1 }
 }
@@ -0,0 +1,16 @@
+// SPIR-V to WGSL through naga (spv-in -> validate -> wgsl-out), no spirv-cross involved
+
+use naga::back::wgsl;
+use naga::front::spv;
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+use crate::error::CompileError;
+
+pub fn compile_to_wgsl(words: &[u32]) -> Result<String, CompileError> {
+    let module = spv::parse_u8_slice(bytemuck::cast_slice(words), &spv::Options::default())
+        .map_err(|e| CompileError::Naga(e.to_string()))?;
+    let info = Validator::new(ValidationFlags::all(), Capabilities::empty())
+        .validate(&module)
+        .map_err(|e| CompileError::Naga(e.into_inner().to_string()))?;
+    wgsl::write_string(&module, &info, wgsl::WriterFlags::empty())
+        .map_err(|e| CompileError::Naga(e.to_string()))
+}
@@ -0,0 +1,223 @@
+// High-level entry point: Hydra source -> IR -> SPIR-V -> target source, in one call
+// (Target::GlslDirect prints its source from the IR and skips SPIR-V)
+
+use crate::backend::glsl_direct::GlslEmitter;
+#[cfg(feature = "spirv-cross")]
+use crate::backend::spirv_cross::CrossOptions;
+#[cfg(feature = "spirv-cross")]
+use crate::backend::spirv_glsl::{compile_to_glsl, compile_to_glsl_es};
+#[cfg(feature = "spirv-cross")]
+use crate::backend::spirv_hlsl::compile_to_hlsl;
+#[cfg(feature = "spirv-cross")]
+use crate::backend::spirv_msl::compile_to_msl;
+#[cfg(feature = "spirv-cross")]
+use crate::backend::spirv_reflect::reflect;
+use crate::backend::spirv_reflect::{interface_from_ir, ShaderInterface};
+use crate::backend::spirv_context::Aspect;
+use crate::backend::spirv_visitor::SpirvEmitter;
+use crate::backend::spirv_wgsl::compile_to_wgsl;
+use crate::error::{CompileError, Diagnostic};
+use crate::external::ExternalSource;
+use crate::frontend::hydra_ecma::hydra_ecma;
+use crate::globals::CUSTOM_SLOTS;
+use crate::ir::opt::FoldIdentity;
+use crate::ir::split::split_passes;
+use crate::ir::{rewrite, IRBuilder, NodeId, MAX_SUPERSAMPLE};
+use crate::output::{AlphaMode, ColorSpace, OutputTransform};
+use crate::passes::{MAX_HISTORY, MAX_PASSES};
+use crate::post_process::{PostProcess, MAX_LUT_SIZE};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Target {
+    // SPIR-V words only, no cross-compiled source
+    SpirV,
+    #[default]
+    Glsl,
+    // GLSL 460 printed straight from the IR by backend::glsl_direct, same interface as Glsl;
+    // no SPIR-V is emitted and the spirv-cross feature is not needed
+    GlslDirect,
+    // GLSL ES 3.00 for WebGL2, uniform blocks flattened (see spirv_glsl::compile_to_glsl_es)
+    GlslEs,
+    Hlsl,
+    Msl,
+    // Translated by naga rather than spirv-cross (cross options do not apply)
+    Wgsl,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OptLevel {
+    None,
+    // Fold no-op nodes (see ir::opt)
+    #[default]
+    Basic,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    pub target: Target,
+    // Overridden by a `postProcess(...)` statement in the sketch
+    pub post_process: PostProcess,
+    pub opt_level: OptLevel,
+    pub aspect: Aspect,
+    // Alpha encoding of the main shader's output
+    pub alpha: AlphaMode,
+    // Space the sketch's ops work in; textures are decoded from sRGB into it
+    pub color_space: ColorSpace,
+    // What the output target expects: Srgb for a UNORM target, Linear for an sRGB view, which
+    // encodes on write. The main shader converts once, after post-processing and the clamp.
+    pub output_space: ColorSpace,
+    // SDR or an HDR encoding; HDR outputs skip tone mapping and the clamp to 1.0 and ignore
+    // output_space
+    pub output_transform: OutputTransform,
+    // Samples per pixel for the whole sketch, at jittered points inside the pixel; 0 or 1 is off,
+    // at most MAX_SUPERSAMPLE. `supersample(n)` in a sketch covers one subtree instead.
+    pub supersample: u32,
+    // Embed the sketch and per-node OpLine/OpName in the SPIR-V
+    pub debug_info: bool,
+    // Emit one function per IR node (readable target source)
+    pub node_functions: bool,
+    // Versions and binding remap for the cross-compiled targets
+    #[cfg(feature = "spirv-cross")]
+    pub cross: CrossOptions,
+    // Names a sketch can use as arguments, bound to Globals::custom[i] (at most CUSTOM_SLOTS)
+    pub custom_globals: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompiledShader {
+    // Empty for Target::GlslDirect
+    pub spirv: Vec<u32>,
+    // Cross-compiled source; None for Target::SpirV
+    pub source: Option<String>,
+    pub interface: ShaderInterface,
+    // Files the host should load into the `s0..s3` textures
+    pub sources: Vec<ExternalSource>,
+    // Drawn in order before this shader, pass i into target `pass<i>` (see crate::passes)
+    pub passes: Vec<CompiledPass>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+// An intermediate pass for `blur`, `edges`, `bloom` or a temporal effect, compiled for the same target
+#[derive(Debug, Clone)]
+pub struct CompiledPass {
+    pub spirv: Vec<u32>,
+    pub source: Option<String>,
+    pub interface: ShaderInterface,
+    // Past frames of this pass the host keeps as `pass<i>_history` (see crate::passes), 0 for none
+    pub history: u32,
+}
+
+#[derive(Default)]
+pub struct Compiler {
+    options: CompileOptions,
+}
+
+impl Compiler {
+    pub fn new(options: CompileOptions) -> Self { Self { options } }
+
+    pub fn options(&self) -> &CompileOptions { &self.options }
+
+    pub fn compile(&self, source: &str) -> Result<CompiledShader, CompileError> {
+        self.compile_named("hydra.js", source)
+    }
+
+    // `file_name` is only used for debug info
+    pub fn compile_named(&self, file_name: &str, source: &str) -> Result<CompiledShader, CompileError> {
+        if self.options.custom_globals.len() > CUSTOM_SLOTS {
+            return Err(CompileError::Unsupported(format!("more than {} custom globals", CUSTOM_SLOTS)));
+        }
+        let script = hydra_ecma(source)?;
+        let mut ir = IRBuilder::new().with_custom_globals(&self.options.custom_globals);
+        let root = ir.build_script(&script);
+        if let Some(e) = ir.errors.drain(..).next() {
+            return Err(e);
+        }
+        let mut root = root.ok_or(CompileError::EmptyProgram)?;
+        if self.options.opt_level == OptLevel::Basic {
+            root = rewrite(&mut FoldIdentity, &mut ir, root);
+        }
+
+        let post_process = ir.post_process.clone().unwrap_or_else(|| self.options.post_process.clone());
+        if post_process.lut.len() == 1 || post_process.lut.len() > MAX_LUT_SIZE {
+            return Err(CompileError::Unsupported(format!("LUT needs 2 to {} entries, got {}", MAX_LUT_SIZE, post_process.lut.len())));
+        }
+        if post_process.gamma <= 0.0 {
+            return Err(CompileError::Unsupported(format!("gamma must be positive, got {}", post_process.gamma)));
+        }
+
+        if let OutputTransform::ScRgb { paper_white } | OutputTransform::Pq { paper_white } = self.options.output_transform
+            && paper_white <= 0.0
+        {
+            return Err(CompileError::Unsupported(format!("paper white must be positive, got {}", paper_white)));
+        }
+
+        if self.options.supersample > MAX_SUPERSAMPLE {
+            return Err(CompileError::Unsupported(format!("{} samples per pixel, at most {}", self.options.supersample, MAX_SUPERSAMPLE)));
+        }
+
+        let split = split_passes(&mut ir, root);
+        if split.passes.len() > MAX_PASSES {
+            return Err(CompileError::Unsupported(format!("{} render passes, at most {}", split.passes.len(), MAX_PASSES)));
+        }
+        if let Some(&frames) = ir.pass_history.iter().find(|&&f| f > MAX_HISTORY) {
+            return Err(CompileError::Unsupported(format!("{} frames of history, at most {}", frames, MAX_HISTORY)));
+        }
+        let mut passes = Vec::new();
+        for (i, &pass) in split.passes.iter().enumerate() {
+            let (spirv, source, interface) = self.emit(&ir, pass, None, file_name, source)?;
+            passes.push(CompiledPass { interface, spirv, source, history: ir.history_frames(i as u32) });
+        }
+
+        let (spirv, source, interface) = self.emit(&ir, split.root, Some(post_process), file_name, source)?;
+        Ok(CompiledShader { spirv, source, interface, sources: ir.external_sources, passes, diagnostics: ir.diagnostics })
+    }
+
+    // SPIR-V, target source and interface for the graph under `root`; no post-processing means an
+    // intermediate pass
+    fn emit(&self, ir: &IRBuilder, root: NodeId, post_process: Option<PostProcess>, file_name: &str, sketch: &str) -> Result<(Vec<u32>, Option<String>, ShaderInterface), CompileError> {
+        if self.options.target == Target::GlslDirect {
+            let mut direct = GlslEmitter::new().with_aspect(self.options.aspect).with_color_space(self.options.color_space)
+                .with_supersample(self.options.supersample);
+            direct = match post_process {
+                Some(pp) => direct.with_post_process(pp).with_alpha(self.options.alpha).with_output_space(self.options.output_space)
+                    .with_output_transform(self.options.output_transform),
+                None => direct.with_intermediate_output(),
+            };
+            let source = direct.emit_ir(ir, Some(root))?;
+            return Ok((Vec::new(), Some(source), interface_from_ir(ir, root)));
+        }
+
+        let mut emitter = SpirvEmitter::new().with_aspect(self.options.aspect).with_color_space(self.options.color_space)
+            .with_supersample(self.options.supersample);
+        emitter = match post_process {
+            Some(pp) => emitter.with_post_process(pp).with_alpha(self.options.alpha).with_output_space(self.options.output_space)
+                .with_output_transform(self.options.output_transform),
+            None => emitter.with_intermediate_output(),
+        };
+        if self.options.debug_info { emitter = emitter.with_source(file_name, sketch); }
+        if self.options.node_functions { emitter = emitter.with_node_functions(); }
+        let spirv = emitter.emit_ir(ir, Some(root))?;
+
+        let source = match self.options.target {
+            Target::SpirV | Target::GlslDirect => None,
+            #[cfg(feature = "spirv-cross")]
+            Target::Glsl => Some(compile_to_glsl(&spirv, &self.options.cross)?.to_string()),
+            #[cfg(feature = "spirv-cross")]
+            Target::GlslEs => Some(compile_to_glsl_es(&spirv, &self.options.cross)?.to_string()),
+            #[cfg(feature = "spirv-cross")]
+            Target::Hlsl => Some(compile_to_hlsl(&spirv, &self.options.cross)?.to_string()),
+            #[cfg(feature = "spirv-cross")]
+            Target::Msl => Some(compile_to_msl(&spirv, &self.options.cross)?.to_string()),
+            #[cfg(not(feature = "spirv-cross"))]
+            target @ (Target::Glsl | Target::GlslEs | Target::Hlsl | Target::Msl) => {
+                return Err(CompileError::Unsupported(format!("{:?} output needs the spirv-cross feature", target)));
+            }
+            Target::Wgsl => Some(compile_to_wgsl(&spirv)?),
+        };
+        #[cfg(feature = "spirv-cross")]
+        let interface = reflect(&spirv)?;
+        #[cfg(not(feature = "spirv-cross"))]
+        let interface = interface_from_ir(ir, root);
+        Ok((spirv, source, interface))
+    }
+}
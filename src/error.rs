@@ -0,0 +1,83 @@
+// Error type shared by the frontend and the SPIR-V emitter
+
+use std::fmt;
+use swc_common::Span;
+
+#[derive(Debug)]
+pub enum CompileError {
+    // The sketch is not valid JavaScript; the span is where parsing stopped
+    Parse(Diagnostic),
+    // rspirv rejected an instruction (e.g. emitted outside a block)
+    Builder(rspirv::dr::Error),
+    // No drawable chain was found in the sketch
+    EmptyProgram,
+    // The IR graph is malformed (dangling node ids, missing coordinates, ...)
+    InvalidProgram(String),
+    // The sketch uses a function or argument form the compiler does not implement
+    Unsupported(String),
+    // A call to a function that is neither a Hydra source nor a chain method, at its span
+    UnknownFunction(Diagnostic),
+    // spirv-cross failed to reflect or cross-compile the module
+    #[cfg(feature = "spirv-cross")]
+    CrossCompile(spirv_cross2::SpirvCrossError),
+    // naga rejected the module while translating it to WGSL
+    Naga(String),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::Parse(d) => write!(f, "parse error: {}", d),
+            CompileError::Builder(e) => write!(f, "SPIR-V builder error: {}", e),
+            CompileError::EmptyProgram => write!(f, "sketch contains no drawable chain"),
+            CompileError::InvalidProgram(msg) => write!(f, "invalid program: {}", msg),
+            CompileError::Unsupported(msg) => write!(f, "unsupported: {}", msg),
+            CompileError::UnknownFunction(d) => write!(f, "{}", d),
+            #[cfg(feature = "spirv-cross")]
+            CompileError::CrossCompile(e) => write!(f, "cross-compile error: {}", e),
+            CompileError::Naga(msg) => write!(f, "naga error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CompileError::Builder(e) => Some(e),
+            #[cfg(feature = "spirv-cross")]
+            CompileError::CrossCompile(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rspirv::dr::Error> for CompileError {
+    fn from(e: rspirv::dr::Error) -> Self { CompileError::Builder(e) }
+}
+
+#[cfg(feature = "spirv-cross")]
+impl From<spirv_cross2::SpirvCrossError> for CompileError {
+    fn from(e: spirv_cross2::SpirvCrossError) -> Self { CompileError::CrossCompile(e) }
+}
+
+// Non-fatal problem found while compiling, e.g. a Hydra function the IR builder skipped
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    // 1-based line/column in `source`, the text the span was parsed from (see hydra_ecma)
+    pub fn line_col(&self, source: &str) -> (usize, usize) {
+        let offset = (self.span.lo.0 as usize).saturating_sub(1).min(source.len());
+        let before = &source[..offset];
+        let line = before.matches('\n').count() + 1;
+        let col = offset - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        (line, col)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.message) }
+}
@@ -0,0 +1,45 @@
+// External sources `s0..s3`: images and video bound as sampled textures, read with `src(s0)`.
+// A sketch declares what to load with `s0.initImage("logo.png")` or `s0.initVideo("clip.y4m")`;
+// the host loads the file and uploads it to the slot's texture (see examples/render/media.rs).
+// Slots that are sampled but never initialised still get a binding and read as the host's placeholder.
+
+pub const EXTERNAL_SLOTS: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind { Image, Video }
+
+// Addressing outside 0..1 on one axis. The shader applies it to the coordinate, and hosts give the
+// slot's sampler the matching address mode so filtering at the edges agrees (Border samples
+// clamp-to-edge; the shader zeroes everything outside).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Wrap {
+    // fract(st), as Hydra's `src`
+    #[default]
+    Repeat,
+    // Edge texels extend outwards
+    Clamp,
+    // Every other repeat is flipped
+    Mirror,
+    // Transparent black outside the image
+    Border,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalSource {
+    pub slot: u32,
+    pub kind: MediaKind,
+    // As written in the sketch; hosts resolve relative paths against the sketch's directory
+    pub path: String,
+    // Horizontal and vertical addressing, from `{ wrap }` or regl-style `{ wrapS, wrapT }`
+    pub wrap: [Wrap; 2],
+}
+
+// Separate texture and sampler per slot at set 0, after the Globals block:
+// s0 at bindings 1 and 2, s1 at 3 and 4, ...
+pub fn texture_binding(slot: u32) -> u32 { 1 + 2 * slot }
+
+pub fn sampler_binding(slot: u32) -> u32 { 2 + 2 * slot }
+
+pub fn texture_name(slot: u32) -> String { format!("s{}", slot) }
+
+pub fn sampler_name(slot: u32) -> String { format!("s{}_sampler", slot) }
@@ -3,11 +3,13 @@ use swc_common::{
     errors::{ColorConfig, Handler},
     SourceMap,
     FileName,
+    Spanned,
 };
 use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax};
 use swc_ecma_ast::Script;
+use crate::error::{CompileError, Diagnostic};
 
-pub fn hydra_ecma(source: &str) -> Script {
+pub fn hydra_ecma(source: &str) -> Result<Script, CompileError> {
     let cm: Lrc<SourceMap> = Default::default();
     let handler =
         Handler::with_tty_emitter(ColorConfig::Auto, true, false,
@@ -34,13 +36,12 @@ pub fn hydra_ecma(source: &str) -> Script {
         e.into_diagnostic(&handler).emit();
     }
 
-    let ast = parser
+    parser
         .parse_script()
         .map_err(|e| {
             // Unrecoverable fatal error occurred
-            e.into_diagnostic(&handler).emit()
+            let diagnostic = Diagnostic { message: e.kind().msg().into_owned(), span: e.span() };
+            e.into_diagnostic(&handler).emit();
+            CompileError::Parse(diagnostic)
         })
-        .expect("failed to parser module");
-
-    ast
 }
@@ -0,0 +1,66 @@
+// Per-frame values shared by every shader: the `Globals` uniform block at set 0, binding 0.
+// The host fills this struct and uploads it as-is; its layout matches the std140 block declared
+// in SpirvContext::new and glsl_direct::HEADER.
+
+use bytemuck::{Pod, Zeroable};
+use crate::passes::MAX_HISTORY;
+
+pub const CUSTOM_SLOTS: usize = 8;
+
+// Globals::frame counts modulo this: the least common multiple of every history ring length up to
+// MAX_HISTORY, so `frame % ring` runs on across the wrap, and small enough to be exact in an f32
+// (a plain f32 counter stops at 2^24, about 77 hours at 60 fps). Hosts that set `frame` themselves
+// have to wrap it the same way.
+pub const FRAME_WRAP: u32 = frame_wrap();
+
+const fn frame_wrap() -> u32 {
+    const fn gcd(a: u32, b: u32) -> u32 { if b == 0 { a } else { gcd(b, a % b) } }
+    let (mut lcm, mut n) = (1, 2);
+    while n <= MAX_HISTORY {
+        lcm = lcm / gcd(lcm, n) * n;
+        n += 1;
+    }
+    lcm
+}
+
+const _: () = assert!(FRAME_WRAP <= 1 << 24);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Pod, Zeroable)]
+pub struct Globals {
+    pub time: f32,
+    // Frames drawn, modulo FRAME_WRAP
+    pub frame: f32,
+    pub resolution: [f32; 2],
+    // Pixels from the top-left corner, like Hydra's `mouse`
+    pub mouse: [f32; 2],
+    // Bitmask: 1 left, 2 right, 4 middle
+    pub mouse_buttons: f32,
+    pub dt: f32,
+    pub fps: f32,
+    pub bpm: f32,
+    // Position within the current beat, 0..1
+    pub beat: f32,
+    pub _pad: f32,
+    // Values named by CompileOptions::custom_globals, in declaration order
+    pub custom: [f32; CUSTOM_SLOTS],
+}
+
+impl Globals {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self { resolution: [width, height], bpm: 30.0, ..Default::default() }
+    }
+
+    // Moves to the next frame at `time` seconds, deriving dt, fps, frame and beat
+    pub fn advance(&mut self, time: f32) {
+        self.dt = (time - self.time).max(0.0);
+        self.time = time;
+        self.frame = ((self.frame as u32 + 1) % FRAME_WRAP) as f32;
+        if self.dt > 0.0 {
+            // Smoothed so the value is readable when driving visuals
+            let fps = 1.0 / self.dt;
+            self.fps = if self.fps == 0.0 { fps } else { self.fps * 0.9 + fps * 0.1 };
+        }
+        self.beat = (time * self.bpm / 60.0).fract();
+    }
+}
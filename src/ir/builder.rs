@@ -1,23 +1,241 @@
 // IR builder extracted from former backend::hydra_ir
+use swc_common::{Span, Spanned, DUMMY_SP};
 use swc_ecma_ast::*;
 use super::node::*;
+use crate::error::{CompileError, Diagnostic};
+use crate::external::{ExternalSource, MediaKind, Wrap, EXTERNAL_SLOTS};
+use crate::passes::MAX_HISTORY;
+use crate::post_process::{PostProcess, ToneMap};
 
 pub struct IRBuilder {
     pub nodes: Vec<IRNode>,
+    // Calls and arguments that were skipped while building
+    pub diagnostics: Vec<Diagnostic>,
+    // Problems that fail the compile, e.g. an unknown function; building carries on past them
+    pub errors: Vec<CompileError>,
+    // Names resolving to Global::Custom(index)
+    custom_globals: Vec<String>,
+    // Set by a `postProcess(...)` statement; replaces CompileOptions::post_process when present
+    pub post_process: Option<PostProcess>,
+    // From `s0.initImage(...)` / `s0.initVideo(...)` statements, one per slot
+    pub external_sources: Vec<ExternalSource>,
+    // Frames of history kept per pass, filled in by ir::split
+    pub pass_history: Vec<u32>,
+}
+
+impl Default for IRBuilder {
+    fn default() -> Self { Self::new() }
 }
 
 impl IRBuilder {
-    pub fn new() -> Self { Self { nodes: Vec::new() } }
+    pub fn new() -> Self { Self { nodes: Vec::new(), diagnostics: Vec::new(), errors: Vec::new(), custom_globals: Vec::new(), post_process: None, external_sources: Vec::new(), pass_history: Vec::new() } }
+
+    // Identifiers a sketch may use for user-defined values, e.g. `osc(intensity)`
+    pub fn with_custom_globals(mut self, names: &[String]) -> Self {
+        self.custom_globals = names.to_vec();
+        self
+    }
 
-    fn push(&mut self, kind: IRKind) -> NodeId {
+    fn push(&mut self, kind: IRKind, span: Span) -> NodeId {
         let id = NodeId(self.nodes.len());
-        self.nodes.push(IRNode { kind });
+        self.nodes.push(IRNode { kind, span });
         id
     }
 
+    // Public node access for passes (see ir::visit)
+    pub fn add_node(&mut self, kind: IRKind) -> NodeId { self.push(kind, DUMMY_SP) }
+
+    pub fn node(&self, id: NodeId) -> &IRNode { &self.nodes[id.0] }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut IRNode { &mut self.nodes[id.0] }
+
+    // Relink the `slot`-th child (in `IRKind::children` order) of `parent`
+    pub fn set_child(&mut self, parent: NodeId, slot: usize, child: NodeId) {
+        if let Some(c) = self.nodes[parent.0].kind.children_mut().into_iter().nth(slot) { *c = child; }
+    }
+
+    // The first chain in the script is drawn; `postProcess(...)` and `s0.initImage(...)` statements may appear anywhere
     pub fn build_script(&mut self, script: &Script) -> Option<NodeId> {
-        for stmt in &script.body { if let Stmt::Expr(e) = stmt { return self.build_expr(&e.expr); } }
-        None
+        let mut root = None;
+        for stmt in &script.body {
+            let Stmt::Expr(e) = stmt else { continue };
+            if let Expr::Call(call) = &*e.expr
+                && let Callee::Expr(callee) = &call.callee {
+                if let Expr::Ident(ident) = &**callee && ident.sym.as_ref() == "postProcess" {
+                    self.post_process = Some(self.build_post_process(call));
+                    continue;
+                }
+                if let Expr::Member(member) = &**callee
+                    && let (Expr::Ident(obj), MemberProp::Ident(method)) = (&*member.obj, &member.prop)
+                    && let Some(slot) = Self::external_slot(obj.sym.as_ref()) {
+                    self.build_external_init(slot, method.sym.as_ref(), call);
+                    continue;
+                }
+            }
+            if root.is_none() { root = self.build_expr(&e.expr); }
+        }
+        root
+    }
+
+    // `postProcess("reinhard")` picks a tone map alone; the object form sets any of
+    // `{ exposure: 4, toneMap: "aces", gamma: 2.2, lut: [0, 0.5, 1] }`, where `lut` entries are
+    // numbers or [r, g, b] triples. Stages that are not mentioned are off.
+    fn build_post_process(&mut self, call: &CallExpr) -> PostProcess {
+        let mut pp = PostProcess::none();
+        let Some(first) = call.args.first() else { return pp };
+        match &*first.expr {
+            Expr::Lit(Lit::Str(s)) => {
+                if let Some(tone_map) = self.tone_map(s) { pp.tone_map = tone_map; }
+            }
+            Expr::Object(object) => {
+                for prop in &object.props {
+                    let PropOrSpread::Prop(prop) = prop else { continue };
+                    let Prop::KeyValue(kv) = &**prop else {
+                        self.warn("unsupported `postProcess` property ignored".into(), prop.span());
+                        continue;
+                    };
+                    let key = match &kv.key {
+                        PropName::Ident(i) => i.sym.as_ref(),
+                        PropName::Str(s) => s.value.as_ref(),
+                        _ => "",
+                    };
+                    let value = &*kv.value;
+                    match (key, value) {
+                        ("exposure", Expr::Lit(Lit::Num(n))) => pp.auto_exposure = Some(n.value as f32),
+                        // `exposure: true` keeps the default cap
+                        ("exposure", Expr::Lit(Lit::Bool(b))) => pp.auto_exposure = if b.value { PostProcess::default().auto_exposure } else { None },
+                        ("toneMap", Expr::Lit(Lit::Str(s))) => {
+                            if let Some(tone_map) = self.tone_map(s) { pp.tone_map = tone_map; }
+                        }
+                        ("gamma", Expr::Lit(Lit::Num(n))) => pp.gamma = n.value as f32,
+                        ("lut", Expr::Array(array)) => pp.lut = self.lut(array),
+                        _ => self.warn(format!("unsupported `postProcess` property `{}` ignored", key), kv.span()),
+                    }
+                }
+            }
+            other => self.warn("`postProcess` expects a tone map name or an options object".into(), other.span()),
+        }
+        pp
+    }
+
+    // `s0.initImage("logo.png")` or `s0.initVideo("clip.y4m")`, optionally followed by Hydra's texture
+    // options `{ wrap: "mirror" }` or `{ wrapS: "repeat", wrapT: "clamp" }`; a later init of the same slot replaces it
+    fn build_external_init(&mut self, slot: u32, method: &str, call: &CallExpr) {
+        let kind = match method {
+            "initImage" => MediaKind::Image,
+            "initVideo" => MediaKind::Video,
+            _ => {
+                self.warn(format!("`s{}.{}` is not supported, ignored", slot, method), call.span);
+                return;
+            }
+        };
+        let Some(first) = call.args.first() else {
+            self.warn(format!("`{}` expects a file path", method), call.span);
+            return;
+        };
+        let Expr::Lit(Lit::Str(path)) = &*first.expr else {
+            self.warn(format!("`{}` expects a file path string, ignored", method), first.expr.span());
+            return;
+        };
+        let mut source = ExternalSource { slot, kind, path: path.value.to_string(), wrap: [Wrap::default(); 2] };
+        if let Some(options) = call.args.get(1) {
+            let Expr::Object(object) = &*options.expr else {
+                self.warn(format!("`{}` options must be an object, ignored", method), options.expr.span());
+                return;
+            };
+            for prop in &object.props {
+                let PropOrSpread::Prop(prop) = prop else { continue };
+                let Prop::KeyValue(kv) = &**prop else {
+                    self.warn(format!("unsupported `{}` option ignored", method), prop.span());
+                    continue;
+                };
+                let key = match &kv.key {
+                    PropName::Ident(i) => i.sym.as_ref(),
+                    PropName::Str(s) => s.value.as_ref(),
+                    _ => "",
+                };
+                let axes = match key {
+                    "wrap" => 0..2,
+                    "wrapS" => 0..1,
+                    "wrapT" => 1..2,
+                    _ => {
+                        self.warn(format!("unsupported `{}` option `{}` ignored", method, key), kv.span());
+                        continue;
+                    }
+                };
+                let Expr::Lit(Lit::Str(value)) = &*kv.value else {
+                    self.warn(format!("`{}` expects a string", key), kv.value.span());
+                    continue;
+                };
+                if let Some(wrap) = self.wrap(value) {
+                    for axis in axes { source.wrap[axis] = wrap; }
+                }
+            }
+        }
+        self.external_sources.retain(|s| s.slot != slot);
+        self.external_sources.push(source);
+    }
+
+    fn wrap(&mut self, s: &Str) -> Option<Wrap> {
+        Some(match s.value.as_ref() {
+            "repeat" => Wrap::Repeat,
+            "clamp" => Wrap::Clamp,
+            "mirror" => Wrap::Mirror,
+            "border" => Wrap::Border,
+            other => {
+                self.warn(format!("unknown wrap mode `{}` ignored", other), s.span);
+                return None;
+            }
+        })
+    }
+
+    // Addressing of a sampled slot; slots without an init statement repeat
+    pub fn external_wrap(&self, slot: u32) -> [Wrap; 2] {
+        self.external_sources.iter().find(|s| s.slot == slot).map_or([Wrap::default(); 2], |s| s.wrap)
+    }
+
+    // Ring size of a pass's history texture; 0 when nothing reads its past frames
+    pub fn history_frames(&self, pass: u32) -> u32 {
+        self.pass_history.get(pass as usize).copied().unwrap_or(0)
+    }
+
+    // `s0`..`s3`
+    fn external_slot(name: &str) -> Option<u32> {
+        name.strip_prefix('s')?.parse().ok().filter(|slot| *slot < EXTERNAL_SLOTS)
+    }
+
+    fn tone_map(&mut self, s: &Str) -> Option<ToneMap> {
+        Some(match s.value.as_ref() {
+            "none" => ToneMap::None,
+            "aces" => ToneMap::Aces,
+            "reinhard" => ToneMap::Reinhard,
+            other => {
+                self.warn(format!("unknown tone map `{}` ignored", other), s.span);
+                return None;
+            }
+        })
+    }
+
+    fn lut(&mut self, array: &ArrayLit) -> Vec<[f32; 3]> {
+        let number = |e: &Expr| match e {
+            Expr::Lit(Lit::Num(n)) => Some(n.value as f32),
+            _ => None,
+        };
+        let mut lut = Vec::new();
+        for elem in array.elems.iter().flatten() {
+            let entry = match &*elem.expr {
+                Expr::Array(rgb) if rgb.elems.len() == 3 => {
+                    let c: Vec<f32> = rgb.elems.iter().flatten().filter_map(|e| number(&e.expr)).collect();
+                    (c.len() == 3).then(|| [c[0], c[1], c[2]])
+                }
+                e => number(e).map(|v| [v; 3]),
+            };
+            match entry {
+                Some(entry) => lut.push(entry),
+                None => self.warn("unsupported `lut` entry ignored".into(), elem.expr.span()),
+            }
+        }
+        lut
     }
 
     fn build_expr(&mut self, expr: &Expr) -> Option<NodeId> {
@@ -32,10 +250,64 @@ impl IRBuilder {
         self.build_expr(&member.obj)
     }
 
-    fn extract_f32_args(&self, call: &CallExpr) -> Vec<f32> {
-        call.args.iter().filter_map(|a| {
-            if let Expr::Lit(Lit::Num(n)) = &*a.expr { Some(n.value as f32) } else { None }
-        }).collect()
+    fn warn(&mut self, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic { message, span });
+    }
+
+    fn unknown_function(&mut self, message: String, span: Span) {
+        self.errors.push(CompileError::UnknownFunction(Diagnostic { message, span }));
+    }
+
+    // `time`, `bpm`, `mouse.x`, `resolution.y`, a custom global, or any of those wrapped as `() => x`
+    fn resolve_global(&self, expr: &Expr) -> Option<Global> {
+        match expr {
+            Expr::Ident(ident) => Some(match ident.sym.as_ref() {
+                "time" => Global::Time,
+                "frame" => Global::Frame,
+                "width" => Global::ResolutionX,
+                "height" => Global::ResolutionY,
+                "dt" => Global::Dt,
+                "fps" => Global::Fps,
+                "bpm" => Global::Bpm,
+                "beat" => Global::Beat,
+                name => Global::Custom(self.custom_globals.iter().position(|c| c == name)? as u32),
+            }),
+            Expr::Member(member) => {
+                let (Expr::Ident(obj), MemberProp::Ident(prop)) = (&*member.obj, &member.prop) else { return None };
+                Some(match (obj.sym.as_ref(), prop.sym.as_ref()) {
+                    ("mouse", "x") => Global::MouseX,
+                    ("mouse", "y") => Global::MouseY,
+                    ("mouse", "buttons") => Global::MouseButtons,
+                    ("resolution", "x") => Global::ResolutionX,
+                    ("resolution", "y") => Global::ResolutionY,
+                    _ => return None,
+                })
+            }
+            Expr::Arrow(arrow) if arrow.params.is_empty() => match &*arrow.body {
+                BlockStmtOrExpr::Expr(body) => self.resolve_global(body),
+                _ => None,
+            },
+            Expr::Paren(p) => self.resolve_global(&p.expr),
+            _ => None,
+        }
+    }
+
+    fn extract_arg(&self, expr: &Expr) -> Option<Arg> {
+        match expr {
+            Expr::Lit(Lit::Num(n)) => Some(Arg::Const(n.value as f32)),
+            Expr::Unary(u) if u.op == UnaryOp::Minus && let Expr::Lit(Lit::Num(n)) = &*u.arg => Some(Arg::Const(-n.value as f32)),
+            _ => self.resolve_global(expr).map(Arg::Global),
+        }
+    }
+
+    fn extract_args(&mut self, name: &str, call: &CallExpr) -> Vec<Arg> {
+        let mut args = Vec::new();
+        for a in &call.args {
+            if let Some(arg) = self.extract_arg(&a.expr) { args.push(arg); } else {
+                self.warn(format!("unsupported argument to `{}` ignored", name), a.expr.span());
+            }
+        }
+        args
     }
 
     fn classify_source(name: &str) -> Option<SourceType> {
@@ -63,6 +335,12 @@ impl IRBuilder {
             "repeatX" => SpatialType::RepeatX,
             "repeatY" => SpatialType::RepeatY,
             "pixelate" => SpatialType::Pixelate,
+            "polar" => SpatialType::Polar,
+            "cartesian" => SpatialType::Cartesian,
+            "mirror" => SpatialType::Mirror,
+            "tile" => SpatialType::Tile,
+            "skew" => SpatialType::Skew,
+            "transform" => SpatialType::Transform,
             _ => return None,
         })
     }
@@ -95,50 +373,147 @@ impl IRBuilder {
             "mask" => BinaryType::Mask,
             "modulate" => BinaryType::Modulate,
             "modulateScale" => BinaryType::ModulateScale,
+            "screen" => BinaryType::Screen,
+            "overlay" => BinaryType::Overlay,
+            "softLight" => BinaryType::SoftLight,
+            "hardLight" => BinaryType::HardLight,
+            "colorDodge" => BinaryType::ColorDodge,
+            "colorBurn" => BinaryType::ColorBurn,
+            "darken" => BinaryType::Darken,
+            "lighten" => BinaryType::Lighten,
+            "exclusion" => BinaryType::Exclusion,
+            "hue" => BinaryType::Hue,
+            "saturation" => BinaryType::Saturation,
+            "color" => BinaryType::Color,
+            "luminosity" => BinaryType::Luminosity,
             _ => return None,
         })
     }
 
+    // Neighbourhood effects, cut into passes by ir::split. `blur(radius = 4)` is a horizontal then a
+    // vertical Gaussian; `bloom(threshold = 0.6, strength = 1, radius = 8)` blurs the bright pass of
+    // its input and adds it back on top; `edges()` is a Sobel filter. `supersample(samples = 4)`
+    // anti-aliases its input by averaging it at jittered points inside the pixel, and stays inline.
+    fn build_filter(&mut self, name: &str, args: Vec<Arg>, child: NodeId, span: Span) -> NodeId {
+        let arg = |i: usize, default: f32| args.get(i).copied().unwrap_or(Arg::Const(default));
+        match name {
+            "edges" => self.push(IRKind::Filter { ty: FilterType::Edges, args, child }, span),
+            "supersample" => {
+                // The count unrolls the subtree, so it has to be a number
+                let samples = match arg(0, 4.0) {
+                    Arg::Const(v) => v.round().clamp(1.0, MAX_SUPERSAMPLE as f32),
+                    Arg::Global(_) => {
+                        self.warn("`supersample` needs a constant sample count, 4 used".into(), span);
+                        4.0
+                    }
+                };
+                self.push(IRKind::Filter { ty: FilterType::Supersample, args: vec![Arg::Const(samples)], child }, span)
+            }
+            "bloom" => {
+                let bright = self.push(IRKind::UnaryColor { ty: UnaryColorType::BrightPass, args: vec![arg(0, 0.6)], child }, span);
+                let blurred = self.build_filter("blur", vec![arg(2, 8.0)], bright, span);
+                self.push(IRKind::Binary { ty: BinaryType::Add, args: vec![arg(1, 1.0)], left: child, right: blurred }, span)
+            }
+            _ => {
+                let x = self.push(IRKind::Filter { ty: FilterType::BlurX, args: args.clone(), child }, span);
+                self.push(IRKind::Filter { ty: FilterType::BlurY, args, child: x }, span)
+            }
+        }
+    }
+
+    // Effects over past frames, backed by a pass history (see ir::split). `trails(decay = 0.9)`,
+    // `echo(frames = 4, spacing = 4)` and `motionBlur(samples = 4)`. Frame counts size the history
+    // ring, so they have to be numbers, and the ring may hold at most MAX_HISTORY frames.
+    fn build_temporal(&mut self, name: &str, args: Vec<Arg>, child: NodeId, span: Span) -> NodeId {
+        let (ty, defaults): (TemporalType, &[f32]) = match name {
+            "trails" => (TemporalType::Trails, &[0.9]),
+            "echo" => (TemporalType::Echo, &[4.0, 4.0]),
+            _ => (TemporalType::MotionBlur, &[4.0]),
+        };
+        let mut filled = Vec::new();
+        for (i, &default) in defaults.iter().enumerate() {
+            filled.push(match args.get(i).copied().unwrap_or(Arg::Const(default)) {
+                arg if matches!(ty, TemporalType::Trails) => arg,
+                Arg::Const(v) => Arg::Const(v.round().max(1.0)),
+                Arg::Global(_) => {
+                    self.warn(format!("`{}` needs a constant frame count, {} used", name, default), span);
+                    Arg::Const(default)
+                }
+            });
+        }
+        if ty.history(&filled) > MAX_HISTORY {
+            self.errors.push(CompileError::Unsupported(format!("`{}` reads more than {} past frames", name, MAX_HISTORY)));
+            // Keep the counts small for the passes that still run over the graph
+            filled.fill(Arg::Const(1.0));
+        }
+        self.push(IRKind::Temporal { ty, args: filled, child, history: None }, span)
+    }
+
     fn build_call(&mut self, call: &CallExpr) -> Option<NodeId> {
         if let Callee::Expr(callee_expr) = &call.callee {
             match &**callee_expr {
                 Expr::Ident(ident) => {
                     let name = ident.sym.as_ref();
+                    // `src(s0)` samples an external texture, `src(o1)` reads an output
+                    if name == "src"
+                        && let Some(first) = call.args.first()
+                        && let Expr::Ident(arg) = &*first.expr {
+                        let arg = arg.sym.as_ref();
+                        if let Some(slot) = Self::external_slot(arg) {
+                            return Some(self.push(IRKind::Source { ty: SourceType::External, args: vec![Arg::Const(slot as f32)] }, call.span));
+                        }
+                        if let Some(index) = arg.strip_prefix('o').and_then(|i| i.parse::<u32>().ok()) {
+                            return Some(self.push(IRKind::Source { ty: SourceType::Src, args: vec![Arg::Const(index as f32)] }, call.span));
+                        }
+                    }
                     if let Some(src_ty) = Self::classify_source(name) {
-                        let args = self.extract_f32_args(call);
-                        return Some(self.push(IRKind::Source { ty: src_ty, args }));
+                        let args = self.extract_args(name, call);
+                        return Some(self.push(IRKind::Source { ty: src_ty, args }, call.span));
                     }
+                    self.unknown_function(format!("unknown source `{}`", name), call.span);
                 }
                 Expr::Member(member) => {
                     if let MemberProp::Ident(mid) = &member.prop {
                         let method_name = mid.sym.as_ref();
                         let base_node = self.build_expr(&member.obj)?;
+                        // Span of just `.method(args)`, not the whole chain before it
+                        let span = Span::new(mid.span.lo, call.span.hi);
                         if method_name == "out" {
                             let mut index: u32 = 0;
-                            if let Some(first) = call.args.get(0) { if let Expr::Lit(Lit::Num(n)) = &*first.expr { index = n.value as u32; } }
-                            return Some(self.push(IRKind::Output { child: base_node, index }));
+                            if let Some(first) = call.args.first() && let Expr::Lit(Lit::Num(n)) = &*first.expr { index = n.value as u32; }
+                            return Some(self.push(IRKind::Output { child: base_node, index }, span));
                         }
                         if let Some(spatial_ty) = Self::classify_spatial(method_name) {
-                            let args = self.extract_f32_args(call);
-                            return Some(self.push(IRKind::Spatial { ty: spatial_ty, args, child: base_node }));
+                            let args = self.extract_args(method_name, call);
+                            return Some(self.push(IRKind::Spatial { ty: spatial_ty, args, child: base_node }, span));
                         }
-                        if let Some(bin_ty) = Self::classify_binary(method_name) {
-                            if let Some(first_arg) = call.args.get(0) {
-                                if let Expr::Call(other_call) = &*first_arg.expr {
-                                    let right = self.build_call(other_call)?;
-                                    let mut args_vec = Vec::new();
-                                    if call.args.len() > 1 {
-                                        if let Expr::Lit(Lit::Num(n)) = &*call.args[1].expr { args_vec.push(n.value as f32); }
-                                    }
-                                    return Some(self.push(IRKind::Binary { ty: bin_ty, args: args_vec, left: base_node, right }));
-                                }
+                        // `hue` and `color` blend only when given a source chain, otherwise they adjust colour
+                        let chain_arg = call.args.first().is_some_and(|a| matches!(&*a.expr, Expr::Call(_)));
+                        let bin_ty = Self::classify_binary(method_name)
+                            .filter(|ty| chain_arg || !matches!(ty, BinaryType::Hue | BinaryType::Color));
+                        if let Some(bin_ty) = bin_ty {
+                            if let Some(first_arg) = call.args.first() && let Expr::Call(other_call) = &*first_arg.expr {
+                                let right = self.build_call(other_call)?;
+                                let mut args_vec = Vec::new();
+                                if let Some(amount) = call.args.get(1) && let Some(arg) = self.extract_arg(&amount.expr) { args_vec.push(arg); }
+                                return Some(self.push(IRKind::Binary { ty: bin_ty, args: args_vec, left: base_node, right }, span));
                             }
+                            self.warn(format!("`{}` expects a source chain as its first argument, ignored", method_name), span);
                             return Some(base_node);
                         }
+                        if matches!(method_name, "blur" | "edges" | "bloom" | "supersample") {
+                            let args = self.extract_args(method_name, call);
+                            return Some(self.build_filter(method_name, args, base_node, span));
+                        }
+                        if matches!(method_name, "trails" | "echo" | "motionBlur") {
+                            let args = self.extract_args(method_name, call);
+                            return Some(self.build_temporal(method_name, args, base_node, span));
+                        }
                         if let Some(unary_ty) = Self::classify_unary_color(method_name) {
-                            let args = self.extract_f32_args(call);
-                            return Some(self.push(IRKind::UnaryColor { ty: unary_ty, args, child: base_node }));
+                            let args = self.extract_args(method_name, call);
+                            return Some(self.push(IRKind::UnaryColor { ty: unary_ty, args, child: base_node }, span));
                         }
+                        self.unknown_function(format!("unknown function `{}`", method_name), span);
                         return Some(base_node);
                     }
                 }
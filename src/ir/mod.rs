@@ -1,6 +1,10 @@
 pub mod node;
 pub mod builder;
+pub mod visit;
+pub mod opt;
+pub mod split;
 
 // Re-export IR types so that `use crate::ir::*;` works after moving IR into its own module/crate.
 pub use node::*;
 pub use builder::*;
+pub use visit::*;
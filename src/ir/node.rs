@@ -1,34 +1,198 @@
 // IR node/type definitions extracted from former backend::hydra_ir
 
-#[derive(Clone, Copy, Debug)]
+use swc_common::Span;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct NodeId(pub usize);
 
+// Src reads an output `o0..o3` (args[0] is the index); External samples a texture `s0..s3`
+// (args[0] is the slot, see crate::external); Pass samples an intermediate target (args[0] is the
+// pass index, see ir::split)
 #[derive(Debug, Clone, Copy)]
-pub enum SourceType { Osc, Noise, Solid, Gradient, Shape, Voronoi, Src }
+pub enum SourceType { Osc, Noise, Solid, Gradient, Shape, Voronoi, Src, External, Pass }
 
 #[derive(Debug, Clone, Copy)]
-pub enum SpatialType { Scale, Kaleid, Rotate, ScrollX, ScrollY, Scroll, Repeat, RepeatX, RepeatY, Pixelate }
+pub enum SpatialType {
+    Scale, Kaleid, Rotate, ScrollX, ScrollY, Scroll, Repeat, RepeatX, RepeatY, Pixelate,
+    // Polar maps st to (angle, radius) around the centre and Cartesian back
+    Polar, Cartesian, Mirror, Tile, Skew, Transform,
+}
 
 #[derive(Debug, Clone, Copy)]
 pub enum UnaryColorType {
     Invert, Color, Brightness, Contrast, Saturate, Posterize, Thresh, Hue,
     Colorama, Luma, Shift,
+    // Bloom's bright pass, `max(c - threshold, 0)`; no method of its own
+    BrightPass,
 }
 
+// `left` is the base and `right` the other chain. Colour ops combine rgb as in Hydra and cover
+// the union of both alphas, a + b - ab, before mixing with `left` by the amount. `mult` and
+// `mask` intersect instead (alpha times alpha, or times luma); `blend` crossfades all channels.
 #[derive(Debug, Clone, Copy)]
 pub enum BinaryType {
     Add, Sub, Mult, Blend, Diff, Layer, Mask,
     Modulate, ModulateScale,
+    // Photoshop-style blend modes
+    Screen, Overlay, SoftLight, HardLight, ColorDodge, ColorBurn, Darken, Lighten, Exclusion,
+    Hue, Saturation, Color, Luminosity,
+}
+
+// Effects that read neighbouring pixels of their child. The child is rendered to an intermediate
+// target first (see ir::split) and sampled at offsets measured in pixels.
+#[derive(Debug, Clone, Copy)]
+pub enum FilterType {
+    // One axis of a separable Gaussian blur
+    BlurX, BlurY,
+    // Sobel gradient magnitude of the luma
+    Edges,
+    // Mean of the child at jittered points inside the pixel; evaluated inline, never a pass
+    Supersample,
+}
+
+// Most samples per pixel for supersample() and CompileOptions::supersample; each one evaluates the
+// whole subtree again
+pub const MAX_SUPERSAMPLE: u32 = 16;
+
+// Effects that combine their child with earlier frames. The runtime keeps a ring of past frames
+// of a pass (see crate::passes); ir::split decides which pass and how many frames.
+#[derive(Debug, Clone, Copy)]
+pub enum TemporalType {
+    // max(child, decay * last frame of this node), so the node's own output is the pass
+    Trails,
+    // The child and `frames - 1` fading copies of it, `spacing` frames apart
+    Echo,
+    // Mean of the child over the last `samples` frames
+    MotionBlur,
+}
+
+impl TemporalType {
+    // Past frames the node reads; the builder makes these arguments constant
+    pub fn history(&self, args: &[Arg]) -> u32 {
+        let arg = |i: usize, default: f32| args.get(i).and_then(Arg::as_const).unwrap_or(default) as u32;
+        match self {
+            TemporalType::Trails => 1,
+            TemporalType::Echo => arg(0, 4.0).saturating_sub(1).saturating_mul(arg(1, 4.0)),
+            TemporalType::MotionBlur => arg(0, 4.0).saturating_sub(1),
+        }
+    }
+}
+
+// Per-frame value from the Globals uniform block (see crate::globals)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Global {
+    Time, Frame, ResolutionX, ResolutionY, MouseX, MouseY, MouseButtons,
+    Dt, Fps, Bpm, Beat,
+    // Index into CompileOptions::custom_globals
+    Custom(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arg {
+    Const(f32),
+    Global(Global),
+}
+
+impl Arg {
+    // Value known at compile time, None for globals
+    pub fn as_const(&self) -> Option<f32> {
+        match self { Arg::Const(v) => Some(*v), Arg::Global(_) => None }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum IRKind {
-    Source { ty: SourceType, args: Vec<f32> },
-    Spatial { ty: SpatialType, args: Vec<f32>, child: NodeId },
-    UnaryColor { ty: UnaryColorType, args: Vec<f32>, child: NodeId },
-    Binary { ty: BinaryType, args: Vec<f32>, left: NodeId, right: NodeId },
+    Source { ty: SourceType, args: Vec<Arg> },
+    Spatial { ty: SpatialType, args: Vec<Arg>, child: NodeId },
+    UnaryColor { ty: UnaryColorType, args: Vec<Arg>, child: NodeId },
+    Binary { ty: BinaryType, args: Vec<Arg>, left: NodeId, right: NodeId },
+    Filter { ty: FilterType, args: Vec<Arg>, child: NodeId },
+    // `history` is the pass whose past frames are read, None until ir::split
+    Temporal { ty: TemporalType, args: Vec<Arg>, child: NodeId, history: Option<u32> },
     Output { child: NodeId, index: u32 },
 }
 
+// Hydra method names, used for debug names in emitted shaders
+impl SourceType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SourceType::Osc => "osc", SourceType::Noise => "noise", SourceType::Solid => "solid",
+            SourceType::Gradient => "gradient", SourceType::Shape => "shape", SourceType::Voronoi => "voronoi",
+            SourceType::Src | SourceType::External => "src", SourceType::Pass => "pass",
+        }
+    }
+}
+
+impl SpatialType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpatialType::Scale => "scale", SpatialType::Kaleid => "kaleid", SpatialType::Rotate => "rotate",
+            SpatialType::ScrollX => "scrollX", SpatialType::ScrollY => "scrollY", SpatialType::Scroll => "scroll",
+            SpatialType::Repeat => "repeat", SpatialType::RepeatX => "repeatX", SpatialType::RepeatY => "repeatY",
+            SpatialType::Pixelate => "pixelate", SpatialType::Polar => "polar", SpatialType::Cartesian => "cartesian",
+            SpatialType::Mirror => "mirror", SpatialType::Tile => "tile", SpatialType::Skew => "skew",
+            SpatialType::Transform => "transform",
+        }
+    }
+}
+
+impl UnaryColorType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            UnaryColorType::Invert => "invert", UnaryColorType::Color => "color", UnaryColorType::Brightness => "brightness",
+            UnaryColorType::Contrast => "contrast", UnaryColorType::Saturate => "saturate", UnaryColorType::Posterize => "posterize",
+            UnaryColorType::Thresh => "thresh", UnaryColorType::Hue => "hue", UnaryColorType::Colorama => "colorama",
+            UnaryColorType::Luma => "luma", UnaryColorType::Shift => "shift", UnaryColorType::BrightPass => "bloom",
+        }
+    }
+}
+
+impl BinaryType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BinaryType::Add => "add", BinaryType::Sub => "sub", BinaryType::Mult => "mult", BinaryType::Blend => "blend",
+            BinaryType::Diff => "diff", BinaryType::Layer => "layer", BinaryType::Mask => "mask",
+            BinaryType::Modulate => "modulate", BinaryType::ModulateScale => "modulateScale",
+            BinaryType::Screen => "screen", BinaryType::Overlay => "overlay", BinaryType::SoftLight => "softLight",
+            BinaryType::HardLight => "hardLight", BinaryType::ColorDodge => "colorDodge", BinaryType::ColorBurn => "colorBurn",
+            BinaryType::Darken => "darken", BinaryType::Lighten => "lighten", BinaryType::Exclusion => "exclusion",
+            BinaryType::Hue => "hue", BinaryType::Saturation => "saturation", BinaryType::Color => "color",
+            BinaryType::Luminosity => "luminosity",
+        }
+    }
+}
+
+impl FilterType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FilterType::BlurX | FilterType::BlurY => "blur", FilterType::Edges => "edges",
+            FilterType::Supersample => "supersample",
+        }
+    }
+}
+
+impl TemporalType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TemporalType::Trails => "trails", TemporalType::Echo => "echo", TemporalType::MotionBlur => "motionBlur",
+        }
+    }
+}
+
+impl IRKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            IRKind::Source { ty, .. } => ty.name(),
+            IRKind::Spatial { ty, .. } => ty.name(),
+            IRKind::UnaryColor { ty, .. } => ty.name(),
+            IRKind::Binary { ty, .. } => ty.name(),
+            IRKind::Filter { ty, .. } => ty.name(),
+            IRKind::Temporal { ty, .. } => ty.name(),
+            IRKind::Output { .. } => "out",
+        }
+    }
+}
+
+// `span` is the swc span of the call that produced the node (DUMMY_SP for synthesized nodes)
 #[derive(Debug, Clone)]
-pub struct IRNode { pub kind: IRKind }
+pub struct IRNode { pub kind: IRKind, pub span: Span }
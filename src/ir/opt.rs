@@ -0,0 +1,61 @@
+// Optimisation passes over the IR (see ir::visit)
+
+use super::builder::IRBuilder;
+use super::node::*;
+use super::visit::Rewriter;
+
+// Drops nodes whose arguments make them a no-op, e.g. `.scale(1)`, `.brightness(1)`, `.add(x, 0)`.
+// Defaults match the emitters in backend::hydra_effects and spirv_visitor.
+pub struct FoldIdentity;
+
+// None when the argument is a global, which never folds
+fn arg(args: &[Arg], i: usize, default: f32) -> Option<f32> {
+    args.get(i).map_or(Some(default), Arg::as_const)
+}
+
+impl Rewriter for FoldIdentity {
+    fn rewrite_post(&mut self, ir: &mut IRBuilder, id: NodeId) -> Option<NodeId> {
+        match &ir.node(id).kind {
+            IRKind::Spatial { ty, args, child } => {
+                let identity = match ty {
+                    SpatialType::Scale => arg(args, 0, 1.0) == Some(1.0) && arg(args, 1, 1.0) == Some(1.0),
+                    SpatialType::Rotate | SpatialType::Skew => arg(args, 0, 0.0) == Some(0.0) && arg(args, 1, 0.0) == Some(0.0),
+                    SpatialType::Transform => [1.0, 0.0, 0.0, 1.0, 0.0, 0.0].iter().enumerate().all(|(i, &d)| arg(args, i, d) == Some(d)),
+                    _ => false,
+                };
+                identity.then_some(*child)
+            }
+            IRKind::UnaryColor { ty, args, child } => {
+                let identity = match ty {
+                    UnaryColorType::Brightness | UnaryColorType::Contrast | UnaryColorType::Saturate => arg(args, 0, 1.0) == Some(1.0),
+                    UnaryColorType::Hue => arg(args, 0, 0.4) == Some(0.0),
+                    UnaryColorType::Color => (0..4).all(|i| arg(args, i, 1.0) == Some(1.0)),
+                    _ => false,
+                };
+                identity.then_some(*child)
+            }
+            IRKind::Binary { ty, args, left, .. } => {
+                // binary_mix(a, b, 0) == a
+                let mixes = !matches!(ty, BinaryType::Diff | BinaryType::Layer | BinaryType::Mask | BinaryType::Modulate | BinaryType::ModulateScale);
+                (mixes && args.first() == Some(&Arg::Const(0.0))).then_some(*left)
+            }
+            // A zero radius blurs nothing, and saves two passes
+            IRKind::Filter { ty: FilterType::BlurX | FilterType::BlurY, args, child } => {
+                (arg(args, 0, 4.0) == Some(0.0)).then_some(*child)
+            }
+            // One sample is the child itself
+            IRKind::Filter { ty: FilterType::Supersample, args, child } => {
+                (arg(args, 0, 4.0) == Some(1.0)).then_some(*child)
+            }
+            // No past frames to read: `trails(0)`, `echo(1)`, `motionBlur(1)`
+            IRKind::Temporal { ty, args, child, .. } => {
+                let still = match ty {
+                    TemporalType::Trails => arg(args, 0, 0.9) == Some(0.0),
+                    _ => ty.history(args) == 0,
+                };
+                still.then_some(*child)
+            }
+            _ => None,
+        }
+    }
+}
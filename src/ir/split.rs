@@ -0,0 +1,88 @@
+// Pass splitter: cuts the graph below every Filter node. The filter's input becomes a pass of its
+// own, rendered to an intermediate target (see crate::passes), and the filter reads it back
+// through a `Source { Pass }` node, so each tap is a texture read instead of the whole subtree.
+// Supersample is the exception: its taps are inside one pixel, finer than a pass can store.
+//
+// Temporal nodes read past frames of a pass, so their input has to be a pass too. Echo and
+// motionBlur cut their child like a filter; trails feeds back its own output, so the trails node
+// itself becomes the pass and its parent reads that. IRBuilder::pass_history records how many
+// frames of each pass the host keeps.
+
+use std::collections::{HashMap, HashSet};
+use super::builder::IRBuilder;
+use super::node::*;
+use super::visit::{rewrite, Rewriter};
+
+pub struct Passes {
+    // Roots of the intermediate passes in render order; pass i draws into target i
+    pub passes: Vec<NodeId>,
+    // What is left for the main shader
+    pub root: NodeId,
+}
+
+#[derive(Default)]
+struct Splitter {
+    passes: Vec<NodeId>,
+    // A subtree read by several filters (e.g. bloom's input) is rendered once
+    targets: HashMap<NodeId, u32>,
+    // Shared subtrees are visited once per parent
+    done: HashSet<NodeId>,
+}
+
+impl Splitter {
+    // Pass index of the subtree under `id`, adding a pass on first use
+    fn target(&mut self, ir: &mut IRBuilder, id: NodeId) -> u32 {
+        *self.targets.entry(id).or_insert_with(|| {
+            self.passes.push(id);
+            ir.pass_history.push(0);
+            (self.passes.len() - 1) as u32
+        })
+    }
+
+    // Points the temporal node `id` at the history of pass `index`, growing the ring to fit
+    fn keep_history(ir: &mut IRBuilder, id: NodeId, index: u32) {
+        let IRKind::Temporal { ty, args, history, .. } = &mut ir.node_mut(id).kind else { return };
+        *history = Some(index);
+        let frames = ty.history(args);
+        let ring = &mut ir.pass_history[index as usize];
+        *ring = (*ring).max(frames);
+    }
+}
+
+// `Source { Pass }` reading pass `index`, with the span of the node it stands in for
+fn pass_source(ir: &mut IRBuilder, index: u32, span_of: NodeId) -> NodeId {
+    let span = ir.node(span_of).span;
+    let source = ir.add_node(IRKind::Source { ty: SourceType::Pass, args: vec![Arg::Const(index as f32)] });
+    ir.node_mut(source).span = span;
+    source
+}
+
+impl Rewriter for Splitter {
+    // Post-order, so filters nested in the input have already been cut and come earlier
+    fn rewrite_post(&mut self, ir: &mut IRBuilder, id: NodeId) -> Option<NodeId> {
+        match ir.node(id).kind {
+            IRKind::Temporal { ty: TemporalType::Trails, .. } => {
+                let index = self.target(ir, id);
+                Self::keep_history(ir, id, index);
+                Some(pass_source(ir, index, id))
+            }
+            IRKind::Filter { ty: FilterType::Supersample, .. } => None,
+            IRKind::Filter { child, .. } | IRKind::Temporal { child, .. } => {
+                if !self.done.insert(id) { return None; }
+                let index = self.target(ir, child);
+                Self::keep_history(ir, id, index);
+                let source = pass_source(ir, index, id);
+                ir.set_child(id, 0, source);
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn split_passes(ir: &mut IRBuilder, root: NodeId) -> Passes {
+    let mut splitter = Splitter::default();
+    ir.pass_history.clear();
+    let root = rewrite(&mut splitter, ir, root);
+    Passes { passes: splitter.passes, root }
+}
@@ -0,0 +1,208 @@
+// IR traversal: read-only visitor and mutable rewriter over IRKind
+
+use super::builder::IRBuilder;
+use super::node::*;
+
+impl IRKind {
+    pub fn children(&self) -> Vec<NodeId> {
+        match self {
+            IRKind::Source { .. } => vec![],
+            IRKind::Spatial { child, .. }
+            | IRKind::UnaryColor { child, .. }
+            | IRKind::Filter { child, .. }
+            | IRKind::Temporal { child, .. }
+            | IRKind::Output { child, .. } => vec![*child],
+            IRKind::Binary { left, right, .. } => vec![*left, *right],
+        }
+    }
+
+    pub fn children_mut(&mut self) -> Vec<&mut NodeId> {
+        match self {
+            IRKind::Source { .. } => vec![],
+            IRKind::Spatial { child, .. }
+            | IRKind::UnaryColor { child, .. }
+            | IRKind::Filter { child, .. }
+            | IRKind::Temporal { child, .. }
+            | IRKind::Output { child, .. } => vec![child],
+            IRKind::Binary { left, right, .. } => vec![left, right],
+        }
+    }
+}
+
+// Read-only traversal. `enter` runs pre-order, `leave` post-order.
+//
+// The IR is a DAG: a node shared by several parents (e.g. bloom's input, which feeds both the
+// bright pass and the final add) is walked once per parent, so its hooks run more than once.
+// Visitors that must see each node once keep a set of visited NodeIds.
+pub trait Visitor {
+    // Return false to skip the children of `id` (`leave` is still called).
+    fn enter(&mut self, _ir: &IRBuilder, _id: NodeId) -> bool { true }
+    fn leave(&mut self, _ir: &IRBuilder, _id: NodeId) {}
+}
+
+pub fn walk<V: Visitor + ?Sized>(visitor: &mut V, ir: &IRBuilder, id: NodeId) {
+    if visitor.enter(ir, id) {
+        for child in ir.node(id).kind.children() {
+            walk(visitor, ir, child);
+        }
+    }
+    visitor.leave(ir, id);
+}
+
+// Mutable traversal. Each hook may return a replacement node id; new nodes are
+// created with `IRBuilder::add_node`, which is how wrappers get inserted.
+//
+// As with Visitor, a shared node is rewritten once per parent. The second time its children have
+// already been relinked, so a rewriter that edits nodes in place has to be idempotent or track
+// the NodeIds it has done (see ir::split).
+pub trait Rewriter {
+    // Pre-order. A replacement returned here is not descended into.
+    fn rewrite_pre(&mut self, _ir: &mut IRBuilder, _id: NodeId) -> Option<NodeId> { None }
+    // Post-order, after the children of `id` have been rewritten and relinked.
+    fn rewrite_post(&mut self, _ir: &mut IRBuilder, _id: NodeId) -> Option<NodeId> { None }
+}
+
+// Returns the id that now stands in for `id` (the new root when called on the root).
+pub fn rewrite<R: Rewriter + ?Sized>(rewriter: &mut R, ir: &mut IRBuilder, id: NodeId) -> NodeId {
+    if let Some(replacement) = rewriter.rewrite_pre(ir, id) {
+        return replacement;
+    }
+    for (slot, child) in ir.node(id).kind.children().into_iter().enumerate() {
+        let new_child = rewrite(rewriter, ir, child);
+        if new_child != child {
+            ir.set_child(id, slot, new_child);
+        }
+    }
+    rewriter.rewrite_post(ir, id).unwrap_or(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn osc(ir: &mut IRBuilder) -> NodeId {
+        ir.add_node(IRKind::Source { ty: SourceType::Osc, args: vec![] })
+    }
+
+    fn invert(ir: &mut IRBuilder, child: NodeId) -> NodeId {
+        ir.add_node(IRKind::UnaryColor { ty: UnaryColorType::Invert, args: vec![], child })
+    }
+
+    fn add(ir: &mut IRBuilder, left: NodeId, right: NodeId) -> NodeId {
+        ir.add_node(IRKind::Binary { ty: BinaryType::Add, args: vec![], left, right })
+    }
+
+    #[derive(Default)]
+    struct Trace {
+        events: Vec<(&'static str, usize)>,
+        skip: Option<NodeId>,
+    }
+
+    impl Visitor for Trace {
+        fn enter(&mut self, _ir: &IRBuilder, id: NodeId) -> bool {
+            self.events.push(("enter", id.0));
+            self.skip != Some(id)
+        }
+
+        fn leave(&mut self, _ir: &IRBuilder, id: NodeId) { self.events.push(("leave", id.0)); }
+    }
+
+    #[test]
+    fn walk_visits_children_in_order() {
+        // add(invert(osc), osc)
+        let mut ir = IRBuilder::new();
+        let a = osc(&mut ir);
+        let inv = invert(&mut ir, a);
+        let b = osc(&mut ir);
+        let root = add(&mut ir, inv, b);
+
+        let mut trace = Trace::default();
+        walk(&mut trace, &ir, root);
+        assert_eq!(trace.events, vec![
+            ("enter", 3), ("enter", 1), ("enter", 0), ("leave", 0), ("leave", 1),
+            ("enter", 2), ("leave", 2), ("leave", 3),
+        ]);
+    }
+
+    #[test]
+    fn walk_skips_children_when_enter_returns_false() {
+        let mut ir = IRBuilder::new();
+        let a = osc(&mut ir);
+        let root = invert(&mut ir, a);
+
+        let mut trace = Trace { skip: Some(root), ..Default::default() };
+        walk(&mut trace, &ir, root);
+        assert_eq!(trace.events, vec![("enter", 1), ("leave", 1)]);
+    }
+
+    #[test]
+    fn walk_visits_shared_node_once_per_parent() {
+        // add(osc, invert(osc)) with one osc node
+        let mut ir = IRBuilder::new();
+        let a = osc(&mut ir);
+        let inv = invert(&mut ir, a);
+        let root = add(&mut ir, a, inv);
+
+        let mut trace = Trace::default();
+        walk(&mut trace, &ir, root);
+        assert_eq!(trace.events.iter().filter(|&&e| e == ("enter", a.0)).count(), 2);
+    }
+
+    // Replaces every invert node with its child
+    struct DropInvert;
+
+    impl Rewriter for DropInvert {
+        fn rewrite_post(&mut self, ir: &mut IRBuilder, id: NodeId) -> Option<NodeId> {
+            match ir.node(id).kind {
+                IRKind::UnaryColor { ty: UnaryColorType::Invert, child, .. } => Some(child),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn rewrite_relinks_replaced_children() {
+        let mut ir = IRBuilder::new();
+        let a = osc(&mut ir);
+        let inv = invert(&mut ir, a);
+        let b = osc(&mut ir);
+        let root = add(&mut ir, b, inv);
+
+        assert_eq!(rewrite(&mut DropInvert, &mut ir, root), root);
+        assert_eq!(ir.node(root).kind.children(), vec![b, a]);
+    }
+
+    #[test]
+    fn rewrite_returns_replacement_root() {
+        let mut ir = IRBuilder::new();
+        let a = osc(&mut ir);
+        let root = invert(&mut ir, a);
+
+        assert_eq!(rewrite(&mut DropInvert, &mut ir, root), a);
+    }
+
+    // Wraps every source in an invert node
+    struct WrapSources;
+
+    impl Rewriter for WrapSources {
+        fn rewrite_pre(&mut self, ir: &mut IRBuilder, id: NodeId) -> Option<NodeId> {
+            matches!(ir.node(id).kind, IRKind::Source { .. }).then(|| invert(ir, id))
+        }
+    }
+
+    #[test]
+    fn rewrite_inserts_nodes() {
+        let mut ir = IRBuilder::new();
+        let a = osc(&mut ir);
+        let b = osc(&mut ir);
+        let root = add(&mut ir, a, b);
+
+        assert_eq!(rewrite(&mut WrapSources, &mut ir, root), root);
+        let children = ir.node(root).kind.children();
+        assert_eq!(ir.nodes.len(), 5);
+        for (child, source) in children.into_iter().zip([a, b]) {
+            assert!(matches!(ir.node(child).kind, IRKind::UnaryColor { ty: UnaryColorType::Invert, .. }));
+            assert_eq!(ir.node(child).kind.children(), vec![source]);
+        }
+    }
+}
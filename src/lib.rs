@@ -1,3 +1,10 @@
+pub mod compiler;
+pub mod error;
 pub mod frontend;
+pub mod globals;
+pub mod external;
+pub mod passes;
+pub mod post_process;
+pub mod output;
 pub mod backend;
 pub mod ir;
@@ -1,20 +1,34 @@
+use std::env;
 use std::fs;
 
-use live_lang::backend::spirv_glsl::compile_to_glsl;
-use live_lang::backend::spirv_visitor::SpirvEmitter;
-use live_lang::frontend::hydra_ecma::*;
+use live_lang::compiler::{CompileOptions, Compiler, Target};
 
 fn main() {
+    // Output language: glsl (default), glsl-direct, glsl-es, hlsl, msl or wgsl
+    let target = match env::args().nth(1).as_deref() {
+        Some("glsl-direct") => Target::GlslDirect,
+        Some("glsl-es") => Target::GlslEs,
+        Some("hlsl") => Target::Hlsl,
+        Some("msl") => Target::Msl,
+        Some("wgsl") => Target::Wgsl,
+        _ => Target::Glsl,
+    };
+
     // Read the hydra.js file
     let source = fs::read_to_string("examples/hydra/color.js")
         .expect("Failed to read color.js");
 
-    let ast = hydra_ecma(&source);
-
-    let emitter = SpirvEmitter::new();
-    let spirv_words = emitter.emit_pipeline(&ast);
+    let options = CompileOptions { target, ..Default::default() };
+    let shader = Compiler::new(options).compile(&source).expect("failed to compile color.js");
 
-    let glsl = compile_to_glsl(&spirv_words).unwrap();
+    for d in &shader.diagnostics {
+        let (line, col) = d.line_col(&source);
+        eprintln!("warning: {} at {}:{}", d, line, col);
+    }
 
-    println!("{}", glsl.to_string());
+    // Multi-pass sketches (blur, edges, bloom) print their intermediate passes first
+    for (i, pass) in shader.passes.iter().enumerate() {
+        println!("// pass{}\n{}", i, pass.source.as_deref().unwrap_or_default());
+    }
+    println!("{}", shader.source.unwrap_or_default());
 }
@@ -0,0 +1,45 @@
+// How the final colour is written to the output target, after post-processing and the clamp.
+// Passes (see crate::passes) always store straight alpha in the working colour space.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum AlphaMode {
+    // Colour and alpha as computed, alpha unclamped; fine for an opaque surface
+    #[default]
+    Straight,
+    // Alpha clamped to 0..1 and multiplied into the colour, for compositing over other content
+    // (e.g. a surface with wgpu::CompositeAlphaMode::PreMultiplied)
+    Premultiplied,
+}
+
+// How colour values are encoded. Hydra works on sRGB values, as 8-bit images store them, and
+// writes them to the canvas unchanged.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ColorSpace {
+    // Gamma-encoded with the sRGB transfer function
+    #[default]
+    Srgb,
+    // Linear light
+    Linear,
+}
+
+// Dynamic range of the main shader's output. Passes always keep values above 1.0 (they draw into
+// Rgba16Float targets); this only decides how the final colour reaches the display.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputTransform {
+    // Tone mapped, clamped to 0..1 and encoded for CompileOptions::output_space
+    #[default]
+    Sdr,
+    // Linear Rec.709 where 1.0 is 80 nits and values above 1.0 are kept, for an Rgba16Float
+    // extended-range surface. `paper_white` is the brightness of working-space 1.0 in nits.
+    ScRgb { paper_white: f32 },
+    // SMPTE ST 2084 over Rec.2020 primaries, 0..1 for 0..10000 nits, for HDR10 outputs
+    // (e.g. Rgb10a2Unorm); `paper_white` as for ScRgb
+    Pq { paper_white: f32 },
+}
+
+// ITU-R BT.2408 reference white in nits, a sensible paper_white
+pub const REFERENCE_WHITE: f32 = 203.0;
+
+impl OutputTransform {
+    pub fn is_hdr(self) -> bool { !matches!(self, OutputTransform::Sdr) }
+}
@@ -0,0 +1,36 @@
+// Intermediate render targets `pass0`, `pass1`, ... for effects that read neighbouring pixels
+// (`blur`, `edges`, `bloom`, see ir::split). The host draws CompiledShader::passes in order, each
+// into its own target the size of the output, before the main shader. Later passes read target i
+// as a texture with a clamp-to-edge sampler. Targets should be float (e.g. rgba16float): passes
+// are neither post-processed nor clamped, so bright values survive for bloom.
+//
+// Temporal effects (`trails`, `echo`, `motionBlur`) read past frames of a pass. For every pass with
+// CompiledPass::history > 0 the host keeps a ring of that many frames in a 2D array texture, bound
+// as `pass<i>_history`. After drawing frame `Globals::frame`, it copies target i into layer
+// `frame % history`; the shaders read k frames ago from layer `(frame - k) % history`. The frame
+// count wraps at globals::FRAME_WRAP, a multiple of every ring length, so the layers stay in step.
+
+use crate::external::EXTERNAL_SLOTS;
+
+pub const MAX_PASSES: usize = 8;
+
+// Frames of history per pass, e.g. `echo(5, 4)` needs 16
+pub const MAX_HISTORY: u32 = 16;
+
+// After the external sources at set 0: pass0 at bindings 9 and 10, pass1 at 11 and 12, ...
+pub fn texture_binding(index: u32) -> u32 { 1 + 2 * (EXTERNAL_SLOTS + index) }
+
+pub fn sampler_binding(index: u32) -> u32 { 2 + 2 * (EXTERNAL_SLOTS + index) }
+
+pub fn texture_name(index: u32) -> String { format!("pass{}", index) }
+
+pub fn sampler_name(index: u32) -> String { format!("pass{}_sampler", index) }
+
+// After every pass target: pass0_history at bindings 25 and 26, ...
+pub fn history_texture_binding(index: u32) -> u32 { 1 + 2 * (EXTERNAL_SLOTS + MAX_PASSES as u32 + index) }
+
+pub fn history_sampler_binding(index: u32) -> u32 { 2 + 2 * (EXTERNAL_SLOTS + MAX_PASSES as u32 + index) }
+
+pub fn history_texture_name(index: u32) -> String { format!("pass{}_history", index) }
+
+pub fn history_sampler_name(index: u32) -> String { format!("pass{}_history_sampler", index) }
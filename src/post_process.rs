@@ -0,0 +1,40 @@
+// Stages applied to the final colour before it is clamped and stored, in field order:
+// auto exposure, tone mapping, gamma, then the LUT. Chosen per compile (CompileOptions::post_process)
+// or per sketch with a `postProcess(...)` statement (see IRBuilder::build_post_process).
+
+// Upper bound on LUT entries; the curve is unrolled into the shader
+pub const MAX_LUT_SIZE: usize = 64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ToneMap {
+    None,
+    // Filmic approximation, x(2.51x + 0.03) / (x(2.43x + 0.59) + 0.14)
+    #[default]
+    Aces,
+    // x / (1 + x)
+    Reinhard,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostProcess {
+    // Luma-based gain 1 / (luma + 0.02), capped at this value; None leaves exposure alone
+    pub auto_exposure: Option<f32>,
+    pub tone_map: ToneMap,
+    // Output is raised to 1 / gamma; 1.0 is a no-op
+    pub gamma: f32,
+    // Per-channel curve sampled evenly over 0..1 with linear interpolation; empty for none
+    pub lut: Vec<[f32; 3]>,
+}
+
+impl Default for PostProcess {
+    fn default() -> Self {
+        Self { auto_exposure: Some(6.0), tone_map: ToneMap::Aces, gamma: 1.0, lut: Vec::new() }
+    }
+}
+
+impl PostProcess {
+    // Colour goes straight to the clamp, as in Hydra
+    pub fn none() -> Self {
+        Self { auto_exposure: None, tone_map: ToneMap::None, gamma: 1.0, lut: Vec::new() }
+    }
+}
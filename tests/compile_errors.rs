@@ -0,0 +1,69 @@
+// A bad sketch has to come back as a CompileError, never as a panic in the host
+
+use live_lang::compiler::{CompileOptions, Compiler};
+use live_lang::error::CompileError;
+
+fn compile(source: &str) -> Result<(), CompileError> {
+    Compiler::default().compile(source).map(|_| ())
+}
+
+#[test]
+fn empty_program() {
+    for source in ["", "// nothing to draw", "postProcess(\"aces\")"] {
+        assert!(matches!(compile(source), Err(CompileError::EmptyProgram)), "{:?}", source);
+    }
+}
+
+#[test]
+fn parse_error_points_at_the_bad_token() {
+    let source = "osc(10).out()\nnoise(3).out(]\n";
+    let Err(CompileError::Parse(d)) = compile(source) else { panic!("expected a parse error") };
+    assert_eq!(d.line_col(source), (2, 14));
+}
+
+#[test]
+fn unknown_method() {
+    let source = "osc(10)\n  .wobble(2)\n  .out()";
+    let Err(CompileError::UnknownFunction(d)) = compile(source) else { panic!("expected an unknown function error") };
+    assert!(d.message.contains("wobble"), "{}", d);
+    assert_eq!(d.line_col(source), (2, 4));
+}
+
+#[test]
+fn unknown_source() {
+    let source = "wobble(2).out()";
+    let Err(CompileError::UnknownFunction(d)) = compile(source) else { panic!("expected an unknown function error") };
+    assert_eq!(d.line_col(source), (1, 1));
+}
+
+#[test]
+fn out_of_range_arguments() {
+    for source in [
+        "postProcess({ gamma: 0 })\nosc().out()",
+        "postProcess({ lut: [0.5] })\nosc().out()",
+        // 19 copies 4 frames apart, past passes::MAX_HISTORY
+        "osc().echo(20).out()",
+    ] {
+        assert!(matches!(compile(source), Err(CompileError::Unsupported(_))), "{:?}", source);
+    }
+}
+
+#[test]
+fn out_of_range_options() {
+    let compiler = Compiler::new(CompileOptions { supersample: 64, ..Default::default() });
+    assert!(matches!(compiler.compile("osc().out()"), Err(CompileError::Unsupported(_))));
+}
+
+#[test]
+fn oversized_history() {
+    for source in [
+        "osc().echo(100000, 100000).out()",
+        "osc().echo(4294967296, 2).out()",
+        "osc().motionBlur(100000).out()",
+        "osc().echo(6, 4).trails(0.8).out()",
+    ] {
+        assert!(matches!(compile(source), Err(CompileError::Unsupported(_))), "{:?}", source);
+    }
+    // 16 frames is the limit itself
+    assert!(compile("osc().echo(5, 4).out()").is_ok());
+}
@@ -0,0 +1,109 @@
+use std::fs;
+use std::path::Path;
+
+#[path = "../examples/render/mod.rs"]
+mod render;
+
+use live_lang::backend::spirv_context::Aspect;
+use live_lang::backend::spirv_reflect::ShaderInterface;
+use live_lang::compiler::{CompileOptions, Compiler, Target};
+use live_lang::output::{ColorSpace, OutputTransform, REFERENCE_WHITE};
+use crate::render::headless::Headless;
+use crate::render::media::load_sources;
+use crate::render::passes::{spirv_stages, Stage};
+
+// Renders every sketch in examples/hydra through both backends (SPIR-V and direct GLSL) and
+// compares the pixels. Needs a wgpu adapter; a software one (llvmpipe, lavapipe) is enough.
+
+const SKETCH_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/hydra");
+// Not square, so the aspect modes differ
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 96;
+// Every aspect mode in sRGB, then linear working space once (textures decoded, output encoded)
+// and PQ output once (it fits the 8-bit target, unlike scRGB)
+const SDR: OutputTransform = OutputTransform::Sdr;
+const CONFIGS: [(Aspect, ColorSpace, OutputTransform); 5] = [
+    (Aspect::Stretch, ColorSpace::Srgb, SDR),
+    (Aspect::Corrected, ColorSpace::Srgb, SDR),
+    (Aspect::Square, ColorSpace::Srgb, SDR),
+    (Aspect::Stretch, ColorSpace::Linear, SDR),
+    (Aspect::Stretch, ColorSpace::Srgb, OutputTransform::Pq { paper_white: REFERENCE_WHITE }),
+];
+const TIMES: [f32; 3] = [0.0, 1.5, 7.25];
+// Max per-channel difference (out of 255) before a pixel counts as different;
+// the two paths only differ in float evaluation order
+const CHANNEL_TOLERANCE: u8 = 2;
+// Fraction of pixels allowed over tolerance (hash noise can flip at cell edges)
+const PIXEL_TOLERANCE: f32 = 0.001;
+
+#[test]
+fn backends_render_the_same() {
+    let _ = env_logger::try_init();
+
+    let mut sketches: Vec<String> = fs::read_dir(SKETCH_DIR)
+        .expect("failed to read sketch directory")
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "js"))
+        .map(|p| p.display().to_string())
+        .collect();
+    sketches.sort();
+    assert!(!sketches.is_empty(), "no sketches in {}", SKETCH_DIR);
+
+    let headless = pollster::block_on(Headless::new())
+        .expect("no wgpu adapter available; install a software rasterizer (Mesa llvmpipe or lavapipe) to run this test");
+    println!("Adapter: {} ({:?})", headless.adapter_info.name, headless.adapter_info.backend);
+
+    let mut failures = 0;
+    for path in &sketches {
+        let source = fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read hydra source '{}': {}", path, e));
+        for (aspect, color_space, output_transform) in CONFIGS {
+            let spirv = Compiler::new(CompileOptions { target: Target::SpirV, aspect, color_space, output_transform, ..Default::default() })
+                .compile_named(path, &source)
+                .unwrap_or_else(|e| panic!("{}: SPIR-V compile failed: {}", path, e));
+            let direct = Compiler::new(CompileOptions { target: Target::GlslDirect, aspect, color_space, output_transform, ..Default::default() })
+                .compile_named(path, &source)
+                .unwrap_or_else(|e| panic!("{}: direct GLSL compile failed: {}", path, e));
+            let glsl: Vec<(String, ShaderInterface, u32)> = direct.passes.into_iter().map(|p| (p.source, p.interface, p.history))
+                .chain([(direct.source, direct.interface, 0)])
+                .map(|(s, interface, history)| (s.expect("GlslDirect always produces source"), interface, history))
+                .collect();
+            let mut media = load_sources(&spirv.sources, Path::new(path)).unwrap_or_else(|e| panic!("{}", e));
+
+            for time in TIMES {
+                for (source, m) in &mut media {
+                    m.advance(time).unwrap_or_else(|e| panic!("{}: {}", source.path, e));
+                }
+                let a = headless.render_with_sources(spirv_stages(&spirv), &media, WIDTH, HEIGHT, time);
+                // Direct GLSL passes with their own interfaces, built from the IR
+                let stages = glsl.iter().map(|(glsl, interface, history)| Stage {
+                    fragment: wgpu::ShaderSource::Glsl {
+                        shader: glsl.as_str().into(),
+                        stage: wgpu::naga::ShaderStage::Fragment,
+                        defines: &[],
+                    },
+                    interface,
+                    history: *history,
+                }).collect();
+                let b = headless.render_with_sources(stages, &media, WIDTH, HEIGHT, time);
+
+                let mut max_diff = 0u8;
+                let mut over = 0usize;
+                for (pa, pb) in a.chunks(4).zip(b.chunks(4)) {
+                    let diff = pa.iter().zip(pb).map(|(x, y)| x.abs_diff(*y)).max().unwrap_or(0);
+                    max_diff = max_diff.max(diff);
+                    if diff > CHANNEL_TOLERANCE { over += 1; }
+                }
+                let fraction = over as f32 / (WIDTH * HEIGHT) as f32;
+                let ok = fraction <= PIXEL_TOLERANCE;
+                if !ok { failures += 1; }
+                println!(
+                    "{} {} {:?} {:?} {:?} t={:<5} max diff {:>3}, {:>5.2}% pixels over tolerance",
+                    if ok { "ok  " } else { "FAIL" }, path, aspect, color_space, output_transform, time, max_diff, fraction * 100.0
+                );
+            }
+        }
+    }
+
+    assert_eq!(failures, 0, "{} comparison(s) failed", failures);
+}
@@ -0,0 +1,55 @@
+// The host uploads live_lang::globals::Globals byte-for-byte as the `Globals` block, so the block
+// the shaders declare has to match the Rust struct member for member
+
+use std::mem::{offset_of, size_of, size_of_val};
+
+use live_lang::compiler::{CompileOptions, Compiler, Target};
+use live_lang::globals::{Globals, FRAME_WRAP};
+use live_lang::passes::MAX_HISTORY;
+
+// Block member name, Rust field offset and size
+fn rust_layout() -> Vec<(&'static str, usize, usize)> {
+    let g = Globals::default();
+    vec![
+        ("time", offset_of!(Globals, time), size_of_val(&g.time)),
+        ("frame", offset_of!(Globals, frame), size_of_val(&g.frame)),
+        ("resolution", offset_of!(Globals, resolution), size_of_val(&g.resolution)),
+        ("mouse", offset_of!(Globals, mouse), size_of_val(&g.mouse)),
+        ("mouseButtons", offset_of!(Globals, mouse_buttons), size_of_val(&g.mouse_buttons)),
+        ("dt", offset_of!(Globals, dt), size_of_val(&g.dt)),
+        ("fps", offset_of!(Globals, fps), size_of_val(&g.fps)),
+        ("bpm", offset_of!(Globals, bpm), size_of_val(&g.bpm)),
+        ("beat", offset_of!(Globals, beat), size_of_val(&g.beat)),
+        ("custom", offset_of!(Globals, custom), size_of_val(&g.custom)),
+    ]
+}
+
+#[test]
+fn reflected_block_matches_rust_struct() {
+    let shader = Compiler::new(CompileOptions { target: Target::SpirV, ..Default::default() })
+        .compile("osc().out()")
+        .expect("compile failed");
+    let block = shader.interface.uniform_buffer("Globals").expect("no Globals block");
+    assert_eq!((block.set, block.binding), (0, 0));
+    assert_eq!(block.size, size_of::<Globals>());
+
+    let layout = rust_layout();
+    assert_eq!(block.members.len(), layout.len(), "{:?}", block.members);
+    for (name, offset, size) in layout {
+        let member = block.member(name).unwrap_or_else(|| panic!("no `{}` in the Globals block", name));
+        assert_eq!(member.offset as usize, offset, "offset of `{}`", name);
+        assert_eq!(member.size, size, "size of `{}`", name);
+    }
+}
+
+#[test]
+fn frame_wraps_in_step_with_every_history_ring() {
+    for ring in 1..=MAX_HISTORY {
+        assert_eq!(FRAME_WRAP % ring, 0, "ring of {}", ring);
+    }
+    let mut g = Globals { frame: (FRAME_WRAP - 1) as f32, ..Globals::new(64.0, 64.0) };
+    // Still exact as an f32 just before the wrap
+    assert_eq!(g.frame as u32, FRAME_WRAP - 1);
+    g.advance(1.0);
+    assert_eq!(g.frame, 0.0);
+}
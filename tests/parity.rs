@@ -0,0 +1,138 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[path = "../examples/render/mod.rs"]
+mod render;
+
+use live_lang::compiler::{CompileOptions, Compiler, Target};
+use live_lang::post_process::PostProcess;
+use crate::render::headless::Headless;
+use crate::render::media::load_sources;
+use crate::render::passes::spirv_stages;
+use crate::render::ppm::Image;
+
+// Renders each sketch in examples/parity and compares it with the reference render next to it
+// (`sketch.ppm`), written by examples/parity/reference.mjs, a CPU port of the hydra-synth
+// functions. The references are port-derived, not captured from hydra-synth. Needs a wgpu adapter;
+// a software one (llvmpipe, lavapipe) is enough. Set PARITY_OUT to a directory to keep the renders.
+
+const CORPUS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/parity");
+const SIZE: u32 = 256;
+const DEFAULT_TIME: f32 = 1.0;
+// Mean CIE76 delta E allowed per sketch; about 2.3 is a just-noticeable difference
+const DEFAULT_THRESHOLD: f32 = 3.0;
+// Pixels further apart than this are reported as visibly different
+const VISIBLE_DELTA_E: f32 = 10.0;
+
+// Per-sketch overrides from a `// parity: time=2.5 threshold=4` line
+struct Settings {
+    time: f32,
+    threshold: f32,
+}
+
+fn settings(source: &str) -> Settings {
+    let mut s = Settings { time: DEFAULT_TIME, threshold: DEFAULT_THRESHOLD };
+    for line in source.lines() {
+        let Some(rest) = line.trim().strip_prefix("// parity:") else { continue };
+        for field in rest.split_whitespace() {
+            match field.split_once('=') {
+                Some(("time", v)) => s.time = v.parse().unwrap_or_else(|_| panic!("bad parity time '{}'", v)),
+                Some(("threshold", v)) => s.threshold = v.parse().unwrap_or_else(|_| panic!("bad parity threshold '{}'", v)),
+                _ => panic!("unknown parity setting '{}'", field),
+            }
+        }
+    }
+    s
+}
+
+// sRGB (D65) to CIE L*a*b*
+fn lab(rgb: &[u8]) -> [f32; 3] {
+    let linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    };
+    let (r, g, b) = (linear(rgb[0]), linear(rgb[1]), linear(rgb[2]));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f32| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+// (mean delta E, fraction of pixels over VISIBLE_DELTA_E)
+fn compare(a: &Image, b: &Image) -> (f32, f32) {
+    let mut sum = 0.0;
+    let mut visible = 0usize;
+    let pixels = a.rgb.len() / 3;
+    for (pa, pb) in a.rgb.chunks(3).zip(b.rgb.chunks(3)) {
+        let (la, lb) = (lab(pa), lab(pb));
+        let delta = ((la[0] - lb[0]).powi(2) + (la[1] - lb[1]).powi(2) + (la[2] - lb[2]).powi(2)).sqrt();
+        sum += delta;
+        if delta > VISIBLE_DELTA_E { visible += 1; }
+    }
+    (sum / pixels as f32, visible as f32 / pixels as f32)
+}
+
+#[test]
+fn sketches_match_port_derived_references() {
+    let _ = env_logger::try_init();
+
+    let mut sketches: Vec<PathBuf> = fs::read_dir(CORPUS_DIR)
+        .expect("failed to read parity corpus")
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "js"))
+        .collect();
+    sketches.sort();
+    assert!(!sketches.is_empty(), "no sketches in {}", CORPUS_DIR);
+    let out_dir = env::var_os("PARITY_OUT").map(PathBuf::from);
+    if let Some(dir) = &out_dir {
+        fs::create_dir_all(dir).unwrap_or_else(|e| panic!("Failed to create '{}': {}", dir.display(), e));
+    }
+
+    let headless = pollster::block_on(Headless::new())
+        .expect("no wgpu adapter available; install a software rasterizer (Mesa llvmpipe or lavapipe) to run this test");
+    println!("Adapter: {} ({:?})", headless.adapter_info.name, headless.adapter_info.backend);
+
+    // Hydra draws the chain as-is: no tone mapping, stretched coordinates
+    let compiler = Compiler::new(CompileOptions { target: Target::SpirV, post_process: PostProcess::none(), ..Default::default() });
+
+    let mut failures = 0;
+    for path in &sketches {
+        let source = fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read hydra source '{}': {}", path.display(), e));
+        let settings = settings(&source);
+        let shader = compiler.compile_named(&path.display().to_string(), &source)
+            .unwrap_or_else(|e| panic!("{}: compile failed: {}", path.display(), e));
+        let mut media = load_sources(&shader.sources, path).unwrap_or_else(|e| panic!("{}", e));
+        for (source, m) in &mut media {
+            m.advance(settings.time).unwrap_or_else(|e| panic!("{}: {}", source.path, e));
+        }
+        let rgba = headless.render_with_sources(spirv_stages(&shader), &media, SIZE, SIZE, settings.time);
+        // live-lang's st has y = 0 at the top, Hydra's at the bottom; compare in Hydra's orientation
+        let image = Image::from_rgba(SIZE, SIZE, &rgba).flipped();
+        if let Some(dir) = &out_dir {
+            let name = path.file_stem().unwrap_or_default();
+            image.write(&dir.join(Path::new(name).with_extension("ppm"))).unwrap_or_else(|e| panic!("{}", e));
+        }
+
+        let reference_path = path.with_extension("ppm");
+        let reference = Image::read(&reference_path)
+            .unwrap_or_else(|e| panic!("{}: no usable reference render at {}: {}", path.display(), reference_path.display(), e));
+        assert!(
+            reference.width == SIZE && reference.height == SIZE,
+            "{}: reference is {}x{}, expected {}x{}", path.display(), reference.width, reference.height, SIZE, SIZE
+        );
+
+        let (mean, visible) = compare(&image, &reference);
+        let ok = mean <= settings.threshold;
+        if !ok { failures += 1; }
+        println!(
+            "{} {} t={:<5} mean dE {:>6.2} (limit {}), {:>5.1}% pixels visibly different",
+            if ok { "ok  " } else { "FAIL" }, path.display(), settings.time, mean, settings.threshold, visible * 100.0
+        );
+    }
+
+    assert_eq!(failures, 0, "{} sketch(es) over their limit", failures);
+}